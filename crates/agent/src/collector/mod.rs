@@ -107,6 +107,7 @@ impl SystemCollector {
             hardware: hw_info,
             peripherals: self.get_peripherals(),
             software: self.get_software(),
+            tags: Vec::new(),
         }
     }
 }
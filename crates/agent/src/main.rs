@@ -4,8 +4,9 @@ mod sca;
 
 use uuid::Uuid;
 use collector::SystemCollector;
-use sca::ScaEngine;
+use sca::{ScaEngine, HostContext};
 use std::fs;
+use std::sync::Arc;
 
 fn get_stable_agent_id() -> Uuid {
     if let Ok(c) = fs::read_to_string(".agent_id") {
@@ -25,14 +26,37 @@ async fn main() {
     tracing::info!("ðŸ†” Agent ID: {}", agent_id);
 
     let mut collector = SystemCollector::new();
-    let host_info = collector.collect();
+    let mut host_info = collector.collect();
+
+    // Tags do agente enviadas no handshake (AGENT_TAGS=prod,dmz)
+    host_info.tags = std::env::var("AGENT_TAGS").unwrap_or_default()
+        .split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
     
-    let sca = ScaEngine::new("assets/cis_windows_basic.yaml");
-    let report = sca.run_scan();
+    // Limite de regras SCA em paralelo (SCA_CONCURRENCY, padrao 8)
+    let concurrency = std::env::var("SCA_CONCURRENCY").ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(sca::DEFAULT_CONCURRENCY);
+
+    // Roles do agente para as pre-condicoes das regras (AGENT_ROLES=web,db)
+    let roles: Vec<String> = std::env::var("AGENT_ROLES").unwrap_or_default()
+        .split(',').map(|r| r.trim().to_string()).filter(|r| !r.is_empty()).collect();
+
+    // Politica conforme o SO (SCA_POLICY sobrescreve)
+    let default_policy = if cfg!(target_os = "windows") { "assets/cis_windows_basic.yaml" } else { "assets/cis_linux_baseline.yaml" };
+    let policy_path = std::env::var("SCA_POLICY").unwrap_or_else(|_| default_policy.to_string());
+
+    // SCA_ROOT: raiz do sistema avaliado (ex: /host quando o agente roda em container)
+    let mut context = HostContext::from_host(&host_info, roles);
+    context.root = std::env::var("SCA_ROOT").ok().filter(|r| !r.is_empty()).map(Into::into);
+
+    let sca = Arc::new(ScaEngine::new(&policy_path)
+        .with_concurrency(concurrency)
+        .with_context(context));
+    let report = sca.run_scan().await;
 
     if report.is_some() {
         tracing::info!("ðŸ“Š Relatorio SCA gerado.");
     }
 
-    net::start_agent_loop(agent_id, host_info, report).await;
+    net::start_agent_loop(agent_id, host_info, sca, report).await;
 }
@@ -1,21 +1,31 @@
 ﻿use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as WsMessage};
 use futures::{SinkExt, StreamExt};
 use url::Url;
-use shared::protocol::{Message, CommandType};
+use shared::protocol::{Message, CommandType, CommandValidity};
 use shared::models::HostInfo;
-use shared::models::sca::ComplianceReport;
+use shared::models::sca::{ComplianceReport, PolicyAssignment, RemediationRequest};
 use shared::crypto;
 use uuid::Uuid;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::mpsc;
 use tokio::time::sleep;
-use std::process::Command;
+use crate::sca::ScaEngine;
+
+mod replay;
+use replay::ReplayGuard;
+
+#[cfg(test)]
+mod tests;
 
 const ADMIN_PUBLIC_KEY: &str = "4d6e4d06c24a64de1044ff65403bdbe4ff5cf70bc48c062117a90e47b9f03c7b"; 
 
-pub async fn start_agent_loop(agent_id: Uuid, host_info: HostInfo, sca_report: Option<ComplianceReport>) {
+pub async fn start_agent_loop(agent_id: Uuid, host_info: HostInfo, sca: Arc<ScaEngine>, sca_report: Option<ComplianceReport>) {
     // URL DE PRODUCAO (Coolify)
     // Nota: Se configurar SSL no Coolify depois, mude para wss://
     let url = Url::parse("ws://uk4gco4wgco84s0gco0w4co8.72.60.141.205.sslip.io/ws").unwrap();
+    // Sobrevive as reconexoes: um comando aceito nao pode ser reenviado numa sessao nova
+    let mut replay = ReplayGuard::default();
 
     loop {
         tracing::info!("Tentando conectar ao servidor em {}...", url);
@@ -37,31 +47,61 @@ pub async fn start_agent_loop(agent_id: Uuid, host_info: HostInfo, sca_report: O
                     let msg = Message::ScaReport {
                         agent_id,
                         report: report.clone(),
+                        assigned: false,
                     };
                     if let Err(e) = write.send(WsMessage::Text(serde_json::to_string(&msg).unwrap())).await {
                         tracing::error!("Erro ao enviar SCA report: {}", e);
                     }
                 }
 
+                // Mensagens produzidas por tarefas em background (ex: remediacao)
+                let (out_tx, mut out_rx) = mpsc::unbounded_channel::<Message>();
+                // Pedido do servidor (desligamento) para aguardar antes de reconectar
+                let mut reconnect_after = None;
+
                 loop {
                     tokio::select! {
                         _ = sleep(Duration::from_secs(10)) => {
                             let hb = Message::Heartbeat { agent_id, timestamp: chrono::Utc::now() };
                             if let Err(_) = write.send(WsMessage::Text(serde_json::to_string(&hb).unwrap())).await { break; }
                         }
+                        Some(out) = out_rx.recv() => {
+                            if write.send(WsMessage::Text(serde_json::to_string(&out).unwrap())).await.is_err() { break; }
+                        }
                         msg = read.next() => {
                             match msg {
-                                Some(Ok(WsMessage::Text(text))) => {
-                                    if let Ok(Message::Command { id, cmd_type, args, signature }) = serde_json::from_str(&text) {
-                                        tracing::info!("Comando recebido (Logica completa no script Fase 5)");
+                                Some(Ok(WsMessage::Text(text))) => match serde_json::from_str(&text) {
+                                    Ok(Message::Command { id, cmd_type, args, signature }) => {
+                                        let args = args.unwrap_or_default();
+                                        match authorize(&args, &signature, &mut replay) {
+                                            Ok(()) => handle_command(agent_id, id, cmd_type, args, sca.clone(), out_tx.clone()),
+                                            Err(e) => {
+                                                tracing::error!("Comando {} rejeitado: {}", id, e);
+                                                let _ = out_tx.send(Message::CommandResult {
+                                                    cmd_id: id,
+                                                    status: "REJECTED".to_string(),
+                                                    stdout: String::new(),
+                                                    stderr: e,
+                                                });
+                                            }
+                                        }
+                                    }
+                                    Ok(Message::Reconnect { after_secs, reason }) => {
+                                        tracing::info!("Servidor pediu reconexao em {}s: {}", after_secs, reason);
+                                        reconnect_after = Some(Duration::from_secs(after_secs));
+                                        break;
                                     }
-                                }
+                                    _ => {}
+                                },
                                 Some(Err(_)) | None => break, 
                                 _ => {}
                             }
                         }
                     }
                 }
+                if let Some(delay) = reconnect_after {
+                    sleep(delay).await;
+                }
             }
             Err(e) => {
                 tracing::error!("Falha conexao: {}. Retry 5s...", e);
@@ -70,3 +110,76 @@ pub async fn start_agent_loop(agent_id: Uuid, host_info: HostInfo, sca_report: O
         }
     }
 }
+
+/// Assinatura do admin e validade (nonce/expiracao) assinadas junto com o `args`
+fn authorize(args: &str, signature: &str, replay: &mut ReplayGuard) -> Result<(), String> {
+    crypto::verify_signature(ADMIN_PUBLIC_KEY, args, signature).map_err(|e| e.to_string())?;
+    let validity = serde_json::from_str::<CommandValidity>(args)
+        .map_err(|e| format!("comando sem validade assinada: {}", e))?;
+    replay.check(&validity, chrono::Utc::now())
+}
+
+/// Despacha um comando ja autorizado para o executor correspondente
+fn handle_command(
+    agent_id: Uuid,
+    cmd_id: Uuid,
+    cmd_type: CommandType,
+    args: String,
+    sca: Arc<ScaEngine>,
+    out_tx: mpsc::UnboundedSender<Message>,
+) {
+
+    match cmd_type {
+        CommandType::Remediate => {
+            tokio::spawn(async move {
+                let request = match serde_json::from_str::<RemediationRequest>(&args) {
+                    Ok(r) if r.agent_id == agent_id => r,
+                    Ok(_) => return reject(&out_tx, cmd_id, "Comando destinado a outro agente".to_string()),
+                    Err(e) => return reject(&out_tx, cmd_id, format!("Pedido de remediacao invalido: {}", e)),
+                };
+
+                match sca.remediate(&request).await {
+                    Ok(results) => {
+                        let _ = out_tx.send(Message::RemediationReport {
+                            agent_id,
+                            cmd_id,
+                            policy_id: request.policy_id.clone(),
+                            dry_run: request.dry_run,
+                            results,
+                        });
+                        // Apos aplicar, envia uma varredura nova para fechar os achados no painel
+                        if !request.dry_run {
+                            if let Some(report) = sca.run_scan().await {
+                                let _ = out_tx.send(Message::ScaReport { agent_id, report, assigned: false });
+                            }
+                        }
+                    }
+                    Err(e) => reject(&out_tx, cmd_id, e.to_string()),
+                }
+            });
+        }
+        CommandType::RunPolicy => {
+            tokio::spawn(async move {
+                let assignment = match serde_json::from_str::<PolicyAssignment>(&args) {
+                    Ok(a) if a.agent_id == agent_id => a,
+                    Ok(_) => return reject(&out_tx, cmd_id, "Comando destinado a outro agente".to_string()),
+                    Err(e) => return reject(&out_tx, cmd_id, format!("Politica atribuida invalida: {}", e)),
+                };
+                tracing::info!("Politica {} atribuida pelo servidor", assignment.policy.id);
+                let report = sca.scan(assignment.policy).await;
+                let _ = out_tx.send(Message::ScaReport { agent_id, report, assigned: true });
+            });
+        }
+        other => tracing::warn!("Comando {:?} ainda nao suportado pelo agente", other),
+    }
+}
+
+fn reject(out_tx: &mpsc::UnboundedSender<Message>, cmd_id: Uuid, reason: String) {
+    tracing::error!("Comando {} falhou: {}", cmd_id, reason);
+    let _ = out_tx.send(Message::CommandResult {
+        cmd_id,
+        status: "ERROR".to_string(),
+        stdout: String::new(),
+        stderr: reason,
+    });
+}
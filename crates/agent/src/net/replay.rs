@@ -0,0 +1,39 @@
+﻿use chrono::{DateTime, Duration, Utc};
+use shared::protocol::CommandValidity;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Tolerancia para diferenca de relogio entre servidor e agente
+const CLOCK_SKEW_SECS: i64 = 60;
+/// Maior validade aceita: um comando assinado nao pode valer indefinidamente
+const MAX_TTL_SECS: i64 = 3600;
+
+/// Nonces de comandos ja aceitos, guardados ate a expiracao de cada um.
+/// Depois disso a propria validade recusa o replay, entao um restart do agente
+/// so reabre a janela de comandos ainda nao expirados.
+#[derive(Default)]
+pub struct ReplayGuard {
+    seen: HashMap<Uuid, DateTime<Utc>>,
+}
+
+impl ReplayGuard {
+    /// Aceita cada nonce uma unica vez, dentro da validade assinada
+    pub fn check(&mut self, validity: &CommandValidity, now: DateTime<Utc>) -> Result<(), String> {
+        let skew = Duration::seconds(CLOCK_SKEW_SECS);
+        self.seen.retain(|_, expires_at| *expires_at + skew >= now);
+
+        if validity.issued_at > now + skew {
+            return Err(format!("comando emitido no futuro ({})", validity.issued_at));
+        }
+        if validity.expires_at + skew < now {
+            return Err(format!("comando expirado em {}", validity.expires_at));
+        }
+        if validity.expires_at - validity.issued_at > Duration::seconds(MAX_TTL_SECS) {
+            return Err("validade do comando acima do permitido".to_string());
+        }
+        if self.seen.insert(validity.nonce, validity.expires_at).is_some() {
+            return Err(format!("nonce {} ja utilizado", validity.nonce));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+﻿use chrono::{Duration, Utc};
+use shared::protocol::CommandValidity;
+use uuid::Uuid;
+use super::replay::ReplayGuard;
+
+fn validity(issued_offset_secs: i64, ttl_secs: i64) -> CommandValidity {
+    let issued_at = Utc::now() + Duration::seconds(issued_offset_secs);
+    CommandValidity { nonce: Uuid::new_v4(), issued_at, expires_at: issued_at + Duration::seconds(ttl_secs) }
+}
+
+#[test]
+fn fresh_command_is_accepted_once() {
+    let mut guard = ReplayGuard::default();
+    let v = validity(0, 300);
+    assert!(guard.check(&v, Utc::now()).is_ok());
+    let err = guard.check(&v, Utc::now()).unwrap_err();
+    assert!(err.contains("ja utilizado"), "{}", err);
+    // Outro nonce com a mesma validade passa
+    assert!(guard.check(&CommandValidity { nonce: Uuid::new_v4(), ..v }, Utc::now()).is_ok());
+}
+
+#[test]
+fn expired_and_future_commands_are_rejected() {
+    let mut guard = ReplayGuard::default();
+    assert!(guard.check(&validity(-600, 300), Utc::now()).unwrap_err().contains("expirado"));
+    assert!(guard.check(&validity(600, 300), Utc::now()).unwrap_err().contains("futuro"));
+    // Dentro da tolerancia de relogio
+    assert!(guard.check(&validity(-330, 300), Utc::now()).is_ok());
+    assert!(guard.check(&validity(30, 300), Utc::now()).is_ok());
+}
+
+#[test]
+fn long_lived_commands_are_rejected() {
+    let mut guard = ReplayGuard::default();
+    assert!(guard.check(&validity(0, 7 * 24 * 3600), Utc::now()).unwrap_err().contains("acima do permitido"));
+}
+
+#[test]
+fn nonces_are_forgotten_only_after_expiring() {
+    let mut guard = ReplayGuard::default();
+    let v = validity(0, 300);
+    assert!(guard.check(&v, Utc::now()).is_ok());
+
+    // Ainda valido: continua lembrado
+    let later = Utc::now() + Duration::seconds(200);
+    assert!(guard.check(&v, later).unwrap_err().contains("ja utilizado"));
+
+    // Depois de expirar, o nonce sai da memoria e a expiracao recusa o replay
+    let after = Utc::now() + Duration::seconds(1000);
+    assert!(guard.check(&v, after).unwrap_err().contains("expirado"));
+}
@@ -0,0 +1,86 @@
+﻿use shared::models::sca::Applicability;
+use shared::models::HostInfo;
+use shared::software::{version, Ecosystem};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Dados do host usados para avaliar as pre-condicoes das politicas e regras
+#[derive(Debug, Clone)]
+pub struct HostContext {
+    pub os_family: String,
+    pub os_version: String,
+    pub packages: HashSet<String>,  // Nomes em minusculo
+    pub roles: Vec<String>,
+    pub root: Option<PathBuf>,      // Raiz alternativa para verificar arquivos (testes/containers)
+}
+
+impl Default for HostContext {
+    fn default() -> Self {
+        Self {
+            os_family: std::env::consts::OS.to_string(),
+            os_version: String::new(),
+            packages: HashSet::new(),
+            roles: Vec::new(),
+            root: None,
+        }
+    }
+}
+
+impl HostContext {
+    pub fn from_host(host: &HostInfo, roles: Vec<String>) -> Self {
+        Self {
+            os_version: host.os_version.clone(),
+            packages: host.software.iter().map(|s| s.name.to_lowercase()).collect(),
+            roles,
+            ..Self::default()
+        }
+    }
+
+    /// Resolve um caminho da politica considerando a raiz alternativa
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        match &self.root {
+            Some(root) => root.join(path.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        }
+    }
+}
+
+/// Retorna `Some(motivo)` quando as pre-condicoes NAO sao satisfeitas pelo host
+pub fn not_applicable_reason(cond: &Applicability, ctx: &HostContext) -> Option<String> {
+    if !cond.os_family.is_empty() && !cond.os_family.iter().any(|f| f.eq_ignore_ascii_case(&ctx.os_family)) {
+        return Some(format!("SO {} fora de {:?}", ctx.os_family, cond.os_family));
+    }
+
+    if cond.min_os_version.is_some() || cond.max_os_version.is_some() {
+        if ctx.os_version.is_empty() {
+            return Some("Versao do SO desconhecida".to_string());
+        }
+        // Mesmas regras do servidor: builds do Windows numericos, demais por segmentos
+        let ecosystem = Ecosystem::from_os(&ctx.os_family);
+        if let Some(min) = &cond.min_os_version {
+            if version::compare(&ctx.os_version, min, ecosystem) == Ordering::Less {
+                return Some(format!("Versao do SO {} menor que {}", ctx.os_version, min));
+            }
+        }
+        if let Some(max) = &cond.max_os_version {
+            if version::compare(&ctx.os_version, max, ecosystem) == Ordering::Greater {
+                return Some(format!("Versao do SO {} maior que {}", ctx.os_version, max));
+            }
+        }
+    }
+
+    if let Some(pkg) = cond.packages.iter().find(|p| !ctx.packages.contains(&p.to_lowercase())) {
+        return Some(format!("Pacote {} nao instalado", pkg));
+    }
+
+    if let Some(file) = cond.files.iter().find(|f| !ctx.resolve(f).exists()) {
+        return Some(format!("Arquivo {} nao existe", file));
+    }
+
+    if !cond.roles.is_empty() && !cond.roles.iter().any(|r| ctx.roles.contains(r)) {
+        return Some(format!("Agente sem as roles {:?}", cond.roles));
+    }
+
+    None
+}
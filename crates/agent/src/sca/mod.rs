@@ -1,91 +1,236 @@
-﻿use shared::models::sca::{Policy, ComplianceReport, CheckResult};
-use std::process::Command;
+﻿use shared::models::sca::{Policy, Rule, ComplianceReport, CheckResult, RemediationRequest, RemediationResult, RemediationScope};
+use futures::stream::{self, StreamExt};
+use tokio::process::Command;
+use std::process::Output;
+use std::time::Instant;
 use std::fs;
 
+mod applicability;
+pub use applicability::HostContext;
+use applicability::not_applicable_reason;
+
+// As regras da politica Linux rodam em sh e as raizes falsas usam permissoes/symlinks unix
+#[cfg(all(test, unix))]
+mod tests;
+
+/// Quantidade padrao de regras executadas em paralelo
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
 pub struct ScaEngine {
     policy_path: String,
+    concurrency: usize,
+    context: HostContext,
 }
 
 impl ScaEngine {
     pub fn new(path: &str) -> Self {
-        Self { policy_path: path.to_string() }
+        Self { policy_path: path.to_string(), concurrency: DEFAULT_CONCURRENCY, context: HostContext::default() }
+    }
+
+    /// Define o limite de regras executando ao mesmo tempo (minimo 1)
+    pub fn with_concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+
+    /// Define os dados do host usados nas pre-condicoes (`applies_to`)
+    pub fn with_context(mut self, context: HostContext) -> Self {
+        self.context = context;
+        self
     }
 
-    pub fn run_scan(&self) -> Option<ComplianceReport> {
+    /// Le e interpreta o YAML da politica configurada
+    pub fn load_policy(&self) -> anyhow::Result<Policy> {
+        let content = fs::read_to_string(&self.policy_path)
+            .map_err(|e| anyhow::anyhow!("Falha ao ler politica YAML: {}", e))?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Erro de parse no YAML: {}", e))
+    }
+
+    pub async fn run_scan(&self) -> Option<ComplianceReport> {
         tracing::info!("ðŸ›¡ï¸  Iniciando varredura de Compliance SCA...");
 
         // 1. Carregar Politica
-        let content = match fs::read_to_string(&self.policy_path) {
-            Ok(c) => c,
+        let policy = match self.load_policy() {
+            Ok(p) => p,
             Err(e) => {
-                tracing::error!("Falha ao ler politica YAML: {}", e);
+                tracing::error!("{}", e);
                 return None;
             }
         };
+        Some(self.scan(policy).await)
+    }
 
-        let policy: Policy = match serde_yaml::from_str(&content) {
-            Ok(p) => p,
-            Err(e) => {
-                tracing::error!("Erro de parse no YAML: {}", e);
-                return None;
-            }
+    /// Avalia uma politica ja carregada (local ou atribuida pelo servidor via grupo)
+    pub async fn scan(&self, policy: Policy) -> ComplianceReport {
+        tracing::info!("ðŸ“‹ Politica carregada: {} (concorrencia: {})", policy.name, self.concurrency);
+
+        // 2. Pre-condicoes da politica: se nao se aplica, todas as regras ficam NOT_APPLICABLE
+        let policy_na = self.policy_reason(&policy);
+        if let Some(reason) = &policy_na {
+            tracing::info!("Politica {} nao se aplica a este host: {}", policy.id, reason);
+        }
+
+        // 3. Executar Regras em paralelo (buffered preserva a ordem da politica)
+        let checks: Vec<_> = policy.rules.iter()
+            .map(|rule| run_rule(rule, &self.context, policy_na.as_deref()))
+            .collect();
+        let results: Vec<CheckResult> = stream::iter(checks)
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        let report = ComplianceReport::compute(policy.id, results);
+
+        match (report.score, report.raw_score) {
+            (Some(score), Some(raw)) => tracing::info!("ðŸ Varredura concluida. Score: {}% ({}/{} checks, bruto {}%)", score, report.passed_checks, report.total_checks, raw),
+            _ => tracing::info!("ðŸ Varredura concluida. Nenhuma regra aplicavel ({} N/A)", report.not_applicable),
+        }
+
+        report
+    }
+
+    /// Aplica a remediacao das regras que falharam e verifica novamente cada uma.
+    /// Em `dry_run` apenas verifica e informa o que seria executado.
+    pub async fn remediate(&self, request: &RemediationRequest) -> anyhow::Result<Vec<RemediationResult>> {
+        let policy = self.load_policy()?;
+        if policy.id != request.policy_id {
+            anyhow::bail!("Politica {} nao carregada neste agente (atual: {})", request.policy_id, policy.id);
+        }
+
+        let rules: Vec<&Rule> = match &request.scope {
+            RemediationScope::Policy => policy.rules.iter().collect(),
+            RemediationScope::Rule(id) => policy.rules.iter().filter(|r| r.id == *id).collect(),
         };
+        if rules.is_empty() {
+            anyhow::bail!("Nenhuma regra encontrada para o escopo {:?}", request.scope);
+        }
 
-        tracing::info!("ðŸ“‹ Politica carregada: {}", policy.name);
+        tracing::info!("Remediacao: {} regra(s) da politica {} (dry-run: {})", rules.len(), policy.id, request.dry_run);
 
+        // Sequencial: remediacoes alteram o sistema e podem depender umas das outras
+        let policy_na = self.policy_reason(&policy);
         let mut results = Vec::new();
-        let mut passed_count = 0;
-
-        // 2. Executar Regras
-        for rule in &policy.rules {
-            tracing::info!("   Verificando Regra {}: {}", rule.id, rule.title);
-            
-            // Executa comando (PowerShell no Windows, sh no Linux)
-            let output = if cfg!(target_os = "windows") {
-                // Fix: Usar 'powershell' explicitamente e garantir UTF8 no output
-                Command::new("powershell")
-                    .args(&["-NoProfile", "-Command", &format!("[Console]::OutputEncoding = [System.Text.Encoding]::UTF8; {}", rule.command)])
-                    .output()
-            } else {
-                Command::new("sh")
-                    .arg("-c")
-                    .arg(&rule.command)
-                    .output()
-            };
-
-            let (status, output_str) = match output {
-                Ok(o) => {
-                    let out_txt = String::from_utf8_lossy(&o.stdout).trim().to_string();
-                    // Logica simples de "Contains" (Pode evoluir para Regex)
-                    if out_txt.contains(&rule.expect) {
-                        passed_count += 1;
-                        ("PASS", out_txt)
-                    } else {
-                        ("FAIL", out_txt)
-                    }
-                },
-                Err(e) => ("ERROR", e.to_string())
-            };
-
-            results.push(CheckResult {
-                rule_id: rule.id,
-                title: rule.title.clone(),
-                status: status.to_string(),
-                output: output_str,
-            });
+        for rule in rules {
+            results.push(remediate_rule(rule, &self.context, policy_na.as_deref(), request.dry_run).await);
         }
+        Ok(results)
+    }
+
+    fn policy_reason(&self, policy: &Policy) -> Option<String> {
+        policy.applies_to.as_ref().and_then(|c| not_applicable_reason(c, &self.context))
+    }
+}
+
+/// Executa uma regra isolada e mede o tempo gasto.
+/// Pre-condicoes nao satisfeitas (da politica ou da regra) resultam em NOT_APPLICABLE sem executar o comando.
+async fn run_rule(rule: &Rule, ctx: &HostContext, policy_na: Option<&str>) -> CheckResult {
+    let reason = policy_na.map(str::to_string)
+        .or_else(|| rule.applies_to.as_ref().and_then(|c| not_applicable_reason(c, ctx)));
+    if let Some(reason) = reason {
+        tracing::info!("   Regra {} nao aplicavel: {}", rule.id, reason);
+        return check_result(rule, "NOT_APPLICABLE", reason, 0);
+    }
+
+    tracing::info!("   Verificando Regra {}: {}", rule.id, rule.title);
+    let started = Instant::now();
 
-        let total = policy.rules.len() as u32;
-        let score = if total > 0 { (passed_count as f32 / total as f32 * 100.0) as u32 } else { 0 };
+    let output = exec(&rule.command, ctx).await;
 
-        tracing::info!("ðŸ Varredura concluida. Score: {}% ({}/{} checks)", score, passed_count, total);
+    let (status, output_str) = match output {
+        Ok(o) => {
+            let out_txt = String::from_utf8_lossy(&o.stdout).trim().to_string();
+            // Logica simples de "Contains" (Pode evoluir para Regex)
+            if out_txt.contains(&rule.expect) {
+                ("PASS", out_txt)
+            } else {
+                ("FAIL", out_txt)
+            }
+        },
+        Err(e) => ("ERROR", e.to_string())
+    };
+
+    check_result(rule, status, output_str, started.elapsed().as_millis() as u64)
+}
 
-        Some(ComplianceReport {
-            policy_id: policy.id,
-            score,
-            total_checks: total,
-            passed_checks: passed_count as u32,
-            results,
-        })
+fn check_result(rule: &Rule, status: &str, output: String, duration_ms: u64) -> CheckResult {
+    CheckResult {
+        rule_id: rule.id,
+        title: rule.title.clone(),
+        status: status.to_string(),
+        output,
+        duration_ms,
+        severity: rule.severity,
+        weight: rule.effective_weight(),
     }
 }
+
+/// Verifica a regra, aplica a remediacao se necessario e verifica de novo
+async fn remediate_rule(rule: &Rule, ctx: &HostContext, policy_na: Option<&str>, dry_run: bool) -> RemediationResult {
+    let before = run_rule(rule, ctx, policy_na).await;
+    let mut result = RemediationResult {
+        rule_id: rule.id,
+        title: rule.title.clone(),
+        action: String::new(),
+        before_status: before.status.clone(),
+        after_status: None,
+        remediation: rule.remediation.clone(),
+        output: String::new(),
+    };
+
+    if before.status == "PASS" || before.status == "NOT_APPLICABLE" {
+        result.action = "SKIPPED".to_string();
+        return result;
+    }
+
+    let fix = match &rule.remediation {
+        Some(fix) => fix,
+        None => {
+            result.action = "NO_REMEDIATION".to_string();
+            return result;
+        }
+    };
+
+    if dry_run {
+        result.action = "DRY_RUN".to_string();
+        return result;
+    }
+
+    tracing::info!("   Remediando Regra {}: {}", rule.id, rule.title);
+    match exec(fix, ctx).await {
+        Ok(o) => {
+            let stdout = String::from_utf8_lossy(&o.stdout);
+            let stderr = String::from_utf8_lossy(&o.stderr);
+            result.output = format!("{}{}", stdout, stderr).trim().to_string();
+            result.action = if o.status.success() { "APPLIED" } else { "ERROR" }.to_string();
+        }
+        Err(e) => {
+            result.action = "ERROR".to_string();
+            result.output = e.to_string();
+        }
+    }
+
+    result.after_status = Some(run_rule(rule, ctx, policy_na).await.status);
+    result
+}
+
+/// Executa um comando no shell nativo (PowerShell no Windows, sh no Linux).
+/// A raiz alternativa do contexto fica disponivel para o comando em `SCA_ROOT` (vazia = "/").
+async fn exec(command: &str, ctx: &HostContext) -> std::io::Result<Output> {
+    let mut cmd = if cfg!(target_os = "windows") {
+        // Fix: Usar 'powershell' explicitamente e garantir UTF8 no output
+        let mut c = Command::new("powershell");
+        c.args(["-NoProfile", "-Command", &format!("[Console]::OutputEncoding = [System.Text.Encoding]::UTF8; {}", command)]);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    };
+
+    let root = ctx.root.as_ref().map(|r| r.to_string_lossy().trim_end_matches('/').to_string()).unwrap_or_default();
+    cmd.env("SCA_ROOT", root)
+        .kill_on_drop(true)
+        .output()
+        .await
+}
@@ -0,0 +1,14 @@
+-- Comandos de remediacao enviados aos agentes e o resultado antes/depois por regra
+CREATE TABLE IF NOT EXISTS remediation_actions (
+    id UUID PRIMARY KEY,
+    agent_id UUID REFERENCES agents(id),
+    policy_id VARCHAR(100) NOT NULL,
+    rule_id INT,                -- NULL = politica inteira
+    dry_run BOOLEAN NOT NULL DEFAULT FALSE,
+    status VARCHAR(20) NOT NULL DEFAULT 'SENT',
+    results JSONB,              -- Array de RemediationResult
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_remediation_agent ON remediation_actions(agent_id, requested_at DESC);
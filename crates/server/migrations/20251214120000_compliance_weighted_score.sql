@@ -0,0 +1,2 @@
+-- Score ponderado passa a ocupar a coluna score; raw_score guarda o percentual simples de aprovacao
+ALTER TABLE compliance_scores ADD COLUMN IF NOT EXISTS raw_score INT;
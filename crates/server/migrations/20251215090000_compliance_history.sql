@@ -0,0 +1,34 @@
+-- Historico append-only de varreduras SCA (compliance_scores continua sendo o ultimo estado)
+CREATE TABLE IF NOT EXISTS compliance_scans (
+    id BIGSERIAL PRIMARY KEY,
+    agent_id UUID NOT NULL REFERENCES agents(id),
+    policy_id VARCHAR(100) NOT NULL,
+    score INT NOT NULL,
+    raw_score INT NOT NULL,
+    total_checks INT NOT NULL,
+    passed_checks INT NOT NULL,
+    not_applicable INT NOT NULL DEFAULT 0,
+    scanned_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_compliance_scans_agent ON compliance_scans(agent_id, scanned_at);
+CREATE INDEX IF NOT EXISTS idx_compliance_scans_policy ON compliance_scans(policy_id, scanned_at);
+
+-- Resultado de cada regra em cada varredura
+CREATE TABLE IF NOT EXISTS compliance_rule_results (
+    id BIGSERIAL PRIMARY KEY,
+    scan_id BIGINT NOT NULL REFERENCES compliance_scans(id) ON DELETE CASCADE,
+    agent_id UUID NOT NULL REFERENCES agents(id),
+    policy_id VARCHAR(100) NOT NULL,
+    rule_id INT NOT NULL,
+    title TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    severity VARCHAR(10) NOT NULL,
+    weight INT NOT NULL,
+    output TEXT,
+    duration_ms BIGINT,
+    scanned_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_rule_results_scan ON compliance_rule_results(scan_id);
+CREATE INDEX IF NOT EXISTS idx_rule_results_agent_rule ON compliance_rule_results(agent_id, rule_id, scanned_at);
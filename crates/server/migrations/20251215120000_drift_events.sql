@@ -0,0 +1,19 @@
+-- Mudancas de status de regras entre varreduras consecutivas do mesmo agente/politica
+CREATE TABLE IF NOT EXISTS drift_events (
+    id BIGSERIAL PRIMARY KEY,
+    agent_id UUID NOT NULL REFERENCES agents(id),
+    policy_id VARCHAR(100) NOT NULL,
+    rule_id INT NOT NULL,
+    title TEXT NOT NULL,
+    severity VARCHAR(10) NOT NULL,
+    before_status VARCHAR(20) NOT NULL,
+    after_status VARCHAR(20) NOT NULL,
+    kind VARCHAR(20) NOT NULL,          -- REGRESSION, IMPROVEMENT ou CHANGE
+    output_diff TEXT,
+    previous_scan_id BIGINT NOT NULL REFERENCES compliance_scans(id) ON DELETE CASCADE,
+    scan_id BIGINT NOT NULL REFERENCES compliance_scans(id) ON DELETE CASCADE,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_drift_detected ON drift_events(detected_at DESC);
+CREATE INDEX IF NOT EXISTS idx_drift_agent ON drift_events(agent_id, detected_at DESC);
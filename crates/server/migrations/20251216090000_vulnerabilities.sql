@@ -0,0 +1,42 @@
+-- Base offline de vulnerabilidades (NVD JSON 2.0 / OSV) importada do disco
+CREATE TABLE IF NOT EXISTS vulnerabilities (
+    id VARCHAR(64) PRIMARY KEY,         -- CVE-2024-1234, GHSA-..., DSA-...
+    source VARCHAR(10) NOT NULL,        -- NVD ou OSV
+    aliases TEXT[] NOT NULL DEFAULT '{}',
+    summary TEXT,
+    severity VARCHAR(10),               -- critical, high, medium, low (derivada do CVSS)
+    cvss_score REAL,
+    cvss_vector TEXT,
+    published_at TIMESTAMPTZ,
+    imported_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Produtos/pacotes afetados: CPE (ecosystem 'cpe') ou ecossistema OSV (debian, ubuntu, alpine...)
+CREATE TABLE IF NOT EXISTS vulnerability_ranges (
+    id BIGSERIAL PRIMARY KEY,
+    vuln_id VARCHAR(64) NOT NULL REFERENCES vulnerabilities(id) ON DELETE CASCADE,
+    ecosystem VARCHAR(50) NOT NULL,
+    vendor VARCHAR(255),
+    product VARCHAR(255) NOT NULL,
+    introduced TEXT,                    -- NULL = desde sempre
+    introduced_inclusive BOOLEAN NOT NULL DEFAULT TRUE,
+    fixed TEXT,                         -- Limite superior exclusivo (versao corrigida)
+    last_affected TEXT,                 -- Limite superior inclusivo
+    versions TEXT[] NOT NULL DEFAULT '{}' -- Versoes exatas afetadas
+);
+
+CREATE INDEX IF NOT EXISTS idx_vulnerability_ranges_product ON vulnerability_ranges(product);
+
+-- Achados por agente (recalculados a cada inventario recebido e a cada importacao)
+CREATE TABLE IF NOT EXISTS vulnerability_findings (
+    id BIGSERIAL PRIMARY KEY,
+    agent_id UUID NOT NULL REFERENCES agents(id),
+    vuln_id VARCHAR(64) NOT NULL REFERENCES vulnerabilities(id) ON DELETE CASCADE,
+    software_name VARCHAR(255) NOT NULL,
+    software_version VARCHAR(100),
+    fixed_version TEXT,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (agent_id, vuln_id, software_name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vulnerability_findings_vuln ON vulnerability_findings(vuln_id);
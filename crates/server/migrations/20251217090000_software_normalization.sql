@@ -0,0 +1,6 @@
+-- Identidade canonica do software (shared::software::normalize), preenchida na gravacao do inventario
+ALTER TABLE software_inventory ADD COLUMN IF NOT EXISTS product VARCHAR(255);
+ALTER TABLE software_inventory ADD COLUMN IF NOT EXISTS vendor_key VARCHAR(255);
+
+CREATE INDEX IF NOT EXISTS idx_software_inventory_product ON software_inventory (product);
+CREATE INDEX IF NOT EXISTS idx_software_inventory_agent ON software_inventory (agent_id);
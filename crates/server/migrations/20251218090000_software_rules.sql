@@ -0,0 +1,10 @@
+-- Lista de software proibido (deny) e excecoes (allow) aplicadas ao inventario
+CREATE TABLE IF NOT EXISTS software_rules (
+    id SERIAL PRIMARY KEY,
+    kind VARCHAR(5) NOT NULL CHECK (kind IN ('allow', 'deny')),
+    pattern VARCHAR(255) NOT NULL,      -- Glob ('*') sobre o produto canonico ou o nome instalado
+    vendor VARCHAR(255),                -- Fabricante canonico (opcional)
+    version VARCHAR(100),               -- Restricao de versao, ex.: "<3.0" (opcional)
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,11 @@
+-- Tags estaticas dos agentes
+CREATE TABLE IF NOT EXISTS agent_tags (
+    agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    tag VARCHAR(64) NOT NULL,
+    PRIMARY KEY (agent_id, tag)
+);
+CREATE INDEX IF NOT EXISTS idx_agent_tags_tag ON agent_tags (tag);
+
+-- Listagem paginada de /api/agents (ordenacao por ultimo sinal e hostname)
+CREATE INDEX IF NOT EXISTS idx_agents_last_seen ON agents (last_seen_at, id);
+CREATE INDEX IF NOT EXISTS idx_agents_hostname ON agents (hostname, id);
@@ -0,0 +1,12 @@
+-- Origem da tag: 'api' (definida pelo operador) ou 'agent' (AGENT_TAGS no handshake)
+ALTER TABLE agent_tags ADD COLUMN IF NOT EXISTS source VARCHAR(10) NOT NULL DEFAULT 'api';
+
+-- Grupos dinamicos: membros resolvidos pela consulta a cada uso
+CREATE TABLE IF NOT EXISTS agent_groups (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description TEXT,
+    query TEXT NOT NULL,                -- Ex.: os_name = 'Ubuntu' AND compliance_score < 70
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
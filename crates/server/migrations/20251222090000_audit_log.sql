@@ -0,0 +1,33 @@
+-- Trilha de auditoria das acoes dos operadores (somente insercao)
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    user_id UUID,                       -- Sem FK: o registro sobrevive a exclusao do usuario
+    username VARCHAR(64),
+    role VARCHAR(10),
+    action VARCHAR(128) NOT NULL,       -- Metodo + rota: "DELETE /api/agents/:id"
+    target_type VARCHAR(32),            -- agents, groups, users...
+    target_id TEXT,
+    payload_hash CHAR(64),              -- SHA-256 do corpo da requisicao
+    status_code SMALLINT NOT NULL,
+    result VARCHAR(10) NOT NULL,        -- success, denied, failure
+    prev_hash CHAR(64),                 -- Hash do registro anterior (cadeia opcional)
+    entry_hash CHAR(64) NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_audit_log_occurred ON audit_log (occurred_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_target ON audit_log (target_type, target_id);
+
+-- Registros nao podem ser alterados nem apagados
+CREATE OR REPLACE FUNCTION audit_log_immutable() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log eh somente insercao';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_no_update ON audit_log;
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_immutable();
+
+DROP TRIGGER IF EXISTS audit_log_no_truncate ON audit_log;
+CREATE TRIGGER audit_log_no_truncate BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_immutable();
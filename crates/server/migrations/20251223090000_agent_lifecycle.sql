@@ -0,0 +1,8 @@
+-- Ciclo de vida do agente: active -> stale (sem contato) -> decommissioned (exclusao logica)
+-- e blocked (agent_id impedido de se registrar novamente)
+ALTER TABLE agents ADD COLUMN IF NOT EXISTS lifecycle VARCHAR(16) NOT NULL DEFAULT 'active'
+    CHECK (lifecycle IN ('active', 'stale', 'decommissioned', 'blocked'));
+ALTER TABLE agents ADD COLUMN IF NOT EXISTS decommissioned_at TIMESTAMPTZ;  -- Inicio do prazo de retencao
+ALTER TABLE agents ADD COLUMN IF NOT EXISTS blocked_at TIMESTAMPTZ;
+ALTER TABLE agents ADD COLUMN IF NOT EXISTS lifecycle_reason TEXT;
+CREATE INDEX IF NOT EXISTS idx_agents_lifecycle ON agents (lifecycle);
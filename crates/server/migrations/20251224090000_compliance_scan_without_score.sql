@@ -0,0 +1,3 @@
+-- Varredura em que nenhuma regra se aplica (tudo NOT_APPLICABLE) nao tem score
+ALTER TABLE compliance_scans ALTER COLUMN score DROP NOT NULL;
+ALTER TABLE compliance_scans ALTER COLUMN raw_score DROP NOT NULL;
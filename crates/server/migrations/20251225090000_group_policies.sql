@@ -0,0 +1,7 @@
+-- Politicas SCA atribuidas a grupos: enviadas aos membros no handshake e quando a atribuicao muda
+CREATE TABLE IF NOT EXISTS group_policies (
+    group_id INT NOT NULL REFERENCES agent_groups(id) ON DELETE CASCADE,
+    policy_id VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (group_id, policy_id)
+);
@@ -0,0 +1,242 @@
+﻿//! Ciclo de vida do agente:
+//!
+//!   active -> stale               sem contato ha mais de AGENT_STALE_DAYS
+//!   (qualquer) -> decommissioned  DELETE /api/agents/:id (exclusao logica, dados mantidos)
+//!   decommissioned -> purga       apos AGENT_RETENTION_DAYS, ou ?purge=true
+//!   (qualquer) -> blocked         agent_id recusado no handshake ate ser desbloqueado
+//!
+//! Um agente stale ou decommissioned que volta a se conectar eh reativado.
+
+use axum::{extract::{State, Path, Query}, response::Json, http};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::postgres::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+use crate::AppState;
+use crate::auth::Admin;
+use crate::error::{ApiError, ApiResult};
+
+#[cfg(test)]
+mod tests;
+
+/// Intervalo entre as rodadas de manutencao (stale + retencao)
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Tabelas com dados do agente, na ordem de remocao (dependentes antes)
+const AGENT_TABLES: &[&str] = &[
+    "drift_events",
+    "compliance_rule_results",
+    "compliance_scans",
+    "compliance_scores",
+    "remediation_actions",
+    "vulnerability_findings",
+    "software_inventory",
+    "hardware_specs",
+    "agent_tags",
+];
+
+#[derive(Deserialize)]
+pub struct DeleteQuery {
+    /// Remove os dados imediatamente, sem aguardar a retencao
+    #[serde(default)]
+    purge: bool,
+}
+
+#[derive(Deserialize)]
+pub struct BlockBody {
+    reason: Option<String>,
+}
+
+/// Estado do agente lido pela rodada de manutencao
+#[derive(sqlx::FromRow)]
+struct LifecycleRow {
+    id: Uuid,
+    lifecycle: String,
+    last_seen_at: Option<DateTime<Utc>>,
+    decommissioned_at: Option<DateTime<Utc>>,
+}
+
+/// Transicao decidida pela manutencao
+#[derive(Debug, PartialEq, Eq)]
+enum Transition {
+    Stale,
+    Purge,
+}
+
+/// DELETE /api/agents/:id - descomissiona o agente (ou purga com ?purge=true)
+pub async fn delete_agent(
+    _: Admin,
+    Path(id): Path<Uuid>,
+    Query(q): Query<DeleteQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<http::StatusCode> {
+    let done = if q.purge {
+        purge(&state.pg_pool, id, None).await?
+    } else {
+        sqlx::query(
+            "UPDATE agents SET lifecycle = 'decommissioned', decommissioned_at = NOW(), status = 'OFFLINE'
+             WHERE id = $1 AND lifecycle <> 'blocked'")
+            .bind(id)
+            .execute(&state.pg_pool).await?
+            .rows_affected() > 0
+    };
+    if !done {
+        // Inexistente, ou bloqueado (que ja nao participa da frota e mantem o bloqueio)
+        return Err(match lifecycle_of(&state.pg_pool, id).await? {
+            Some(_) => ApiError::conflict("agente bloqueado: desbloqueie antes de descomissionar"),
+            None => ApiError::not_found("agente"),
+        });
+    }
+    disconnect(&state, id).await;
+    tracing::info!("Agente {} {}", id, if q.purge { "purgado" } else { "descomissionado" });
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+/// POST /api/agents/:id/block - impede o agent_id de se registrar novamente.
+/// Aceita ids ainda desconhecidos (ex.: agente ja purgado).
+pub async fn block_agent(
+    _: Admin,
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<BlockBody>,
+) -> ApiResult<http::StatusCode> {
+    let reason = body.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    sqlx::query(
+        "INSERT INTO agents (id, hostname, os_name, status, lifecycle, blocked_at, lifecycle_reason)
+         VALUES ($1, '', '', 'OFFLINE', 'blocked', NOW(), $2)
+         ON CONFLICT (id) DO UPDATE SET lifecycle = 'blocked', blocked_at = NOW(), lifecycle_reason = $2, status = 'OFFLINE'")
+        .bind(id)
+        .bind(&reason)
+        .execute(&state.pg_pool).await?;
+    disconnect(&state, id).await;
+    tracing::warn!("Agente {} bloqueado: {}", id, reason.as_deref().unwrap_or("-"));
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+/// POST /api/agents/:id/unblock - volta a aceitar o agente (fica descomissionado ate reconectar)
+pub async fn unblock_agent(_: Admin, Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> ApiResult<http::StatusCode> {
+    let result = sqlx::query(
+        "UPDATE agents SET lifecycle = 'decommissioned', decommissioned_at = NOW(), blocked_at = NULL, lifecycle_reason = NULL
+         WHERE id = $1 AND lifecycle = 'blocked'")
+        .bind(id)
+        .execute(&state.pg_pool).await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("agente bloqueado"));
+    }
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+/// Registra o agente no handshake, reativando-o se estava stale/decommissioned.
+/// Retorna false se o agent_id esta bloqueado.
+pub async fn enroll(pool: &PgPool, id: Uuid, hostname: &str, os_name: &str) -> Result<bool, sqlx::Error> {
+    let r = sqlx::query(
+        "INSERT INTO agents (id, hostname, os_name, status, last_seen_at) VALUES ($1, $2, $3, 'ONLINE', NOW())
+         ON CONFLICT (id) DO UPDATE SET last_seen_at = NOW(), status = 'ONLINE',
+             lifecycle = 'active', decommissioned_at = NULL, lifecycle_reason = NULL
+         WHERE agents.lifecycle <> 'blocked'")
+        .bind(id)
+        .bind(hostname)
+        .bind(os_name)
+        .execute(pool).await?;
+    Ok(r.rows_affected() > 0)
+}
+
+/// Rodada periodica: marca agentes sem contato como stale e purga os descomissionados
+/// alem do prazo de retencao. Agentes bloqueados nunca sao purgados.
+pub async fn maintenance(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = state.shutdown.stopped() => return,
+        }
+        // Uma purga iniciada termina antes do processo sair
+        let _guard = state.shutdown.track();
+        if let Err(e) = run_maintenance(&state).await {
+            tracing::error!("Erro Postgres ciclo de vida: {}", e);
+        }
+    }
+}
+
+async fn run_maintenance(state: &AppState) -> Result<(), sqlx::Error> {
+    let connected: HashSet<Uuid> = state.connections.read().await.keys().copied().collect();
+    let rows = sqlx::query_as::<_, LifecycleRow>(
+        "SELECT id, lifecycle, last_seen_at, decommissioned_at FROM agents WHERE lifecycle IN ('active', 'decommissioned')")
+        .fetch_all(&state.pg_pool).await?;
+
+    let now = Utc::now();
+    let (mut stale, mut expired) = (Vec::new(), Vec::new());
+    for row in &rows {
+        match transition(row, now, connected.contains(&row.id), state.agent_stale_days, state.agent_retention_days) {
+            Some(Transition::Stale) => stale.push(row.id),
+            Some(Transition::Purge) => expired.push(row.id),
+            None => {}
+        }
+    }
+
+    // Reconfere o estado: o agente pode ter reconectado desde a consulta
+    let stale = sqlx::query("UPDATE agents SET lifecycle = 'stale', status = 'OFFLINE' WHERE id = ANY($1) AND lifecycle = 'active'")
+        .bind(&stale)
+        .execute(&state.pg_pool).await?
+        .rows_affected();
+    if stale > 0 {
+        tracing::info!("{} agente(s) sem contato marcados como stale", stale);
+    }
+
+    for id in expired {
+        // Pode ter reconectado (e sido reativado) desde a consulta
+        if purge(&state.pg_pool, id, Some("decommissioned")).await? {
+            tracing::info!("Agente {} purgado (retencao expirada)", id);
+        }
+    }
+    Ok(())
+}
+
+/// active sem contato ha mais de `stale_days` -> stale (exceto conectado: last_seen_at
+/// so avanca no handshake); decommissioned ha mais de `retention_days` -> purga.
+/// Demais estados (stale, blocked) nao mudam na manutencao.
+fn transition(row: &LifecycleRow, now: DateTime<Utc>, connected: bool, stale_days: i32, retention_days: i32) -> Option<Transition> {
+    let older_than = |at: Option<DateTime<Utc>>, days: i32| at.is_some_and(|at| at < now - chrono::Duration::days(days.into()));
+    match row.lifecycle.as_str() {
+        "active" if !connected && older_than(row.last_seen_at, stale_days) => Some(Transition::Stale),
+        "decommissioned" if older_than(row.decommissioned_at, retention_days) => Some(Transition::Purge),
+        _ => None,
+    }
+}
+
+/// Remove todos os dados do agente numa unica transacao. Agentes bloqueados mantem
+/// a linha em `agents`, que eh o proprio registro do bloqueio. Com `expected`, so purga
+/// se o agente ainda estiver naquele estado.
+async fn purge(pool: &PgPool, id: Uuid, expected: Option<&str>) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let lifecycle: Option<String> = sqlx::query_scalar("SELECT lifecycle FROM agents WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *tx).await?;
+    let Some(lifecycle) = lifecycle.filter(|l| expected.is_none_or(|e| e == l)) else {
+        return Ok(false);
+    };
+    for table in AGENT_TABLES {
+        sqlx::query(&format!("DELETE FROM {table} WHERE agent_id = $1"))
+            .bind(id)
+            .execute(&mut *tx).await?;
+    }
+    if lifecycle != "blocked" {
+        sqlx::query("DELETE FROM agents WHERE id = $1").bind(id).execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+async fn lifecycle_of(pool: &PgPool, id: Uuid) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT lifecycle FROM agents WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool).await
+}
+
+/// Derruba a conexao do agente: sem o canal no registro o socket eh encerrado
+async fn disconnect(state: &AppState, id: Uuid) {
+    state.connections.write().await.remove(&id);
+}
@@ -0,0 +1,53 @@
+﻿use chrono::{DateTime, Duration, TimeZone, Utc};
+use uuid::Uuid;
+use super::{transition, LifecycleRow, Transition};
+
+const STALE_DAYS: i32 = 7;
+const RETENTION_DAYS: i32 = 30;
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 12, 20, 12, 0, 0).unwrap()
+}
+
+fn agent(lifecycle: &str, last_seen_days: Option<i64>, decommissioned_days: Option<i64>) -> LifecycleRow {
+    LifecycleRow {
+        id: Uuid::nil(),
+        lifecycle: lifecycle.to_string(),
+        last_seen_at: last_seen_days.map(|d| now() - Duration::days(d)),
+        decommissioned_at: decommissioned_days.map(|d| now() - Duration::days(d)),
+    }
+}
+
+fn decide(row: &LifecycleRow, connected: bool) -> Option<Transition> {
+    transition(row, now(), connected, STALE_DAYS, RETENTION_DAYS)
+}
+
+#[test]
+fn active_agent_goes_stale_after_threshold() {
+    assert_eq!(decide(&agent("active", Some(6), None), false), None);
+    assert_eq!(decide(&agent("active", Some(7), None), false), None);
+    assert_eq!(decide(&agent("active", Some(8), None), false), Some(Transition::Stale));
+}
+
+#[test]
+fn connected_agent_never_goes_stale() {
+    assert_eq!(decide(&agent("active", Some(60), None), true), None);
+}
+
+#[test]
+fn agent_never_seen_is_not_marked_stale() {
+    assert_eq!(decide(&agent("active", None, None), false), None);
+}
+
+#[test]
+fn decommissioned_agent_is_purged_after_retention() {
+    assert_eq!(decide(&agent("decommissioned", Some(90), Some(29)), false), None);
+    assert_eq!(decide(&agent("decommissioned", Some(90), Some(31)), false), Some(Transition::Purge));
+    assert_eq!(decide(&agent("decommissioned", Some(90), None), false), None);
+}
+
+#[test]
+fn blocked_and_stale_agents_are_left_alone() {
+    assert_eq!(decide(&agent("blocked", Some(365), Some(365)), false), None);
+    assert_eq!(decide(&agent("stale", Some(365), None), false), None);
+}
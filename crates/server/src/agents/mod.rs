@@ -0,0 +1,155 @@
+﻿use axum::{extract::{State, Query}, response::Json};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::{AgentRow, AppState};
+use crate::error::{ApiError, ApiResult};
+
+pub mod lifecycle;
+
+#[derive(Deserialize)]
+pub struct AgentQuery {
+    status: Option<String>,    // ONLINE / OFFLINE
+    os: Option<String>,        // Trecho do os_name
+    hostname: Option<String>,  // Glob com '*' ("web-*"); sem '*' busca por trecho
+    min_score: Option<i32>,
+    max_score: Option<i32>,
+    tag: Option<String>,
+    group_id: Option<i32>,
+    last_seen_after: Option<DateTime<Utc>>,
+    last_seen_before: Option<DateTime<Utc>>,
+    lifecycle: Option<String>, // Lista separada por virgula ou "all" (padrao: active,stale)
+    sort: Option<String>,      // last_seen_at (padrao), hostname, os_name, compliance_score
+    order: Option<String>,     // asc / desc (padrao)
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AgentPage {
+    items: Vec<AgentRow>,
+    /// Cursor opaco para a proxima pagina (ausente na ultima)
+    next_cursor: Option<String>,
+    /// Total de agentes que atendem aos filtros
+    total: i64,
+}
+
+/// Posicao na ordenacao: valor da coluna ordenada + id como desempate
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    v: String,
+    id: Uuid,
+}
+
+const FILTERS: &str = r#"
+    WHERE ($1::text IS NULL OR UPPER(a.status) = UPPER($1))
+      AND ($2::text IS NULL OR a.os_name ILIKE '%' || $2 || '%')
+      AND ($3::text IS NULL OR a.hostname ILIKE $3 ESCAPE '\')
+      AND ($4::int IS NULL OR c.score >= $4)
+      AND ($5::int IS NULL OR c.score <= $5)
+      AND ($6::text IS NULL OR EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id AND t.tag = $6))
+      AND ($7::timestamptz IS NULL OR a.last_seen_at >= $7)
+      AND ($8::timestamptz IS NULL OR a.last_seen_at < $8)
+      AND ($9::uuid[] IS NULL OR a.id = ANY($9))
+      AND ($10::text[] IS NULL OR a.lifecycle = ANY($10))"#;
+
+/// GET /api/agents - listagem paginada por cursor (keyset), com filtros e ordenacao
+pub async fn list_agents(Query(q): Query<AgentQuery>, State(state): State<Arc<AppState>>) -> ApiResult<Json<AgentPage>> {
+    // Expressao de ordenacao e tipo do valor no cursor (score nulo ordena como -1)
+    let (expr, cast) = match q.sort.as_deref().unwrap_or("last_seen_at") {
+        "last_seen_at" => ("a.last_seen_at", "timestamptz"),
+        "hostname" => ("a.hostname", "text"),
+        "os_name" => ("a.os_name", "text"),
+        "compliance_score" => ("COALESCE(c.score, -1)", "int"),
+        other => return Err(ApiError::bad_request(format!("sort invalido: {other}"))),
+    };
+    let (dir, op) = match q.order.as_deref().unwrap_or("desc") {
+        "desc" => ("DESC", "<"),
+        "asc" => ("ASC", ">"),
+        other => return Err(ApiError::bad_request(format!("order invalido: {other}"))),
+    };
+    let cursor = match q.cursor.as_deref() {
+        Some(c) => Some(decode_cursor(c).ok_or_else(|| ApiError::bad_request("cursor invalido"))?),
+        None => None,
+    };
+    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
+    let hostname = q.hostname.as_deref().map(hostname_pattern);
+    let group = crate::groups::scope(&state.pg_pool, None, q.group_id).await?;
+    let lifecycle = lifecycle_filter(q.lifecycle.as_deref())?;
+
+    let from = "FROM agents a LEFT JOIN compliance_scores c ON a.id = c.agent_id";
+    let keyset = if cursor.is_some() { format!("AND ({expr}, a.id) {op} ($11::{cast}, $12)") } else { String::new() };
+    let sql = format!(
+        "SELECT a.id, a.hostname, a.os_name, a.status, a.lifecycle, a.last_seen_at, c.score AS compliance_score, ({expr})::text AS sort_value
+         {from} {FILTERS} {keyset}
+         ORDER BY {expr} {dir}, a.id {dir}
+         LIMIT {}", limit + 1);
+
+    let mut query = sqlx::query_as::<_, AgentRowWithKey>(&sql);
+    query = query
+        .bind(&q.status).bind(&q.os).bind(&hostname).bind(q.min_score).bind(q.max_score)
+        .bind(&q.tag).bind(q.last_seen_after).bind(q.last_seen_before).bind(&group).bind(&lifecycle);
+    if let Some(c) = &cursor {
+        query = query.bind(&c.v).bind(c.id);
+    }
+    let mut rows = query.fetch_all(&state.pg_pool).await?;
+
+    let next_cursor = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        rows.last().map(|r| encode_cursor(&Cursor { v: r.sort_value.clone(), id: r.agent.id }))
+    } else {
+        None
+    };
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {from} {FILTERS}"))
+        .bind(&q.status).bind(&q.os).bind(&hostname).bind(q.min_score).bind(q.max_score)
+        .bind(&q.tag).bind(q.last_seen_after).bind(q.last_seen_before).bind(&group).bind(&lifecycle)
+        .fetch_one(&state.pg_pool).await?;
+
+    Ok(Json(AgentPage { items: rows.into_iter().map(|r| r.agent).collect(), next_cursor, total }))
+}
+
+#[derive(sqlx::FromRow)]
+struct AgentRowWithKey {
+    #[sqlx(flatten)]
+    agent: AgentRow,
+    sort_value: String,
+}
+
+/// Estados do ciclo de vida pedidos; None = todos. Por padrao os descomissionados
+/// e bloqueados ficam fora da listagem.
+fn lifecycle_filter(param: Option<&str>) -> ApiResult<Option<Vec<String>>> {
+    const STATES: [&str; 4] = ["active", "stale", "decommissioned", "blocked"];
+    match param.map(str::trim) {
+        None | Some("") => Ok(Some(vec!["active".to_string(), "stale".to_string()])),
+        Some("all") => Ok(None),
+        Some(list) => list.split(',')
+            .map(|s| {
+                let s = s.trim().to_ascii_lowercase();
+                if STATES.contains(&s.as_str()) { Ok(s) } else { Err(ApiError::bad_request(format!("lifecycle invalido: {s}"))) }
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+    }
+}
+
+/// "web-*" -> "web-%"; "db" -> "%db%" (curingas do LIKE no texto sao escapados)
+fn hostname_pattern(pattern: &str) -> String {
+    let escaped = pattern.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    if escaped.contains('*') {
+        escaped.replace('*', "%")
+    } else {
+        format!("%{escaped}%")
+    }
+}
+
+fn encode_cursor(cursor: &Cursor) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str) -> Option<Cursor> {
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
@@ -0,0 +1,317 @@
+﻿use axum::{
+    body::Body,
+    extract::{MatchedPath, Query, State},
+    http::{Method, Request},
+    middleware::Next,
+    response::{Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::auth::{Admin, CurrentUser};
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+
+#[cfg(test)]
+mod tests;
+
+/// Chave do advisory lock que serializa a cadeia de hashes
+const CHAIN_LOCK: i64 = 0x0062_7461_7564_6974; // "btaudit"
+
+/// prev_hash do primeiro registro de uma trilha vazia
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Maior corpo lido para o hash; o middleware tambem cobre login/logout (sem autenticacao)
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Campos trocados antes do hash do corpo: sha256 de {"username","password"} sem sal
+/// seria atacavel por dicionario por quem le a trilha (login, criacao e troca de senha)
+const CREDENTIAL_FIELDS: &[&str] = &["password", "secret", "token"];
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    username: Option<String>,
+    action: Option<String>,     // Trecho da acao ("DELETE", "/api/groups")
+    target_type: Option<String>,
+    target_id: Option<String>,
+    result: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    before_id: Option<i64>,     // Paginacao: registros com id menor
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    id: i64,
+    occurred_at: DateTime<Utc>,
+    user_id: Option<Uuid>,
+    username: Option<String>,
+    role: Option<String>,
+    action: String,
+    target_type: Option<String>,
+    target_id: Option<String>,
+    payload_hash: Option<String>,
+    status_code: i16,
+    result: String,
+    prev_hash: Option<String>,
+    entry_hash: String,
+}
+
+#[derive(Serialize)]
+pub struct ChainReport {
+    entries: usize,
+    valid: bool,
+    /// Primeiro registro cujo hash nao confere (ou que quebra a cadeia)
+    first_invalid_id: Option<i64>,
+}
+
+/// Registro a gravar
+struct NewEntry {
+    occurred_at: DateTime<Utc>,
+    user: Option<CurrentUser>,
+    action: String,
+    target_type: Option<String>,
+    target_id: Option<String>,
+    payload_hash: Option<String>,
+    status_code: u16,
+}
+
+/// Middleware: grava toda requisicao que altera estado (POST, PUT, PATCH, DELETE),
+/// inclusive as recusadas pela autenticacao (401, sem usuario)
+pub async fn record(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+    next: Next<Body>,
+) -> ApiResult<Response> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(next.run(req).await);
+    }
+
+    let (parts, body) = req.into_parts();
+    let bytes = hyper::body::to_bytes(http_body::Limited::new(body, MAX_BODY_BYTES)).await.map_err(|e| {
+        if e.is::<http_body::LengthLimitError>() {
+            ApiError::PayloadTooLarge(format!("corpo acima de {} bytes", MAX_BODY_BYTES))
+        } else {
+            ApiError::bad_request(format!("corpo da requisicao ilegivel: {e}"))
+        }
+    })?;
+    let payload_hash = payload_hash(&bytes);
+
+    let route = parts.extensions.get::<MatchedPath>().map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| parts.uri.path().to_string());
+    let (target_type, target_id) = target(parts.uri.path());
+    let method = parts.method.clone();
+    // Truncado em microssegundos para o hash conferir com o valor gravado
+    let now = Utc::now();
+    let entry = NewEntry {
+        occurred_at: DateTime::from_timestamp_micros(now.timestamp_micros()).unwrap_or(now),
+        user: None,
+        action: format!("{} {}", parts.method, route),
+        target_type,
+        target_id,
+        payload_hash,
+        status_code: 0,
+    };
+
+    let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+
+    // Acao sem registro na trilha nao eh confirmada ao cliente
+    let entry = NewEntry {
+        user: response.extensions().get::<CurrentUser>().cloned(),
+        status_code: response.status().as_u16(),
+        ..entry
+    };
+    if let Err(e) = append(&state.pg_pool, state.audit_chain, entry).await {
+        return Err(ApiError::Internal(format!("falha ao gravar auditoria de {} {}: {}", method, route, e)));
+    }
+    Ok(response)
+}
+
+/// GET /api/audit - registros mais recentes primeiro (somente admin)
+pub async fn list(_: Admin, Query(q): Query<AuditQuery>, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<AuditEntry>>> {
+    let entries = sqlx::query_as::<_, AuditEntry>(
+        r#"SELECT id, occurred_at, user_id, username, role, action, target_type, target_id, payload_hash,
+                  status_code, result, prev_hash, entry_hash
+           FROM audit_log
+           WHERE ($1::text IS NULL OR username = $1)
+             AND ($2::text IS NULL OR action ILIKE '%' || $2 || '%')
+             AND ($3::text IS NULL OR target_type = $3)
+             AND ($4::text IS NULL OR target_id = $4)
+             AND ($5::text IS NULL OR result = $5)
+             AND ($6::timestamptz IS NULL OR occurred_at >= $6)
+             AND ($7::timestamptz IS NULL OR occurred_at < $7)
+             AND ($8::bigint IS NULL OR id < $8)
+           ORDER BY id DESC
+           LIMIT $9"#)
+        .bind(&q.username).bind(&q.action).bind(&q.target_type).bind(&q.target_id).bind(&q.result)
+        .bind(q.from).bind(q.to).bind(q.before_id).bind(q.limit.unwrap_or(100).clamp(1, 1000))
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(entries))
+}
+
+/// GET /api/audit/verify - recalcula os hashes e confere a cadeia (somente admin)
+pub async fn verify(_: Admin, State(state): State<Arc<AppState>>) -> ApiResult<Json<ChainReport>> {
+    let rows = sqlx::query_as::<_, AuditEntry>(
+        r#"SELECT id, occurred_at, user_id, username, role, action, target_type, target_id, payload_hash,
+                  status_code, result, prev_hash, entry_hash
+           FROM audit_log ORDER BY id"#)
+        .fetch_all(&state.pg_pool).await?;
+
+    // Antes da cadeia comecar os registros tem prev_hash nulo e so conferem o proprio
+    // hash; depois do primeiro elo, prev_hash nulo eh quebra (ocultaria adulteracao)
+    let mut previous: Option<&str> = None;
+    let mut chained = false;
+    for row in &rows {
+        let linked = match row.prev_hash.as_deref() {
+            None => !chained,
+            Some(prev) => prev == previous.unwrap_or(GENESIS),
+        };
+        chained |= row.prev_hash.is_some();
+        if !linked || entry_hash(row.prev_hash.as_deref(), &Fields::from(row)) != row.entry_hash {
+            return Ok(Json(ChainReport { entries: rows.len(), valid: false, first_invalid_id: Some(row.id) }));
+        }
+        previous = Some(&row.entry_hash);
+    }
+    Ok(Json(ChainReport { entries: rows.len(), valid: true, first_invalid_id: None }))
+}
+
+async fn append(pool: &PgPool, chain: bool, entry: NewEntry) -> Result<(), sqlx::Error> {
+    let fields = Fields {
+        occurred_at: entry.occurred_at,
+        user_id: entry.user.as_ref().map(|u| u.id),
+        username: entry.user.as_ref().map(|u| u.username.as_str()),
+        role: entry.user.as_ref().map(|u| u.role.as_str()),
+        action: &entry.action,
+        target_type: entry.target_type.as_deref(),
+        target_id: entry.target_id.as_deref(),
+        payload_hash: entry.payload_hash.as_deref(),
+        status_code: entry.status_code as i16,
+        result: result(entry.status_code),
+    };
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock($1)").bind(CHAIN_LOCK).execute(&mut *tx).await?;
+    let last: Option<(String, bool)> = sqlx::query_as("SELECT entry_hash, prev_hash IS NOT NULL FROM audit_log ORDER BY id DESC LIMIT 1")
+        .fetch_optional(&mut *tx).await?;
+    // Cadeia iniciada continua mesmo com AUDIT_HASH_CHAIN desligado: um elo nulo seria quebra
+    let prev_hash = match last {
+        Some((hash, was_chained)) if chain || was_chained => Some(hash),
+        None if chain => Some(GENESIS.to_string()),
+        _ => None,
+    };
+    let hash = entry_hash(prev_hash.as_deref(), &fields);
+
+    sqlx::query(
+        r#"INSERT INTO audit_log (occurred_at, user_id, username, role, action, target_type, target_id, payload_hash,
+                                  status_code, result, prev_hash, entry_hash)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"#)
+        .bind(fields.occurred_at).bind(fields.user_id).bind(fields.username).bind(fields.role)
+        .bind(fields.action).bind(fields.target_type).bind(fields.target_id).bind(fields.payload_hash)
+        .bind(fields.status_code).bind(fields.result).bind(&prev_hash).bind(&hash)
+        .execute(&mut *tx).await?;
+    tx.commit().await
+}
+
+/// Campos cobertos pelo hash do registro
+struct Fields<'a> {
+    occurred_at: DateTime<Utc>,
+    user_id: Option<Uuid>,
+    username: Option<&'a str>,
+    role: Option<&'a str>,
+    action: &'a str,
+    target_type: Option<&'a str>,
+    target_id: Option<&'a str>,
+    payload_hash: Option<&'a str>,
+    status_code: i16,
+    result: &'a str,
+}
+
+impl<'a> From<&'a AuditEntry> for Fields<'a> {
+    fn from(e: &'a AuditEntry) -> Self {
+        Self {
+            occurred_at: e.occurred_at,
+            user_id: e.user_id,
+            username: e.username.as_deref(),
+            role: e.role.as_deref(),
+            action: &e.action,
+            target_type: e.target_type.as_deref(),
+            target_id: e.target_id.as_deref(),
+            payload_hash: e.payload_hash.as_deref(),
+            status_code: e.status_code,
+            result: &e.result,
+        }
+    }
+}
+
+/// SHA-256 de prev_hash + campos separados por '\n' (timestamp em microssegundos,
+/// a precisao do Postgres)
+fn entry_hash(prev_hash: Option<&str>, f: &Fields) -> String {
+    let user_id = f.user_id.map(|u| u.to_string());
+    let canonical = [
+        prev_hash.unwrap_or(""),
+        &f.occurred_at.timestamp_micros().to_string(),
+        user_id.as_deref().unwrap_or(""),
+        f.username.unwrap_or(""),
+        f.role.unwrap_or(""),
+        f.action,
+        f.target_type.unwrap_or(""),
+        f.target_id.unwrap_or(""),
+        f.payload_hash.unwrap_or(""),
+        &f.status_code.to_string(),
+        f.result,
+    ].join("\n");
+    format!("{:x}", Sha256::digest(canonical.as_bytes()))
+}
+
+/// Hash do corpo com as credenciais trocadas por "[redacted]"; corpo que nao eh JSON entra cru
+fn payload_hash(body: &[u8]) -> Option<String> {
+    if body.is_empty() {
+        return None;
+    }
+    let digest = match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact(&mut value);
+            Sha256::digest(value.to_string().as_bytes())
+        }
+        Err(_) => Sha256::digest(body),
+    };
+    Some(format!("{:x}", digest))
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                let name = name.to_lowercase();
+                if CREDENTIAL_FIELDS.iter().any(|c| name.contains(c)) {
+                    *field = Value::String("[redacted]".to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// "/api/agents/<uuid>/remediate" -> ("agents", "<uuid>")
+fn target(path: &str) -> (Option<String>, Option<String>) {
+    let mut segments = path.trim_start_matches("/api/").split('/').filter(|s| !s.is_empty());
+    let kind = segments.next().map(str::to_string);
+    let id = segments.next().map(str::to_string);
+    (kind, id)
+}
+
+fn result(status: u16) -> &'static str {
+    match status {
+        200..=399 => "success",
+        401 | 403 => "denied",
+        _ => "failure",
+    }
+}
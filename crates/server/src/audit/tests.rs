@@ -0,0 +1,41 @@
+﻿use serde_json::json;
+use sha2::{Digest, Sha256};
+use super::{payload_hash, redact};
+
+#[test]
+fn credentials_do_not_reach_the_hash() {
+    let login = |password: &str| payload_hash(json!({ "username": "root", "password": password }).to_string().as_bytes());
+    assert_eq!(login("secretpass123"), login("outra-senha"));
+
+    let plain = format!("{:x}", Sha256::digest(br#"{"password":"secretpass123","username":"root"}"#));
+    assert_ne!(login("secretpass123"), Some(plain));
+}
+
+#[test]
+fn other_fields_still_change_the_hash() {
+    let user = |role: &str| payload_hash(json!({ "username": "ana", "role": role, "password": "x" }).to_string().as_bytes());
+    assert_ne!(user("viewer"), user("admin"));
+}
+
+#[test]
+fn redacts_nested_and_case_insensitive_fields() {
+    let mut body = json!({
+        "name": "ci",
+        "NewPassword": "a",
+        "items": [{ "client_secret": "b", "keep": 1 }],
+        "auth": { "api_token": "c" },
+    });
+    redact(&mut body);
+    assert_eq!(body, json!({
+        "name": "ci",
+        "NewPassword": "[redacted]",
+        "items": [{ "client_secret": "[redacted]", "keep": 1 }],
+        "auth": { "api_token": "[redacted]" },
+    }));
+}
+
+#[test]
+fn empty_and_non_json_bodies() {
+    assert_eq!(payload_hash(b""), None);
+    assert_eq!(payload_hash(b"not json"), Some(format!("{:x}", Sha256::digest(b"not json"))));
+}
@@ -0,0 +1,83 @@
+﻿use axum::{extract::{State, Path}, response::Json, http};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+use super::{CurrentUser, Role, API_KEY_PREFIX};
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct ApiKeyRow {
+    id: Uuid,
+    user_id: Uuid,
+    name: String,
+    prefix: String,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct NewKey {
+    name: String,
+    expires_in_days: Option<i64>,
+}
+
+/// Chave recem-criada: o valor so aparece nesta resposta
+#[derive(Serialize)]
+pub struct CreatedKey {
+    key: String,
+    #[serde(flatten)]
+    row: ApiKeyRow,
+}
+
+/// GET /api/auth/api-keys - chaves do usuario atual
+pub async fn list_keys(user: CurrentUser, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<ApiKeyRow>>> {
+    let keys = sqlx::query_as::<_, ApiKeyRow>(
+        r#"SELECT id, user_id, name, prefix, created_at, expires_at, last_used_at, revoked_at
+           FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC"#)
+        .bind(user.id)
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(keys))
+}
+
+/// POST /api/auth/api-keys
+pub async fn create_key(
+    user: CurrentUser,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<NewKey>,
+) -> ApiResult<Json<CreatedKey>> {
+    let name = body.name.trim();
+    if name.is_empty() || name.len() > 100 || body.expires_in_days.is_some_and(|d| !(1..=3650).contains(&d)) {
+        return Err(ApiError::bad_request("nome (1-100 caracteres) e expires_in_days (1-3650) invalidos"));
+    }
+    let key = super::new_token(API_KEY_PREFIX);
+    let expires_at = body.expires_in_days.map(|d| Utc::now() + Duration::days(d));
+
+    let row = sqlx::query_as::<_, ApiKeyRow>(
+        r#"INSERT INTO api_keys (id, user_id, name, prefix, key_hash, expires_at)
+           VALUES ($1, $2, $3, $4, $5, $6)
+           RETURNING id, user_id, name, prefix, created_at, expires_at, last_used_at, revoked_at"#)
+        .bind(Uuid::new_v4())
+        .bind(user.id)
+        .bind(name)
+        .bind(&key[..API_KEY_PREFIX.len() + 8])
+        .bind(super::token_hash(&key))
+        .bind(expires_at)
+        .fetch_one(&state.pg_pool).await?;
+    Ok(Json(CreatedKey { key, row }))
+}
+
+/// DELETE /api/auth/api-keys/:id - revoga (admin pode revogar chaves de qualquer usuario)
+pub async fn revoke_key(user: CurrentUser, Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> ApiResult<http::StatusCode> {
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL AND (user_id = $2 OR $3)")
+        .bind(id).bind(user.id).bind(user.role == Role::Admin)
+        .execute(&state.pg_pool).await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("chave de API"));
+    }
+    Ok(http::StatusCode::NO_CONTENT)
+}
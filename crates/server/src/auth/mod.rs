@@ -0,0 +1,295 @@
+﻿use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::{self, header, request::Parts, HeaderMap, Request},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::{SaltString, rand_core::OsRng}};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+
+pub mod keys;
+pub mod users;
+
+/// Validade da sessao do login
+const SESSION_TTL_HOURS: i64 = 12;
+/// Cookie da sessao (usado pelo dashboard e pelos links de relatorio/exportacao)
+const SESSION_COOKIE: &str = "bt_session";
+/// Prefixos dos tokens: sessao e chave de API
+const SESSION_PREFIX: &str = "bts_";
+const API_KEY_PREFIX: &str = "btk_";
+pub const MIN_PASSWORD_LEN: usize = 12;
+
+/// Papeis em ordem crescente de privilegio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Somente leitura
+    Viewer,
+    /// Envia comandos e altera tags, grupos e regras
+    Analyst,
+    /// Exclusoes e gestao de usuarios
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Analyst => "analyst",
+            Role::Admin => "admin",
+        }
+    }
+
+    fn parse(role: &str) -> Option<Self> {
+        match role {
+            "viewer" => Some(Role::Viewer),
+            "analyst" => Some(Role::Analyst),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// Usuario autenticado da requisicao (inserido pelo middleware `authenticate`)
+#[derive(Debug, Clone, Serialize)]
+pub struct CurrentUser {
+    pub id: Uuid,
+    pub username: String,
+    pub role: Role,
+}
+
+/// Extrator que exige papel analyst ou superior
+pub struct Analyst(pub CurrentUser);
+
+/// Extrator que exige papel admin
+pub struct Admin(pub CurrentUser);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<CurrentUser>().cloned().ok_or(ApiError::Unauthorized)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Analyst {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        require(parts, state, Role::Analyst).await.map(Analyst)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Admin {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        require(parts, state, Role::Admin).await.map(Admin)
+    }
+}
+
+async fn require<S: Send + Sync>(parts: &mut Parts, state: &S, role: Role) -> ApiResult<CurrentUser> {
+    let user = CurrentUser::from_request_parts(parts, state).await?;
+    if user.role < role {
+        return Err(ApiError::Forbidden);
+    }
+    Ok(user)
+}
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    id: Uuid,
+    username: String,
+    role: String,
+}
+
+impl UserRow {
+    fn into_user(self) -> Option<CurrentUser> {
+        Some(CurrentUser { role: Role::parse(&self.role)?, id: self.id, username: self.username })
+    }
+}
+
+/// Middleware das rotas /api: aceita `Authorization: Bearer <token>`, `X-Api-Key` ou o cookie da sessao
+pub async fn authenticate<B>(
+    State(state): State<Arc<AppState>>,
+    mut req: Request<B>,
+    next: Next<B>,
+) -> ApiResult<Response> {
+    let token = request_token(req.headers()).ok_or(ApiError::Unauthorized)?;
+    let user = lookup(&state.pg_pool, &token).await?.ok_or(ApiError::Unauthorized)?;
+    req.extensions_mut().insert(user.clone());
+    let mut response = next.run(req).await;
+    // A auditoria roda por fora da autenticacao e identifica o autor pela resposta
+    response.extensions_mut().insert(user);
+    Ok(response)
+}
+
+fn request_token(headers: &HeaderMap) -> Option<String> {
+    let value = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(token) = value(header::AUTHORIZATION).and_then(|v| v.strip_prefix("Bearer ")) {
+        return Some(token.trim().to_string());
+    }
+    if let Some(key) = value(header::HeaderName::from_static("x-api-key")) {
+        return Some(key.trim().to_string());
+    }
+    headers.get_all(header::COOKIE).iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|c| c.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('=').map(str::to_string))
+}
+
+async fn lookup(pool: &PgPool, token: &str) -> ApiResult<Option<CurrentUser>> {
+    let hash = token_hash(token);
+    let row = if token.starts_with(API_KEY_PREFIX) {
+        sqlx::query_as::<_, UserRow>(
+            r#"UPDATE api_keys k SET last_used_at = NOW()
+               FROM users u
+               WHERE k.key_hash = $1 AND u.id = k.user_id AND NOT u.disabled
+                 AND k.revoked_at IS NULL AND (k.expires_at IS NULL OR k.expires_at > NOW())
+               RETURNING u.id, u.username, u.role"#)
+            .bind(&hash)
+            .fetch_optional(pool).await
+    } else if token.starts_with(SESSION_PREFIX) {
+        sqlx::query_as::<_, UserRow>(
+            r#"UPDATE user_sessions s SET last_used_at = NOW()
+               FROM users u
+               WHERE s.token_hash = $1 AND u.id = s.user_id AND NOT u.disabled AND s.expires_at > NOW()
+               RETURNING u.id, u.username, u.role"#)
+            .bind(&hash)
+            .fetch_optional(pool).await
+    } else {
+        return Ok(None);
+    };
+    Ok(row?.and_then(UserRow::into_user))
+}
+
+#[derive(Deserialize)]
+pub struct LoginBody {
+    username: String,
+    password: String,
+}
+
+#[derive(Serialize)]
+pub struct LoginResponse {
+    token: String,
+    expires_at: DateTime<Utc>,
+    user: CurrentUser,
+}
+
+#[derive(sqlx::FromRow)]
+struct Credentials {
+    id: Uuid,
+    username: String,
+    role: String,
+    password_hash: String,
+}
+
+/// POST /api/auth/login - cria a sessao e devolve o token (tambem no cookie HttpOnly)
+pub async fn login(State(state): State<Arc<AppState>>, Json(body): Json<LoginBody>) -> ApiResult<Response> {
+    let creds = sqlx::query_as::<_, Credentials>(
+        "SELECT id, username, role, password_hash FROM users WHERE username = $1 AND NOT disabled")
+        .bind(body.username.trim())
+        .fetch_optional(&state.pg_pool).await?
+        .ok_or(ApiError::Unauthorized)?;
+    if !verify_password(body.password, creds.password_hash.clone()).await {
+        tracing::warn!("Falha de login para {}", creds.username);
+        return Err(ApiError::Unauthorized);
+    }
+    let user = UserRow { id: creds.id, username: creds.username, role: creds.role }.into_user()
+        .ok_or_else(|| ApiError::Internal(format!("Papel invalido para o usuario {}", body.username.trim())))?;
+
+    let token = new_token(SESSION_PREFIX);
+    let expires_at = Utc::now() + Duration::hours(SESSION_TTL_HOURS);
+    sqlx::query("DELETE FROM user_sessions WHERE expires_at < NOW()")
+        .execute(&state.pg_pool).await?;
+    sqlx::query("INSERT INTO user_sessions (token_hash, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(token_hash(&token)).bind(user.id).bind(expires_at)
+        .execute(&state.pg_pool).await?;
+    sqlx::query("UPDATE users SET last_login_at = NOW() WHERE id = $1")
+        .bind(user.id)
+        .execute(&state.pg_pool).await?;
+
+    let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}", SESSION_COOKIE, token, SESSION_TTL_HOURS * 3600);
+    Ok(([(header::SET_COOKIE, cookie)], Json(LoginResponse { token, expires_at, user })).into_response())
+}
+
+/// POST /api/auth/logout - encerra a sessao atual
+pub async fn logout(State(state): State<Arc<AppState>>, headers: HeaderMap) -> ApiResult<Response> {
+    if let Some(token) = request_token(&headers).filter(|t| t.starts_with(SESSION_PREFIX)) {
+        sqlx::query("DELETE FROM user_sessions WHERE token_hash = $1")
+            .bind(token_hash(&token))
+            .execute(&state.pg_pool).await?;
+    }
+    let cookie = format!("{}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0", SESSION_COOKIE);
+    Ok(([(header::SET_COOKIE, cookie)], http::StatusCode::NO_CONTENT).into_response())
+}
+
+/// GET /api/auth/me
+pub async fn me(user: CurrentUser) -> Json<CurrentUser> {
+    Json(user)
+}
+
+/// Cria o primeiro admin a partir de ADMIN_USERNAME/ADMIN_PASSWORD quando nao ha usuarios
+pub async fn bootstrap_admin(pool: &PgPool) -> anyhow::Result<()> {
+    let empty: bool = sqlx::query_scalar("SELECT NOT EXISTS (SELECT 1 FROM users)").fetch_one(pool).await?;
+    if !empty {
+        return Ok(());
+    }
+    let (Ok(username), Ok(password)) = (std::env::var("ADMIN_USERNAME"), std::env::var("ADMIN_PASSWORD")) else {
+        tracing::warn!("Nenhum usuario cadastrado: defina ADMIN_USERNAME e ADMIN_PASSWORD para criar o primeiro admin");
+        return Ok(());
+    };
+    if password.len() < MIN_PASSWORD_LEN {
+        anyhow::bail!("ADMIN_PASSWORD precisa de ao menos {} caracteres", MIN_PASSWORD_LEN);
+    }
+    let hash = hash_password(password).await.map_err(|_| anyhow::anyhow!("falha ao gerar hash da senha"))?;
+    sqlx::query("INSERT INTO users (id, username, password_hash, role) VALUES ($1, $2, $3, 'admin')")
+        .bind(Uuid::new_v4()).bind(username.trim()).bind(hash)
+        .execute(pool).await?;
+    tracing::info!("Usuario admin '{}' criado", username.trim());
+    Ok(())
+}
+
+/// Argon2id com salt aleatorio (fora do runtime async: o hash eh caro de proposito)
+pub async fn hash_password(password: String) -> ApiResult<String> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default().hash_password(password.as_bytes(), &salt).map(|h| h.to_string())
+    })
+    .await
+    .ok()
+    .and_then(Result::ok)
+    .ok_or_else(|| ApiError::Internal("Falha ao gerar hash da senha".to_string()))
+}
+
+async fn verify_password(password: String, hash: String) -> bool {
+    tokio::task::spawn_blocking(move || {
+        PasswordHash::new(&hash).is_ok_and(|h| Argon2::default().verify_password(password.as_bytes(), &h).is_ok())
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Token aleatorio de 256 bits com prefixo do tipo
+fn new_token(prefix: &str) -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", prefix, URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
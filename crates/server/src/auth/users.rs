@@ -0,0 +1,111 @@
+﻿use axum::{extract::{State, Path}, response::Json, http};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+use super::{Admin, Role, MIN_PASSWORD_LEN};
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct UserInfo {
+    id: Uuid,
+    username: String,
+    role: String,
+    disabled: bool,
+    created_at: DateTime<Utc>,
+    last_login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct NewUser {
+    username: String,
+    password: String,
+    role: Role,
+}
+
+/// Campos ausentes ficam como estao
+#[derive(Deserialize)]
+pub struct UserUpdate {
+    role: Option<Role>,
+    password: Option<String>,
+    disabled: Option<bool>,
+}
+
+const USER_COLUMNS: &str = "id, username, role, disabled, created_at, last_login_at";
+
+/// GET /api/users
+pub async fn list_users(_: Admin, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<UserInfo>>> {
+    let users = sqlx::query_as::<_, UserInfo>(&format!("SELECT {} FROM users ORDER BY username", USER_COLUMNS))
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(users))
+}
+
+/// POST /api/users
+pub async fn create_user(_: Admin, State(state): State<Arc<AppState>>, Json(body): Json<NewUser>) -> ApiResult<Json<UserInfo>> {
+    let username = body.username.trim();
+    let valid_name = !username.is_empty() && username.len() <= 64
+        && username.chars().all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | '@'));
+    if !valid_name {
+        return Err(ApiError::bad_request("username invalido"));
+    }
+    if body.password.len() < MIN_PASSWORD_LEN {
+        return Err(ApiError::bad_request(format!("a senha precisa de ao menos {} caracteres", MIN_PASSWORD_LEN)));
+    }
+    let hash = super::hash_password(body.password).await?;
+
+    let user = sqlx::query_as::<_, UserInfo>(&format!(
+        "INSERT INTO users (id, username, password_hash, role) VALUES ($1, $2, $3, $4) RETURNING {}", USER_COLUMNS))
+        .bind(Uuid::new_v4()).bind(username).bind(hash).bind(body.role.as_str())
+        .fetch_one(&state.pg_pool).await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => ApiError::conflict(format!("usuario '{}' ja existe", username)),
+            _ => ApiError::from(e),
+        })?;
+    Ok(Json(user))
+}
+
+/// PUT /api/users/:id - troca de senha, papel ou desativacao encerram as sessoes do usuario
+pub async fn update_user(
+    Admin(admin): Admin,
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<UserUpdate>,
+) -> ApiResult<Json<UserInfo>> {
+    // O admin nao pode se rebaixar nem se desativar (evita ficar sem nenhum admin)
+    if id == admin.id && (body.role.is_some_and(|r| r != Role::Admin) || body.disabled == Some(true)) {
+        return Err(ApiError::conflict("o admin nao pode rebaixar nem desativar a propria conta"));
+    }
+    let hash = match body.password {
+        Some(p) if p.len() < MIN_PASSWORD_LEN => {
+            return Err(ApiError::bad_request(format!("a senha precisa de ao menos {} caracteres", MIN_PASSWORD_LEN)));
+        }
+        Some(p) => Some(super::hash_password(p).await?),
+        None => None,
+    };
+
+    let mut tx = state.pg_pool.begin().await?;
+    let user = sqlx::query_as::<_, UserInfo>(&format!(
+        r#"UPDATE users SET role = COALESCE($2, role), password_hash = COALESCE($3, password_hash), disabled = COALESCE($4, disabled)
+           WHERE id = $1 RETURNING {}"#, USER_COLUMNS))
+        .bind(id).bind(body.role.map(Role::as_str)).bind(hash).bind(body.disabled)
+        .fetch_optional(&mut *tx).await?
+        .ok_or_else(|| ApiError::not_found("usuario"))?;
+    sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+        .bind(id)
+        .execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(Json(user))
+}
+
+/// DELETE /api/users/:id
+pub async fn delete_user(Admin(admin): Admin, Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> ApiResult<http::StatusCode> {
+    if id == admin.id {
+        return Err(ApiError::conflict("o admin nao pode excluir a propria conta"));
+    }
+    let result = sqlx::query("DELETE FROM users WHERE id = $1").bind(id).execute(&state.pg_pool).await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("usuario"));
+    }
+    Ok(http::StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,97 @@
+﻿// Converte um benchmark XCCDF (+ OVAL) para o YAML de politica SCA do Blue-Taurus
+//
+// Uso: xccdf_import <benchmark.xml> [--oval arquivo.xml]... [--policy-id id] [--first-id N] [-o saida.yaml]
+//
+// Documentos OVAL referenciados pelo benchmark (check-content-ref/@href) sao carregados
+// automaticamente quando existem ao lado do XCCDF.
+use shared::xccdf::{self, ImportOptions};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+struct Args {
+    benchmark: PathBuf,
+    oval: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    options: ImportOptions,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let mut benchmark = None;
+    let mut oval = Vec::new();
+    let mut output = None;
+    let mut options = ImportOptions::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} exige um valor", arg));
+        match arg.as_str() {
+            "--oval" => oval.push(PathBuf::from(value()?)),
+            "--policy-id" => options.policy_id = Some(value()?),
+            "--first-id" => options.first_rule_id = value()?.parse().map_err(|_| "--first-id deve ser numerico".to_string())?,
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "-h" | "--help" => return Err(String::new()),
+            _ if benchmark.is_none() && !arg.starts_with('-') => benchmark = Some(PathBuf::from(arg)),
+            _ => return Err(format!("argumento desconhecido: {}", arg)),
+        }
+    }
+
+    let benchmark = benchmark.ok_or("informe o arquivo XCCDF")?;
+    Ok(Args { benchmark, oval, output, options })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("Erro: {}", e);
+            }
+            eprintln!("Uso: xccdf_import <benchmark.xml> [--oval arquivo.xml]... [--policy-id id] [--first-id N] [-o saida.yaml]");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Erro: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    let xml = read(&args.benchmark)?;
+
+    let mut oval_paths = args.oval.clone();
+    let base = args.benchmark.parent().unwrap_or(Path::new("."));
+    for href in xccdf::oval_hrefs(&xml)? {
+        let path = base.join(&href);
+        if path.is_file() && !oval_paths.contains(&path) {
+            oval_paths.push(path);
+        }
+    }
+    let oval_xml = oval_paths.iter().map(|p| read(p)).collect::<anyhow::Result<Vec<_>>>()?;
+    let oval_refs: Vec<&str> = oval_xml.iter().map(String::as_str).collect();
+
+    let report = xccdf::import(&xml, &oval_refs, &args.options)?;
+    let yaml = serde_yaml::to_string(&report.policy)?;
+
+    match &args.output {
+        Some(path) => std::fs::write(path, yaml).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?,
+        None => print!("{}", yaml),
+    }
+
+    eprintln!("Politica '{}': {} de {} regras importadas", report.policy.id, report.policy.rules.len(), report.total_rules);
+    if !report.skipped.is_empty() {
+        eprintln!("Regras nao traduzidas:");
+        for rule in &report.skipped {
+            eprintln!("  - {} ({}): {}", rule.xccdf_id, rule.title, rule.reason);
+        }
+    }
+    Ok(())
+}
+
+fn read(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
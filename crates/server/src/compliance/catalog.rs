@@ -0,0 +1,48 @@
+﻿use shared::models::sca::{Policy, Rule};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Politicas SCA conhecidas pelo servidor (metadados das regras para relatorios e exportacoes)
+#[derive(Default)]
+pub struct PolicyCatalog {
+    policies: HashMap<String, Policy>,
+}
+
+impl PolicyCatalog {
+    /// Carrega todos os `*.yaml` do diretorio; arquivos invalidos sao ignorados com log
+    pub fn load(dir: &Path) -> Self {
+        let mut policies = HashMap::new();
+        let entries = match fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(e) => {
+                tracing::warn!("Diretorio de politicas {} indisponivel: {}", dir.display(), e);
+                return Self { policies };
+            }
+        };
+
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if !matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml")) { continue; }
+            match load_policy(&path) {
+                Ok(p) => {
+                    tracing::info!("Politica {} carregada ({} regras)", p.id, p.rules.len());
+                    policies.insert(p.id.clone(), p);
+                }
+                Err(e) => tracing::warn!("Ignorando {}: {}", path.display(), e),
+            }
+        }
+        Self { policies }
+    }
+
+    pub fn policy(&self, id: &str) -> Option<&Policy> {
+        self.policies.get(id)
+    }
+
+    pub fn rule(&self, policy_id: &str, rule_id: u32) -> Option<&Rule> {
+        self.policy(policy_id)?.rules.iter().find(|r| r.id == rule_id)
+    }
+}
+
+fn load_policy(path: &Path) -> anyhow::Result<Policy> {
+    Ok(serde_yaml::from_str(&fs::read_to_string(path)?)?)
+}
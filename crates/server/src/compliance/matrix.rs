@@ -0,0 +1,138 @@
+﻿use axum::{extract::{State, Path, Query}, response::Json};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::AppState;
+use crate::error::ApiResult;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Deserialize)]
+pub struct MatrixQuery {
+    os: Option<String>,        // Trecho do os_name (ILIKE)
+    severity: Option<String>,  // Lista separada por virgula: "high,critical"
+    group_id: Option<i32>,
+}
+
+/// Matriz regras x agentes da ultima varredura de cada agente para a politica
+#[derive(Serialize)]
+pub struct ComplianceMatrix {
+    policy_id: String,
+    agents: Vec<MatrixAgent>,
+    rules: Vec<MatrixRule>,
+    /// cells[i][j] = status da regra rules[i] no agente agents[j] (null se a regra nao foi avaliada)
+    cells: Vec<Vec<Option<String>>>,
+}
+
+#[derive(Serialize)]
+pub struct MatrixAgent {
+    id: Uuid,
+    hostname: String,
+    os_name: String,
+    score: Option<i32>,
+    scanned_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct MatrixRule {
+    rule_id: i32,
+    title: String,
+    severity: String,
+    pass: u32,
+    fail: u32,
+    error: u32,
+    not_applicable: u32,
+    /// Percentual de PASS entre os agentes onde a regra se aplica
+    pass_rate: f64,
+}
+
+#[derive(sqlx::FromRow)]
+struct CellRow {
+    agent_id: Uuid,
+    hostname: String,
+    os_name: String,
+    score: Option<i32>,
+    scanned_at: DateTime<Utc>,
+    rule_id: i32,
+    title: String,
+    severity: String,
+    status: String,
+}
+
+/// GET /api/compliance/matrix/:policy_id
+pub async fn compliance_matrix(
+    Path(policy_id): Path<String>,
+    Query(q): Query<MatrixQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Json<ComplianceMatrix>> {
+    let severities: Option<Vec<String>> = q.severity.as_ref().map(|s| {
+        s.split(',').map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty()).collect()
+    });
+
+    let agents = crate::groups::scope(&state.pg_pool, None, q.group_id).await?;
+
+    let rows = sqlx::query_as::<_, CellRow>(
+        r#"WITH latest AS (
+               SELECT DISTINCT ON (agent_id) id, agent_id, score, scanned_at
+               FROM compliance_scans
+               WHERE policy_id = $1
+               ORDER BY agent_id, scanned_at DESC
+           )
+           SELECT l.agent_id, a.hostname, a.os_name, l.score, l.scanned_at, r.rule_id, r.title, r.severity, r.status
+           FROM latest l
+           JOIN agents a ON a.id = l.agent_id
+           JOIN compliance_rule_results r ON r.scan_id = l.id
+           WHERE ($2::text IS NULL OR a.os_name ILIKE '%' || $2 || '%')
+             AND ($3::text[] IS NULL OR r.severity = ANY($3))
+             AND ($4::uuid[] IS NULL OR l.agent_id = ANY($4))
+           ORDER BY a.hostname, r.rule_id"#)
+        .bind(&policy_id)
+        .bind(&q.os)
+        .bind(&severities)
+        .bind(&agents)
+        .fetch_all(&state.pg_pool).await?;
+
+    Ok(Json(build_matrix(policy_id, rows)))
+}
+
+fn build_matrix(policy_id: String, rows: Vec<CellRow>) -> ComplianceMatrix {
+    let mut agents: Vec<MatrixAgent> = Vec::new();
+    let mut agent_idx: HashMap<Uuid, usize> = HashMap::new();
+    let mut rules: BTreeMap<i32, MatrixRule> = BTreeMap::new();
+
+    for row in &rows {
+        agent_idx.entry(row.agent_id).or_insert_with(|| {
+            agents.push(MatrixAgent { id: row.agent_id, hostname: row.hostname.clone(), os_name: row.os_name.clone(), score: row.score, scanned_at: row.scanned_at });
+            agents.len() - 1
+        });
+
+        let rule = rules.entry(row.rule_id).or_insert_with(|| MatrixRule {
+            rule_id: row.rule_id, title: row.title.clone(), severity: row.severity.clone(),
+            pass: 0, fail: 0, error: 0, not_applicable: 0, pass_rate: 0.0,
+        });
+        match row.status.as_str() {
+            "PASS" => rule.pass += 1,
+            "FAIL" => rule.fail += 1,
+            "NOT_APPLICABLE" => rule.not_applicable += 1,
+            _ => rule.error += 1,
+        }
+    }
+
+    // Linhas na ordem do id da regra, colunas na ordem do hostname
+    let rule_idx: HashMap<i32, usize> = rules.keys().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut cells = vec![vec![None; agents.len()]; rules.len()];
+    for row in rows {
+        cells[rule_idx[&row.rule_id]][agent_idx[&row.agent_id]] = Some(row.status);
+    }
+
+    let rules = rules.into_values().map(|mut rule| {
+        let applicable = rule.pass + rule.fail + rule.error;
+        rule.pass_rate = if applicable > 0 { rule.pass as f64 / applicable as f64 * 100.0 } else { 0.0 };
+        rule
+    }).collect();
+
+    ComplianceMatrix { policy_id, agents, rules, cells }
+}
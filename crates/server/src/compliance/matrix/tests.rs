@@ -0,0 +1,85 @@
+﻿use chrono::{TimeZone, Utc};
+use uuid::Uuid;
+use super::{build_matrix, CellRow};
+
+fn agent(n: u128) -> Uuid {
+    Uuid::from_u128(n)
+}
+
+fn cell(agent_id: Uuid, hostname: &str, rule_id: i32, status: &str) -> CellRow {
+    CellRow {
+        agent_id,
+        hostname: hostname.to_string(),
+        os_name: "Ubuntu 22.04".to_string(),
+        score: Some(50),
+        scanned_at: Utc.with_ymd_and_hms(2025, 12, 15, 10, 30, 0).unwrap(),
+        rule_id,
+        title: format!("Regra {}", rule_id),
+        severity: "high".to_string(),
+        status: status.to_string(),
+    }
+}
+
+#[test]
+fn cells_follow_rule_and_agent_order() {
+    let (a, b) = (agent(1), agent(2));
+    // Linhas chegam ordenadas por hostname e regra (ORDER BY da consulta)
+    let matrix = build_matrix("cis".to_string(), vec![
+        cell(a, "app-01", 20, "FAIL"),
+        cell(a, "app-01", 10, "PASS"),
+        cell(b, "web-01", 10, "NOT_APPLICABLE"),
+        cell(b, "web-01", 30, "ERROR"),
+    ]);
+
+    assert_eq!(matrix.agents.iter().map(|a| a.hostname.as_str()).collect::<Vec<_>>(), vec!["app-01", "web-01"]);
+    assert_eq!(matrix.rules.iter().map(|r| r.rule_id).collect::<Vec<_>>(), vec![10, 20, 30]);
+    let status = |s: &str| Some(s.to_string());
+    assert_eq!(matrix.cells, vec![
+        vec![status("PASS"), status("NOT_APPLICABLE")],
+        vec![status("FAIL"), None],
+        vec![None, status("ERROR")],
+    ]);
+}
+
+#[test]
+fn pass_rate_ignores_not_applicable() {
+    let rows = vec![
+        cell(agent(1), "a", 1, "PASS"),
+        cell(agent(2), "b", 1, "PASS"),
+        cell(agent(3), "c", 1, "FAIL"),
+        cell(agent(4), "d", 1, "NOT_APPLICABLE"),
+        cell(agent(5), "e", 1, "NOT_APPLICABLE"),
+    ];
+    let rule = &build_matrix("cis".to_string(), rows).rules[0];
+    assert_eq!((rule.pass, rule.fail, rule.error, rule.not_applicable), (2, 1, 0, 2));
+    assert!((rule.pass_rate - 200.0 / 3.0).abs() < 1e-9, "{}", rule.pass_rate);
+}
+
+#[test]
+fn errors_count_against_pass_rate() {
+    let rows = vec![
+        cell(agent(1), "a", 1, "PASS"),
+        cell(agent(2), "b", 1, "ERROR"),
+    ];
+    let rule = &build_matrix("cis".to_string(), rows).rules[0];
+    assert_eq!(rule.error, 1);
+    assert_eq!(rule.pass_rate, 50.0);
+}
+
+#[test]
+fn rule_without_applicable_agents_has_zero_pass_rate() {
+    let rows = vec![
+        cell(agent(1), "a", 1, "NOT_APPLICABLE"),
+        cell(agent(2), "b", 1, "NOT_APPLICABLE"),
+    ];
+    let rule = &build_matrix("cis".to_string(), rows).rules[0];
+    assert_eq!(rule.not_applicable, 2);
+    assert_eq!(rule.pass_rate, 0.0);
+}
+
+#[test]
+fn empty_matrix() {
+    let matrix = build_matrix("cis".to_string(), Vec::new());
+    assert_eq!(matrix.policy_id, "cis");
+    assert!(matrix.agents.is_empty() && matrix.rules.is_empty() && matrix.cells.is_empty());
+}
@@ -0,0 +1,232 @@
+﻿use axum::{extract::{State, Path, Query}, response::Json};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::postgres::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::models::sca::ComplianceReport;
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+
+mod catalog;
+mod matrix;
+pub use catalog::PolicyCatalog;
+pub use matrix::compliance_matrix;
+
+/// Filtros comuns das consultas de historico (padrao: ultimos 30 dias)
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    policy_id: Option<String>,
+    agent_id: Option<Uuid>,
+    group_id: Option<i32>,
+    bucket: Option<String>,   // "hour", "day" (padrao) ou "week"
+    group_by: Option<String>, // "policy" separa a serie por politica
+}
+
+impl HistoryQuery {
+    fn range(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let to = self.to.unwrap_or_else(Utc::now);
+        let from = self.from.unwrap_or(to - Duration::days(30));
+        (from, to)
+    }
+
+    fn bucket(&self) -> ApiResult<&str> {
+        match self.bucket.as_deref().unwrap_or("day") {
+            b @ ("hour" | "day" | "week") => Ok(b),
+            other => Err(ApiError::bad_request(format!("bucket invalido: {other} (hour, day ou week)"))),
+        }
+    }
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct ScanRow {
+    id: i64,
+    policy_id: String,
+    score: Option<i32>,
+    raw_score: Option<i32>,
+    total_checks: i32,
+    passed_checks: i32,
+    not_applicable: i32,
+    scanned_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct RuleResultRow {
+    scan_id: i64,
+    rule_id: i32,
+    title: String,
+    status: String,
+    severity: String,
+    output: Option<String>,
+    scanned_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct TrendPoint {
+    pub bucket: DateTime<Utc>,
+    pub policy_id: Option<String>,
+    pub avg_score: f64,
+    pub min_score: i32,
+    pub max_score: i32,
+    pub agents: i64,
+}
+
+/// Grava a varredura e o resultado de cada regra no historico
+pub async fn record_scan(pool: &PgPool, agent_id: Uuid, report: &ComplianceReport) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let scan_id: i64 = sqlx::query_scalar(
+        r#"INSERT INTO compliance_scans (agent_id, policy_id, score, raw_score, total_checks, passed_checks, not_applicable, scanned_at)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, NOW()) RETURNING id"#)
+        .bind(agent_id)
+        .bind(&report.policy_id)
+        .bind(report.score.map(|s| s as i32))
+        .bind(report.raw_score.map(|s| s as i32))
+        .bind(report.total_checks as i32)
+        .bind(report.passed_checks as i32)
+        .bind(report.not_applicable as i32)
+        .fetch_one(&mut *tx).await?;
+
+    // Insercao em lote via UNNEST (uma query por varredura)
+    let results = &report.results;
+    sqlx::query(
+        r#"INSERT INTO compliance_rule_results (scan_id, agent_id, policy_id, rule_id, title, status, severity, weight, output, duration_ms, scanned_at)
+           SELECT $1, $2, $3, r.rule_id, r.title, r.status, r.severity, r.weight, r.output, r.duration_ms, NOW()
+           FROM UNNEST($4::int[], $5::text[], $6::text[], $7::text[], $8::int[], $9::text[], $10::bigint[])
+                AS r(rule_id, title, status, severity, weight, output, duration_ms)"#)
+        .bind(scan_id)
+        .bind(agent_id)
+        .bind(&report.policy_id)
+        .bind(results.iter().map(|r| r.rule_id as i32).collect::<Vec<_>>())
+        .bind(results.iter().map(|r| r.title.clone()).collect::<Vec<_>>())
+        .bind(results.iter().map(|r| r.status.clone()).collect::<Vec<_>>())
+        .bind(results.iter().map(|r| r.severity.as_str().to_string()).collect::<Vec<_>>())
+        .bind(results.iter().map(|r| r.weight as i32).collect::<Vec<_>>())
+        .bind(results.iter().map(|r| r.output.clone()).collect::<Vec<_>>())
+        .bind(results.iter().map(|r| r.duration_ms as i64).collect::<Vec<_>>())
+        .execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(scan_id)
+}
+
+/// GET /api/agents/:id/compliance/history - varreduras do agente no periodo
+pub async fn agent_history(
+    Path(id): Path<Uuid>,
+    Query(q): Query<HistoryQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Json<Vec<ScanRow>>> {
+    let (from, to) = q.range();
+    let rows = sqlx::query_as::<_, ScanRow>(
+        r#"SELECT id, policy_id, score, raw_score, total_checks, passed_checks, not_applicable, scanned_at
+           FROM compliance_scans
+           WHERE agent_id = $1 AND scanned_at BETWEEN $2 AND $3 AND ($4::text IS NULL OR policy_id = $4)
+           ORDER BY scanned_at ASC"#)
+        .bind(id).bind(from).bind(to).bind(&q.policy_id)
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(rows))
+}
+
+/// GET /api/agents/:id/compliance/rules/:rule_id/history - evolucao de uma regra no agente
+pub async fn rule_history(
+    Path((id, rule_id)): Path<(Uuid, i32)>,
+    Query(q): Query<HistoryQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Json<Vec<RuleResultRow>>> {
+    let (from, to) = q.range();
+    let rows = sqlx::query_as::<_, RuleResultRow>(
+        r#"SELECT scan_id, rule_id, title, status, severity, output, scanned_at
+           FROM compliance_rule_results
+           WHERE agent_id = $1 AND rule_id = $2 AND scanned_at BETWEEN $3 AND $4 AND ($5::text IS NULL OR policy_id = $5)
+           ORDER BY scanned_at ASC"#)
+        .bind(id).bind(rule_id).bind(from).bind(to).bind(&q.policy_id)
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(rows))
+}
+
+/// GET /api/compliance/trend - score agregado por intervalo (frota, politica ou agente)
+pub async fn trend(
+    Query(q): Query<HistoryQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Json<Vec<TrendPoint>>> {
+    let (from, to) = q.range();
+    let bucket = q.bucket()?;
+    let by_policy = q.group_by.as_deref() == Some("policy");
+
+    let agents = crate::groups::scope(&state.pg_pool, q.agent_id, q.group_id).await?;
+
+    let rows = trend_points(&state.pg_pool, bucket, from, to, by_policy, q.policy_id.as_deref(), agents.as_deref()).await?;
+    Ok(Json(rows))
+}
+
+/// Score agregado por intervalo. Em cada intervalo vale a ultima varredura de cada agente,
+/// para nao pesar quem varre mais vezes. Varreduras sem score (nada aplicavel) ficam de fora.
+pub async fn trend_points(
+    pool: &PgPool,
+    bucket: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    by_policy: bool,
+    policy_id: Option<&str>,
+    agents: Option<&[Uuid]>,
+) -> Result<Vec<TrendPoint>, sqlx::Error> {
+    sqlx::query_as::<_, TrendPoint>(
+        r#"SELECT bucket, policy_id, AVG(score)::float8 AS avg_score, MIN(score) AS min_score, MAX(score) AS max_score, COUNT(*) AS agents
+           FROM (
+               SELECT DISTINCT ON (agent_id, series, bucket) agent_id, series AS policy_id, bucket, score
+               FROM (
+                   SELECT agent_id, score, scanned_at, date_trunc($1, scanned_at) AS bucket,
+                          CASE WHEN $4 THEN policy_id END AS series
+                   FROM compliance_scans
+                   WHERE scanned_at BETWEEN $2 AND $3 AND score IS NOT NULL
+                     AND ($5::text IS NULL OR policy_id = $5)
+                     AND ($6::uuid[] IS NULL OR agent_id = ANY($6))
+               ) s
+               ORDER BY agent_id, series, bucket, scanned_at DESC
+           ) latest
+           GROUP BY bucket, policy_id
+           ORDER BY bucket ASC, policy_id"#)
+        .bind(bucket).bind(from).bind(to).bind(by_policy).bind(policy_id).bind(agents)
+        .fetch_all(pool).await
+}
+
+/// Resultado de regra da ultima varredura de cada agente/politica (base de exportacoes e relatorios)
+#[derive(Serialize, sqlx::FromRow, Clone)]
+pub struct LatestResult {
+    pub agent_id: Uuid,
+    pub hostname: String,
+    pub os_name: String,
+    pub policy_id: String,
+    pub scan_id: i64,
+    pub score: Option<i32>,
+    pub scanned_at: DateTime<Utc>,
+    pub rule_id: i32,
+    pub title: String,
+    pub severity: String,
+    pub weight: i32,
+    pub status: String,
+    pub output: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+/// Ultima varredura de cada agente por politica, opcionalmente filtrada por agentes e politica
+pub async fn latest_results(pool: &PgPool, agents: Option<&[Uuid]>, policy_id: Option<&str>) -> Result<Vec<LatestResult>, sqlx::Error> {
+    sqlx::query_as::<_, LatestResult>(
+        r#"WITH latest AS (
+               SELECT DISTINCT ON (agent_id, policy_id) id, agent_id, policy_id, score, scanned_at
+               FROM compliance_scans
+               WHERE ($1::uuid[] IS NULL OR agent_id = ANY($1)) AND ($2::text IS NULL OR policy_id = $2)
+               ORDER BY agent_id, policy_id, scanned_at DESC
+           )
+           SELECT l.agent_id, a.hostname, a.os_name, l.policy_id, l.id AS scan_id, l.score, l.scanned_at,
+                  r.rule_id, r.title, r.severity, r.weight, r.status, r.output, r.duration_ms
+           FROM latest l
+           JOIN agents a ON a.id = l.agent_id
+           JOIN compliance_rule_results r ON r.scan_id = l.id
+           ORDER BY a.hostname, l.policy_id, r.rule_id"#)
+        .bind(agents)
+        .bind(policy_id)
+        .fetch_all(pool).await
+}
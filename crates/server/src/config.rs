@@ -0,0 +1,321 @@
+﻿//! Configuracao do servidor: arquivo YAML opcional + variaveis de ambiente.
+//!
+//! O arquivo vem de CONFIG_FILE (padrao `config.yaml` no diretorio atual, se existir);
+//! as variaveis de ambiente sobrepoem o arquivo. Tudo eh validado na subida e os
+//! problemas sao listados juntos, com a chave/variavel que precisa ser corrigida.
+//! Exemplo comentado em config.example.yaml na raiz do repositorio.
+
+use anyhow::Context;
+use serde::Deserialize;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub elastic: ElasticConfig,
+    pub retention: RetentionConfig,
+    pub features: FeatureConfig,
+    /// Chave privada do Admin para assinar comandos (ADMIN_PRIVATE_KEY)
+    pub admin_private_key: Option<String>,
+    /// Diretorio com os feeds NVD/OSV em JSON (VULN_FEED_DIR)
+    pub vuln_feed_dir: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// BIND_ADDR
+    pub bind: SocketAddr,
+    /// Dashboard e politicas SCA (ASSETS_DIR)
+    pub assets_dir: PathBuf,
+    /// HTTPS/WSS direto no servidor; sem isso espera-se um proxy na frente
+    pub tls: Option<TlsConfig>,
+    /// Prazo para drenar sockets e gravacoes no desligamento (SHUTDOWN_TIMEOUT_SECS)
+    pub shutdown_timeout_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Certificado PEM com a cadeia (TLS_CERT_FILE)
+    pub cert: PathBuf,
+    /// Chave privada PKCS#8 em PEM (TLS_KEY_FILE)
+    pub key: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// DATABASE_URL
+    pub url: String,
+    /// DB_MAX_CONNECTIONS
+    pub max_connections: u32,
+    /// DB_MIN_CONNECTIONS
+    pub min_connections: u32,
+    /// Espera maxima por uma conexao livre (DB_ACQUIRE_TIMEOUT_SECS)
+    pub acquire_timeout_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ElasticConfig {
+    /// ELASTIC_URL
+    pub url: String,
+    /// Relatorios SCA e alertas de drift (ELASTIC_LOGS_INDEX)
+    pub logs_index: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// Dias sem contato ate o agente ficar stale (AGENT_STALE_DAYS)
+    pub agent_stale_days: i32,
+    /// Dias que um agente descomissionado fica guardado antes da purga (AGENT_RETENTION_DAYS)
+    pub agent_retention_days: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureConfig {
+    /// Encadeia os hashes da trilha de auditoria (AUDIT_HASH_CHAIN)
+    pub audit_hash_chain: bool,
+    /// Importa o feed de vulnerabilidades na subida se a base estiver vazia (VULN_AUTO_IMPORT)
+    pub vuln_auto_import: bool,
+    /// Alertas de drift tambem via webhook (DRIFT_WEBHOOK_URL)
+    pub drift_webhook_url: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: ServerConfig::default(),
+            database: DatabaseConfig::default(),
+            elastic: ElasticConfig::default(),
+            retention: RetentionConfig::default(),
+            features: FeatureConfig::default(),
+            admin_private_key: None,
+            vuln_feed_dir: "vulndb".into(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        // 0.0.0.0 para Docker
+        Self { bind: SocketAddr::from(([0, 0, 0, 0], 3000)), assets_dir: "assets".into(), tls: None, shutdown_timeout_secs: 30 }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self { url: String::new(), max_connections: 10, min_connections: 0, acquire_timeout_secs: 30 }
+    }
+}
+
+impl Default for ElasticConfig {
+    fn default() -> Self {
+        Self { url: String::new(), logs_index: "bt-logs-v1".to_string() }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self { agent_stale_days: 7, agent_retention_days: 30 }
+    }
+}
+
+impl Default for FeatureConfig {
+    fn default() -> Self {
+        Self { audit_hash_chain: false, vuln_auto_import: true, drift_webhook_url: None }
+    }
+}
+
+impl Config {
+    /// Le arquivo + ambiente e valida; o erro lista todos os problemas encontrados
+    pub fn load() -> anyhow::Result<Self> {
+        let explicit = std::env::var("CONFIG_FILE").ok();
+        let path = PathBuf::from(explicit.as_deref().unwrap_or("config.yaml"));
+        let mut config = if path.exists() {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("nao foi possivel ler o arquivo de configuracao {}", path.display()))?;
+            serde_yaml::from_str(&text)
+                .with_context(|| format!("arquivo de configuracao {} invalido", path.display()))?
+        } else if explicit.is_some() {
+            anyhow::bail!("CONFIG_FILE aponta para {}, que nao existe", path.display());
+        } else {
+            Config::default()
+        };
+
+        let mut errors = Vec::new();
+        config.apply_env(&mut errors);
+        config.validate(&mut errors);
+        if !errors.is_empty() {
+            anyhow::bail!("configuracao invalida:\n  - {}", errors.join("\n  - "));
+        }
+        Ok(config)
+    }
+
+    fn apply_env(&mut self, errors: &mut Vec<String>) {
+        env_parse("BIND_ADDR", &mut self.server.bind, errors);
+        env_parse("ASSETS_DIR", &mut self.server.assets_dir, errors);
+        tls_pair(env_var("TLS_CERT_FILE"), env_var("TLS_KEY_FILE"), &mut self.server.tls, errors);
+        env_parse("SHUTDOWN_TIMEOUT_SECS", &mut self.server.shutdown_timeout_secs, errors);
+
+        env_parse("DATABASE_URL", &mut self.database.url, errors);
+        env_parse("DB_MAX_CONNECTIONS", &mut self.database.max_connections, errors);
+        env_parse("DB_MIN_CONNECTIONS", &mut self.database.min_connections, errors);
+        env_parse("DB_ACQUIRE_TIMEOUT_SECS", &mut self.database.acquire_timeout_secs, errors);
+
+        env_parse("ELASTIC_URL", &mut self.elastic.url, errors);
+        env_parse("ELASTIC_LOGS_INDEX", &mut self.elastic.logs_index, errors);
+
+        env_parse("AGENT_STALE_DAYS", &mut self.retention.agent_stale_days, errors);
+        env_parse("AGENT_RETENTION_DAYS", &mut self.retention.agent_retention_days, errors);
+
+        env_bool("AUDIT_HASH_CHAIN", &mut self.features.audit_hash_chain, errors);
+        env_bool("VULN_AUTO_IMPORT", &mut self.features.vuln_auto_import, errors);
+        if let Some(url) = env_var("DRIFT_WEBHOOK_URL") {
+            self.features.drift_webhook_url = Some(url);
+        }
+
+        if let Some(key) = env_var("ADMIN_PRIVATE_KEY") {
+            self.admin_private_key = Some(key);
+        }
+        env_parse("VULN_FEED_DIR", &mut self.vuln_feed_dir, errors);
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        check_url(errors, "database.url / DATABASE_URL", &self.database.url, &["postgres", "postgresql"]);
+        check_url(errors, "elastic.url / ELASTIC_URL", &self.elastic.url, &["http", "https"]);
+        if let Some(url) = &self.features.drift_webhook_url {
+            check_url(errors, "features.drift_webhook_url / DRIFT_WEBHOOK_URL", url, &["http", "https"]);
+        }
+
+        let db = &self.database;
+        if db.max_connections == 0 {
+            errors.push("database.max_connections / DB_MAX_CONNECTIONS deve ser maior que zero".to_string());
+        }
+        if db.min_connections > db.max_connections {
+            errors.push(format!(
+                "database.min_connections ({}) maior que database.max_connections ({})",
+                db.min_connections, db.max_connections
+            ));
+        }
+        if db.acquire_timeout_secs == 0 {
+            errors.push("database.acquire_timeout_secs / DB_ACQUIRE_TIMEOUT_SECS deve ser maior que zero".to_string());
+        }
+
+        if let Err(e) = check_index_name(&self.elastic.logs_index) {
+            errors.push(format!("elastic.logs_index / ELASTIC_LOGS_INDEX \"{}\": {}", self.elastic.logs_index, e));
+        }
+
+        let retention = &self.retention;
+        if retention.agent_stale_days < 1 {
+            errors.push("retention.agent_stale_days / AGENT_STALE_DAYS deve ser pelo menos 1".to_string());
+        }
+        if retention.agent_retention_days < 0 {
+            errors.push("retention.agent_retention_days / AGENT_RETENTION_DAYS nao pode ser negativo".to_string());
+        }
+
+        if let Some(tls) = &self.server.tls {
+            check_file(errors, "server.tls.cert / TLS_CERT_FILE", &tls.cert);
+            check_file(errors, "server.tls.key / TLS_KEY_FILE", &tls.key);
+        }
+        if self.server.shutdown_timeout_secs == 0 {
+            errors.push("server.shutdown_timeout_secs / SHUTDOWN_TIMEOUT_SECS deve ser maior que zero".to_string());
+        }
+        if !self.server.assets_dir.is_dir() {
+            errors.push(format!("server.assets_dir / ASSETS_DIR: diretorio {} nao encontrado", self.server.assets_dir.display()));
+        }
+    }
+}
+
+/// URL sem a senha, para logs e mensagens de erro
+pub fn redact(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut u) if u.password().is_some() => {
+            let _ = u.set_password(Some("***"));
+            u.to_string()
+        }
+        Ok(u) => u.to_string(),
+        Err(_) => "<url invalida>".to_string(),
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn env_parse<T: FromStr>(name: &str, target: &mut T, errors: &mut Vec<String>)
+where
+    T::Err: Display,
+{
+    if let Some(value) = env_var(name) {
+        match value.trim().parse() {
+            Ok(v) => *target = v,
+            Err(e) => errors.push(format!("{}=\"{}\" invalido: {}", name, value, e)),
+        }
+    }
+}
+
+fn env_bool(name: &str, target: &mut bool, errors: &mut Vec<String>) {
+    if let Some(value) = env_var(name) {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => *target = true,
+            "false" | "0" | "no" | "off" => *target = false,
+            _ => errors.push(format!("{}=\"{}\" invalido: use true ou false", name, value)),
+        }
+    }
+}
+
+/// Certificado e chave so valem juntos; sem nenhum dos dois mantem o valor do arquivo
+fn tls_pair(cert: Option<String>, key: Option<String>, target: &mut Option<TlsConfig>, errors: &mut Vec<String>) {
+    match (cert, key) {
+        (Some(cert), Some(key)) => *target = Some(TlsConfig { cert: cert.into(), key: key.into() }),
+        (None, None) => {}
+        _ => errors.push("TLS_CERT_FILE e TLS_KEY_FILE devem ser definidas juntas".to_string()),
+    }
+}
+
+fn check_url(errors: &mut Vec<String>, key: &str, url: &str, schemes: &[&str]) {
+    if url.is_empty() {
+        errors.push(format!("{} nao definida", key));
+        return;
+    }
+    match reqwest::Url::parse(url) {
+        Ok(u) if schemes.contains(&u.scheme()) => {}
+        Ok(u) => errors.push(format!("{}: esquema \"{}\" nao suportado (use {})", key, u.scheme(), schemes.join(" ou "))),
+        Err(e) => errors.push(format!("{}: URL invalida ({})", key, e)),
+    }
+}
+
+fn check_file(errors: &mut Vec<String>, key: &str, path: &Path) {
+    if !path.is_file() {
+        errors.push(format!("{}: arquivo {} nao encontrado", key, path.display()));
+    }
+}
+
+/// Regras de nome de indice do Elasticsearch
+fn check_index_name(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() {
+        return Err("vazio");
+    }
+    if name != name.to_lowercase() {
+        return Err("deve ser minusculo");
+    }
+    if name.starts_with(['-', '_', '+']) {
+        return Err("nao pode comecar com -, _ ou +");
+    }
+    if name.contains(['\\', '/', '*', '?', '"', '<', '>', '|', ' ', ',', '#', ':']) {
+        return Err("contem caractere proibido");
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+﻿use super::{check_index_name, env_bool, tls_pair, Config};
+
+/// Configuracao que passa na validacao; cada teste estraga um campo
+fn valid() -> Config {
+    let mut config = Config::default();
+    config.database.url = "postgres://bt@localhost/bt".to_string();
+    config.elastic.url = "http://localhost:9200".to_string();
+    config.server.assets_dir = env!("CARGO_MANIFEST_DIR").into();
+    config
+}
+
+fn errors_of(config: &Config) -> Vec<String> {
+    let mut errors = Vec::new();
+    config.validate(&mut errors);
+    errors
+}
+
+#[test]
+fn valid_config_has_no_errors() {
+    assert_eq!(errors_of(&valid()), Vec::<String>::new());
+}
+
+#[test]
+fn missing_database_url_is_reported() {
+    let mut config = valid();
+    config.database.url = String::new();
+    assert_eq!(errors_of(&config), vec!["database.url / DATABASE_URL nao definida".to_string()]);
+}
+
+#[test]
+fn min_connections_above_max_is_reported() {
+    let mut config = valid();
+    config.database.min_connections = 20;
+    let errors = errors_of(&config);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("min_connections (20)") && errors[0].contains("max_connections (10)"), "{errors:?}");
+}
+
+#[test]
+fn uppercase_index_name_is_reported() {
+    let mut config = valid();
+    config.elastic.logs_index = "BT-Logs".to_string();
+    let errors = errors_of(&config);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("ELASTIC_LOGS_INDEX \"BT-Logs\": deve ser minusculo"), "{errors:?}");
+}
+
+#[test]
+fn errors_are_collected_together() {
+    let mut config = valid();
+    config.database.url = String::new();
+    config.database.min_connections = 20;
+    config.elastic.logs_index = "BT".to_string();
+    config.retention.agent_stale_days = 0;
+    assert_eq!(errors_of(&config).len(), 4);
+}
+
+#[test]
+fn index_name_rules() {
+    assert_eq!(check_index_name("bt-logs-v1"), Ok(()));
+    assert_eq!(check_index_name(""), Err("vazio"));
+    assert_eq!(check_index_name("Logs"), Err("deve ser minusculo"));
+    assert_eq!(check_index_name("_logs"), Err("nao pode comecar com -, _ ou +"));
+    assert_eq!(check_index_name("bt logs"), Err("contem caractere proibido"));
+}
+
+#[test]
+fn tls_cert_without_key_is_reported() {
+    let mut tls = None;
+    let mut errors = Vec::new();
+    tls_pair(Some("cert.pem".to_string()), None, &mut tls, &mut errors);
+    assert!(tls.is_none());
+    assert_eq!(errors, vec!["TLS_CERT_FILE e TLS_KEY_FILE devem ser definidas juntas".to_string()]);
+
+    errors.clear();
+    tls_pair(Some("cert.pem".to_string()), Some("key.pem".to_string()), &mut tls, &mut errors);
+    assert!(errors.is_empty());
+    assert_eq!(tls.map(|t| t.key), Some("key.pem".into()));
+}
+
+#[test]
+fn tls_files_must_exist() {
+    let mut config = valid();
+    tls_pair(Some("/nao/existe/cert.pem".to_string()), Some("/nao/existe/key.pem".to_string()), &mut config.server.tls, &mut Vec::new());
+    let errors = errors_of(&config);
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(errors[0].starts_with("server.tls.cert / TLS_CERT_FILE"));
+}
+
+#[test]
+fn env_bool_accepts_common_spellings() {
+    // nomes exclusivos deste teste: o ambiente eh compartilhado entre as threads de teste
+    let mut errors = Vec::new();
+    let mut flag = false;
+    std::env::set_var("BT_TEST_ENV_BOOL_ON", " Yes ");
+    env_bool("BT_TEST_ENV_BOOL_ON", &mut flag, &mut errors);
+    assert!(flag);
+
+    std::env::set_var("BT_TEST_ENV_BOOL_OFF", "0");
+    env_bool("BT_TEST_ENV_BOOL_OFF", &mut flag, &mut errors);
+    assert!(!flag);
+
+    env_bool("BT_TEST_ENV_BOOL_UNSET", &mut flag, &mut errors);
+    assert!(!flag);
+    assert!(errors.is_empty());
+
+    std::env::set_var("BT_TEST_ENV_BOOL_BAD", "talvez");
+    env_bool("BT_TEST_ENV_BOOL_BAD", &mut flag, &mut errors);
+    assert!(!flag);
+    assert_eq!(errors, vec!["BT_TEST_ENV_BOOL_BAD=\"talvez\" invalido: use true ou false".to_string()]);
+}
@@ -0,0 +1,193 @@
+﻿use axum::{extract::{State, Query}, response::Json};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::models::sca::{CheckResult, ComplianceReport};
+use crate::AppState;
+use crate::error::ApiResult;
+
+mod notifier;
+#[cfg(test)]
+mod tests;
+pub use notifier::{Notifier, LogNotifier, ElasticNotifier, WebhookNotifier, EventBusNotifier};
+
+/// Mudanca de status de uma regra entre duas varreduras do mesmo agente/politica
+#[derive(Debug, Serialize, Clone)]
+pub struct DriftEvent {
+    pub rule_id: u32,
+    pub title: String,
+    pub severity: String,
+    pub before_status: String,
+    pub after_status: String,
+    pub kind: String,         // "REGRESSION", "IMPROVEMENT", "SCOPE" ou "CHANGE"
+    pub output_diff: String,  // Linhas removidas (-) e adicionadas (+)
+}
+
+/// Alerta entregue aos notificadores (somente regressoes)
+#[derive(Debug, Serialize, Clone)]
+pub struct DriftAlert {
+    pub agent_id: Uuid,
+    pub hostname: Option<String>,
+    pub policy_id: String,
+    pub scan_id: i64,
+    pub events: Vec<DriftEvent>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PreviousResult {
+    rule_id: i32,
+    status: String,
+    output: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DriftQuery {
+    agent_id: Option<Uuid>,
+    policy_id: Option<String>,
+    kind: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct DriftRow {
+    id: i64,
+    agent_id: Uuid,
+    hostname: Option<String>,
+    policy_id: String,
+    rule_id: i32,
+    title: String,
+    severity: String,
+    before_status: String,
+    after_status: String,
+    kind: String,
+    output_diff: Option<String>,
+    previous_scan_id: i64,
+    scan_id: i64,
+    detected_at: DateTime<Utc>,
+}
+
+/// Compara a varredura `scan_id` com a anterior do agente, grava os eventos e dispara alertas
+pub async fn check_drift(state: &AppState, agent_id: Uuid, report: &ComplianceReport, scan_id: i64) -> Result<(), sqlx::Error> {
+    let previous_scan: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM compliance_scans WHERE agent_id = $1 AND policy_id = $2 AND id < $3 ORDER BY id DESC LIMIT 1")
+        .bind(agent_id).bind(&report.policy_id).bind(scan_id)
+        .fetch_optional(&state.pg_pool).await?;
+
+    // Primeira varredura desta politica: nada para comparar
+    let Some(previous_scan) = previous_scan else { return Ok(()) };
+
+    let previous = sqlx::query_as::<_, PreviousResult>(
+        "SELECT rule_id, status, output FROM compliance_rule_results WHERE scan_id = $1")
+        .bind(previous_scan)
+        .fetch_all(&state.pg_pool).await?;
+
+    let events = detect(&previous, &report.results);
+    if events.is_empty() { return Ok(()); }
+
+    let mut tx = state.pg_pool.begin().await?;
+    for ev in &events {
+        sqlx::query(
+            r#"INSERT INTO drift_events (agent_id, policy_id, rule_id, title, severity, before_status, after_status, kind, output_diff, previous_scan_id, scan_id)
+               VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"#)
+            .bind(agent_id)
+            .bind(&report.policy_id)
+            .bind(ev.rule_id as i32)
+            .bind(&ev.title)
+            .bind(&ev.severity)
+            .bind(&ev.before_status)
+            .bind(&ev.after_status)
+            .bind(&ev.kind)
+            .bind(&ev.output_diff)
+            .bind(previous_scan)
+            .bind(scan_id)
+            .execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    let regressions: Vec<DriftEvent> = events.into_iter().filter(|e| e.kind == "REGRESSION").collect();
+    if regressions.is_empty() { return Ok(()); }
+
+    let hostname: Option<String> = sqlx::query_scalar("SELECT hostname FROM agents WHERE id = $1")
+        .bind(agent_id).fetch_optional(&state.pg_pool).await?;
+
+    // Entrega fora do caminho do relatorio: um webhook lento nao segura o socket do agente.
+    // O guard faz o desligamento esperar as entregas em andamento.
+    let alert = Arc::new(DriftAlert { agent_id, hostname, policy_id: report.policy_id.clone(), scan_id, events: regressions });
+    for notifier in &state.notifiers {
+        let (notifier, alert, guard) = (notifier.clone(), alert.clone(), state.shutdown.track());
+        tokio::spawn(async move {
+            if let Err(e) = notifier.notify(&alert).await {
+                tracing::error!("Notificador {} falhou: {}", notifier.name(), e);
+            }
+            drop(guard);
+        });
+    }
+    Ok(())
+}
+
+/// Regras cujo status mudou em relacao a varredura anterior.
+/// Entrar ou sair de NOT_APPLICABLE eh mudanca de escopo (SCOPE), nunca regressao.
+fn detect(previous: &[PreviousResult], current: &[CheckResult]) -> Vec<DriftEvent> {
+    let before: HashMap<u32, &PreviousResult> = previous.iter().map(|p| (p.rule_id as u32, p)).collect();
+
+    current.iter().filter_map(|cur| {
+        let prev = before.get(&cur.rule_id)?;
+        if prev.status == cur.status { return None; }
+
+        let kind = if prev.status == "NOT_APPLICABLE" || cur.status == "NOT_APPLICABLE" {
+            "SCOPE"
+        } else if prev.status == "PASS" {
+            "REGRESSION"
+        } else if cur.status == "PASS" {
+            "IMPROVEMENT"
+        } else {
+            "CHANGE"
+        };
+
+        Some(DriftEvent {
+            rule_id: cur.rule_id,
+            title: cur.title.clone(),
+            severity: cur.severity.as_str().to_string(),
+            before_status: prev.status.clone(),
+            after_status: cur.status.clone(),
+            kind: kind.to_string(),
+            output_diff: line_diff(prev.output.as_deref().unwrap_or(""), &cur.output),
+        })
+    }).collect()
+}
+
+/// Diff simples por linha: o que saiu da saida anterior (-) e o que entrou (+)
+fn line_diff(before: &str, after: &str) -> String {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    old.iter().filter(|l| !new.contains(l)).map(|l| format!("- {}", l))
+        .chain(new.iter().filter(|l| !old.contains(l)).map(|l| format!("+ {}", l)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// GET /api/drift - eventos de drift mais recentes (padrao: ultimos 7 dias, 200 eventos)
+pub async fn list_drift(Query(q): Query<DriftQuery>, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<DriftRow>>> {
+    let to = q.to.unwrap_or_else(Utc::now);
+    let from = q.from.unwrap_or(to - Duration::days(7));
+    let limit = q.limit.unwrap_or(200).clamp(1, 1000);
+
+    let rows = sqlx::query_as::<_, DriftRow>(
+        r#"SELECT d.id, d.agent_id, a.hostname, d.policy_id, d.rule_id, d.title, d.severity, d.before_status, d.after_status,
+                  d.kind, d.output_diff, d.previous_scan_id, d.scan_id, d.detected_at
+           FROM drift_events d
+           LEFT JOIN agents a ON a.id = d.agent_id
+           WHERE d.detected_at BETWEEN $1 AND $2
+             AND ($3::uuid IS NULL OR d.agent_id = $3)
+             AND ($4::text IS NULL OR d.policy_id = $4)
+             AND ($5::text IS NULL OR d.kind = $5)
+           ORDER BY d.detected_at DESC
+           LIMIT $6"#)
+        .bind(from).bind(to).bind(q.agent_id).bind(&q.policy_id).bind(&q.kind).bind(limit)
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(rows))
+}
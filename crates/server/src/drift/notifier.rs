@@ -0,0 +1,106 @@
+﻿use futures::future::BoxFuture;
+use elasticsearch::Elasticsearch;
+use std::time::Duration;
+use crate::events::{AgentEvent, EventBus};
+use super::DriftAlert;
+
+/// Destino de alertas de drift. Novos canais (email, Slack, SIEM) implementam este trait
+/// e sao registrados em `AppState::notifiers`.
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+    fn notify<'a>(&'a self, alert: &'a DriftAlert) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Registra o alerta no log do servidor
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn name(&self) -> &str { "log" }
+
+    fn notify<'a>(&'a self, alert: &'a DriftAlert) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            for ev in &alert.events {
+                tracing::warn!("Drift em {} ({}): regra {} {} -> {} [{}]",
+                    alert.hostname.as_deref().unwrap_or("?"), alert.agent_id, ev.rule_id, ev.before_status, ev.after_status, ev.severity);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Indexa o alerta no Elastic (event_type = compliance_drift) para o Kibana/SIEM
+pub struct ElasticNotifier {
+    client: Elasticsearch,
+    index: String,
+}
+
+impl ElasticNotifier {
+    pub fn new(client: Elasticsearch, index: &str) -> Self {
+        Self { client, index: index.to_string() }
+    }
+}
+
+impl Notifier for ElasticNotifier {
+    fn name(&self) -> &str { "elastic" }
+
+    fn notify<'a>(&'a self, alert: &'a DriftAlert) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut doc = serde_json::to_value(alert)?;
+            if let Some(obj) = doc.as_object_mut() {
+                obj.insert("@timestamp".to_string(), serde_json::json!(chrono::Utc::now()));
+                obj.insert("event_type".to_string(), serde_json::json!("compliance_drift"));
+            }
+            self.client.index(elasticsearch::IndexParts::Index(&self.index)).body(doc).send().await?.error_for_status_code()?;
+            Ok(())
+        })
+    }
+}
+
+/// Prazo de uma entrega ao webhook, incluindo conexao
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Envia o alerta em JSON para um webhook (DRIFT_WEBHOOK_URL)
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build()?;
+        Ok(Self { client, url: url.to_string() })
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str { "webhook" }
+
+    fn notify<'a>(&'a self, alert: &'a DriftAlert) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.client.post(&self.url).json(alert).send().await?.error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// Publica o alerta no barramento de eventos (stream /api/events do dashboard)
+pub struct EventBusNotifier {
+    bus: EventBus,
+}
+
+impl EventBusNotifier {
+    pub fn new(bus: EventBus) -> Self {
+        Self { bus }
+    }
+}
+
+impl Notifier for EventBusNotifier {
+    fn name(&self) -> &str { "events" }
+
+    fn notify<'a>(&'a self, alert: &'a DriftAlert) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.bus.publish(AgentEvent::Alert(alert.clone()));
+            Ok(())
+        })
+    }
+}
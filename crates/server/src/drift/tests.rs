@@ -0,0 +1,90 @@
+﻿use shared::models::sca::{CheckResult, Severity};
+use super::{detect, line_diff, PreviousResult};
+
+fn previous(rule_id: i32, status: &str, output: Option<&str>) -> PreviousResult {
+    PreviousResult { rule_id, status: status.to_string(), output: output.map(str::to_string) }
+}
+
+fn current(rule_id: u32, status: &str, output: &str) -> CheckResult {
+    CheckResult {
+        rule_id,
+        title: format!("Regra {}", rule_id),
+        status: status.to_string(),
+        output: output.to_string(),
+        duration_ms: 0,
+        severity: Severity::High,
+        weight: Severity::High.weight(),
+    }
+}
+
+fn kinds(previous: &[PreviousResult], current: &[CheckResult]) -> Vec<(u32, String)> {
+    detect(previous, current).into_iter().map(|e| (e.rule_id, e.kind)).collect()
+}
+
+#[test]
+fn classifies_status_transitions() {
+    let before = [
+        previous(1, "PASS", None),
+        previous(2, "FAIL", None),
+        previous(3, "FAIL", None),
+        previous(4, "PASS", None),
+        previous(5, "ERROR", None),
+    ];
+    let after = [
+        current(1, "FAIL", ""),
+        current(2, "PASS", ""),
+        current(3, "ERROR", ""),
+        current(4, "ERROR", ""),
+        current(5, "PASS", ""),
+    ];
+    assert_eq!(kinds(&before, &after), vec![
+        (1, "REGRESSION".to_string()),
+        (2, "IMPROVEMENT".to_string()),
+        (3, "CHANGE".to_string()),
+        (4, "REGRESSION".to_string()),
+        (5, "IMPROVEMENT".to_string()),
+    ]);
+}
+
+#[test]
+fn not_applicable_is_a_scope_change_not_a_regression() {
+    let before = [previous(1, "PASS", None), previous(2, "NOT_APPLICABLE", None), previous(3, "FAIL", None)];
+    let after = [current(1, "NOT_APPLICABLE", ""), current(2, "FAIL", ""), current(3, "NOT_APPLICABLE", "")];
+    let events = kinds(&before, &after);
+    assert_eq!(events.len(), 3);
+    assert!(events.iter().all(|(_, kind)| kind == "SCOPE"), "{:?}", events);
+}
+
+#[test]
+fn unchanged_and_new_rules_are_ignored() {
+    let before = [previous(1, "PASS", Some("a")), previous(2, "FAIL", None)];
+    // Regra 1 mudou so a saida; regra 3 nao existia na varredura anterior
+    let after = [current(1, "PASS", "b"), current(2, "FAIL", ""), current(3, "FAIL", "")];
+    assert!(detect(&before, &after).is_empty());
+}
+
+#[test]
+fn event_carries_statuses_severity_and_output_diff() {
+    let before = [previous(7, "PASS", Some("PermitRootLogin no\nPort 22"))];
+    let after = [current(7, "FAIL", "PermitRootLogin yes\nPort 22")];
+    let events = detect(&before, &after);
+    assert_eq!(events.len(), 1);
+    let ev = &events[0];
+    assert_eq!((ev.before_status.as_str(), ev.after_status.as_str()), ("PASS", "FAIL"));
+    assert_eq!(ev.severity, "high");
+    assert_eq!(ev.title, "Regra 7");
+    assert_eq!(ev.output_diff, "- PermitRootLogin no\n+ PermitRootLogin yes");
+}
+
+#[test]
+fn line_diff_lists_removed_then_added_lines() {
+    assert_eq!(line_diff("a\nb\nc", "a\nc\nd"), "- b\n+ d");
+    assert_eq!(line_diff("", "x\ny"), "+ x\n+ y");
+    assert_eq!(line_diff("x\ny", ""), "- x\n- y");
+    assert_eq!(line_diff("igual", "igual"), "");
+}
+
+#[test]
+fn line_diff_ignores_reordering() {
+    assert_eq!(line_diff("a\nb", "b\na"), "");
+}
@@ -0,0 +1,148 @@
+﻿//! Erro padrao da API. Cada variante vira um status HTTP e um corpo JSON:
+//!
+//!   { "error": "not_found", "message": "agente nao encontrado" }
+//!
+//! Falhas de banco nunca viram lista vazia: sao logadas e respondidas como 500/503.
+
+use axum::{http::StatusCode, response::{IntoResponse, Json, Response}};
+use serde::Serialize;
+use std::fmt;
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// Parametro, filtro ou corpo invalido
+    BadRequest(String),
+    Unauthorized,
+    Forbidden,
+    NotFound(String),
+    /// Estado atual impede a acao (agente offline, bloqueado, registro duplicado...)
+    Conflict(String),
+    /// Corpo acima do limite aceito
+    PayloadTooLarge(String),
+    /// Dependencia indisponivel (Postgres sem conexao, Elastic, chave de assinatura)
+    Unavailable(String),
+    Database(sqlx::Error),
+    Internal(String),
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::BadRequest(message.into())
+    }
+
+    pub fn not_found(what: &str) -> Self {
+        Self::NotFound(format!("{what} nao encontrado"))
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::Conflict(message.into())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Database(e) => match db_kind(e) {
+                DbKind::NotFound => StatusCode::NOT_FOUND,
+                DbKind::Conflict => StatusCode::CONFLICT,
+                DbKind::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+                DbKind::Other => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "bad_request",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+            Self::NotFound(_) => "not_found",
+            Self::Conflict(_) => "conflict",
+            Self::PayloadTooLarge(_) => "payload_too_large",
+            Self::Unavailable(_) => "unavailable",
+            Self::Database(e) => match db_kind(e) {
+                DbKind::NotFound => "not_found",
+                DbKind::Conflict => "conflict",
+                DbKind::Unavailable => "database_unavailable",
+                DbKind::Other => "database_error",
+            },
+            Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadRequest(m) | Self::NotFound(m) | Self::Conflict(m) | Self::PayloadTooLarge(m)
+            | Self::Unavailable(m) | Self::Internal(m) => f.write_str(m),
+            Self::Unauthorized => f.write_str("autenticacao necessaria"),
+            Self::Forbidden => f.write_str("papel insuficiente para esta acao"),
+            // Detalhes do Postgres ficam so no log
+            Self::Database(e) => match db_kind(e) {
+                DbKind::NotFound => f.write_str("registro nao encontrado"),
+                DbKind::Conflict => f.write_str("registro conflita com um existente"),
+                DbKind::Unavailable => f.write_str("banco de dados indisponivel"),
+                DbKind::Other => f.write_str("erro interno no banco de dados"),
+            },
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        match &self {
+            Self::Database(e) => tracing::error!("Erro Postgres: {}", e),
+            Self::Internal(m) | Self::Unavailable(m) => tracing::error!("{}", m),
+            _ => {}
+        }
+        let body = ErrorBody { error: self.code(), message: self.to_string() };
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(format!("{e:#}"))
+    }
+}
+
+enum DbKind {
+    NotFound,
+    Conflict,
+    Unavailable,
+    Other,
+}
+
+fn db_kind(e: &sqlx::Error) -> DbKind {
+    match e {
+        sqlx::Error::RowNotFound => DbKind::NotFound,
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) | sqlx::Error::Tls(_) => DbKind::Unavailable,
+        // 23505 unique_violation, 23503 foreign_key_violation
+        sqlx::Error::Database(d) if matches!(d.code().as_deref(), Some("23505" | "23503")) => DbKind::Conflict,
+        _ => DbKind::Other,
+    }
+}
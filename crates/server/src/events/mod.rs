@@ -0,0 +1,126 @@
+﻿//! Barramento interno de eventos dos agentes e stream para os operadores.
+//!
+//! O socket dos agentes publica entradas/saidas, relatorios SCA e resultados de
+//! comandos; os alertas de drift chegam pelo `drift::EventBusNotifier`. Cada
+//! cliente de `/api/events` assina o barramento e recebe server-sent events com
+//! o tipo no campo `event` e o JSON em `data`.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use axum::extract::{Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+use crate::drift::DriftAlert;
+use crate::AppState;
+
+/// Eventos retidos por assinante; quem ficar mais atrasado recebe "lagged"
+const CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    AgentOnline { agent_id: Uuid, hostname: String, os_name: String },
+    AgentOffline { agent_id: Uuid },
+    ScaReport { agent_id: Uuid, policy_id: String, score: Option<i32>, scan_id: i64 },
+    /// Resultado de comando ou remediacao (agent_id ausente se o socket nao fez handshake)
+    CommandResult { agent_id: Option<Uuid>, cmd_id: Uuid, status: String },
+    /// Regressao de compliance (mesmo conteudo entregue aos notificadores de drift)
+    Alert(DriftAlert),
+}
+
+impl AgentEvent {
+    /// Nome do evento SSE, igual ao campo "type" do JSON
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AgentEvent::AgentOnline { .. } => "agent_online",
+            AgentEvent::AgentOffline { .. } => "agent_offline",
+            AgentEvent::ScaReport { .. } => "sca_report",
+            AgentEvent::CommandResult { .. } => "command_result",
+            AgentEvent::Alert(_) => "alert",
+        }
+    }
+
+    pub fn agent_id(&self) -> Option<Uuid> {
+        match self {
+            AgentEvent::AgentOnline { agent_id, .. }
+            | AgentEvent::AgentOffline { agent_id }
+            | AgentEvent::ScaReport { agent_id, .. } => Some(*agent_id),
+            AgentEvent::CommandResult { agent_id, .. } => *agent_id,
+            AgentEvent::Alert(alert) => Some(alert.agent_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Published {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: AgentEvent,
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<Published>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self { tx: broadcast::channel(CAPACITY).0 }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, event: AgentEvent) {
+        // Erro = nenhum operador conectado; o evento eh descartado
+        let _ = self.tx.send(Published { at: Utc::now(), event });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Published> {
+        self.tx.subscribe()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EventQuery {
+    agent_id: Option<Uuid>,
+}
+
+/// Stream SSE dos eventos, opcionalmente de um unico agente
+pub async fn stream(
+    Query(q): Query<EventQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let agent_id = q.agent_id;
+    let shutdown = state.shutdown.clone();
+    let events = stream::unfold(state.events.subscribe(), move |mut rx| {
+        let shutdown = shutdown.clone();
+        async move {
+            loop {
+                // No desligamento o stream termina para o HTTP poder drenar; o navegador reconecta
+                let received = tokio::select! {
+                    received = rx.recv() => received,
+                    _ = shutdown.stopped() => return None,
+                };
+                let event = match received {
+                    Ok(p) if agent_id.is_some_and(|id| p.event.agent_id() != Some(id)) => continue,
+                    Ok(p) => match Event::default().event(p.event.kind()).json_data(&p) {
+                        Ok(event) => event,
+                        Err(e) => {
+                            tracing::error!("Evento {} nao serializado: {}", p.event.kind(), e);
+                            continue;
+                        }
+                    },
+                    // O cliente perdeu eventos e deve recarregar o estado pela API
+                    Err(RecvError::Lagged(skipped)) => Event::default().event("lagged").data(skipped.to_string()),
+                    Err(RecvError::Closed) => return None,
+                };
+                return Some((Ok(event), rx));
+            }
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,49 @@
+﻿use super::ExportRecord;
+
+const HEADER: &[&str] = &[
+    "agent_id", "hostname", "os_name", "policy_id", "scan_id", "scanned_at", "rule_id", "title", "severity",
+    "weight", "status", "duration_ms", "references", "tags", "remediation", "output",
+];
+
+/// Uma linha por regra/agente (RFC 4180)
+pub fn render(records: &[ExportRecord]) -> String {
+    let mut out = String::new();
+    out.push_str(&HEADER.join(","));
+    out.push_str("\r\n");
+
+    for rec in records {
+        let r = &rec.result;
+        let references = rec.references.iter().map(|x| format!("{} {}", x.framework, x.id)).collect::<Vec<_>>().join("; ");
+        let fields = [
+            r.agent_id.to_string(),
+            r.hostname.clone(),
+            r.os_name.clone(),
+            r.policy_id.clone(),
+            r.scan_id.to_string(),
+            r.scanned_at.to_rfc3339(),
+            r.rule_id.to_string(),
+            r.title.clone(),
+            r.severity.clone(),
+            r.weight.to_string(),
+            r.status.clone(),
+            r.duration_ms.map(|d| d.to_string()).unwrap_or_default(),
+            references,
+            rec.tags.join("; "),
+            rec.remediation.clone().unwrap_or_default(),
+            r.output.clone().unwrap_or_default(),
+        ];
+        out.push_str(&fields.iter().map(|f| escape(f)).collect::<Vec<_>>().join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Aspas quando necessario e protecao contra injecao de formulas em planilhas
+fn escape(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@']) { format!("'{}", field) } else { field.to_string() };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
@@ -0,0 +1,106 @@
+﻿use axum::{extract::{State, Path, Query}, response::{IntoResponse, Response}, http};
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::models::sca::Reference;
+use crate::compliance::{self, LatestResult, PolicyCatalog};
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+
+mod csv;
+mod oscal;
+mod sarif;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    format: Option<String>,   // "json" (padrao), "csv", "sarif" ou "oscal"
+    policy_id: Option<String>,
+    group_id: Option<i32>,    // Somente na exportacao da frota
+}
+
+/// Linha exportada: resultado da regra + metadados da politica
+#[derive(Serialize)]
+pub struct ExportRecord {
+    #[serde(flatten)]
+    result: LatestResult,
+    description: Option<String>,
+    rationale: Option<String>,
+    remediation: Option<String>,
+    references: Vec<Reference>,
+    tags: Vec<String>,
+}
+
+impl ExportRecord {
+    fn new(result: LatestResult, catalog: &PolicyCatalog) -> Self {
+        let rule = catalog.rule(&result.policy_id, result.rule_id as u32);
+        Self {
+            description: rule.and_then(|r| r.description.clone()),
+            rationale: rule.and_then(|r| r.rationale.clone()),
+            remediation: rule.and_then(|r| r.remediation.clone()),
+            references: rule.map(|r| r.references.clone()).unwrap_or_default(),
+            tags: rule.map(|r| r.tags.clone()).unwrap_or_default(),
+            result,
+        }
+    }
+}
+
+/// GET /api/agents/:id/compliance/export - ultima varredura do agente
+pub async fn export_agent(
+    Path(id): Path<Uuid>,
+    Query(q): Query<ExportQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Response> {
+    export(&state, Some(id), q).await
+}
+
+/// GET /api/compliance/export - ultima varredura de todos os agentes
+pub async fn export_fleet(Query(q): Query<ExportQuery>, State(state): State<Arc<AppState>>) -> ApiResult<Response> {
+    export(&state, None, q).await
+}
+
+async fn export(state: &AppState, agent_id: Option<Uuid>, q: ExportQuery) -> ApiResult<Response> {
+    let format = q.format.as_deref().unwrap_or("json");
+    if !matches!(format, "json" | "csv" | "sarif" | "oscal") {
+        return Err(ApiError::bad_request(format!("formato invalido: {format} (json, csv, sarif ou oscal)")));
+    }
+
+    let agents = crate::groups::scope(&state.pg_pool, agent_id, q.group_id).await?;
+    let rows = compliance::latest_results(&state.pg_pool, agents.as_deref(), q.policy_id.as_deref()).await?;
+    if agent_id.is_some() && rows.is_empty() {
+        return Err(ApiError::NotFound("agente sem varreduras".to_string()));
+    }
+
+    let generated_at = Utc::now();
+    let records: Vec<ExportRecord> = rows.into_iter().map(|r| ExportRecord::new(r, &state.policies)).collect();
+
+    let (content_type, body) = match format {
+        "csv" => ("text/csv; charset=utf-8", csv::render(&records)),
+        "sarif" => ("application/sarif+json", sarif::render(&records, generated_at).to_string()),
+        "oscal" => ("application/json", oscal::render(&records, generated_at).to_string()),
+        _ => {
+            let doc = serde_json::json!({
+                "generated_at": generated_at,
+                "agent_id": agent_id,
+                "policy_id": q.policy_id,
+                "results": records,
+            });
+            ("application/json", doc.to_string())
+        }
+    };
+
+    let scope = agent_id.map_or("fleet".to_string(), |id| id.to_string());
+    let extension = if format == "oscal" { "oscal.json" } else { format };
+    let filename = format!("blue-taurus-compliance-{}-{}.{}", scope, generated_at.format("%Y%m%d%H%M%S"), extension);
+
+    Ok((
+        [
+            (http::header::CONTENT_TYPE, content_type.to_string()),
+            (http::header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    ).into_response())
+}
@@ -0,0 +1,174 @@
+﻿use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use super::ExportRecord;
+
+pub const OSCAL_VERSION: &str = "1.1.2";
+
+/// Namespace das UUIDs deterministicas (inventario e observacoes)
+const NAMESPACE: &str = "urn:blue-taurus";
+const PROP_NS: &str = "urn:blue-taurus:oscal";
+
+/// OSCAL Assessment Results: cada agente vira um inventory-item, cada regra/agente uma observation
+/// e cada controle NIST SP 800-53 referenciado pelas regras um finding.
+pub fn render(records: &[ExportRecord], generated_at: DateTime<Utc>) -> Value {
+    let mut inventory: BTreeMap<Uuid, Value> = BTreeMap::new();
+    let mut observations: Vec<Value> = Vec::new();
+    let mut controls: BTreeMap<String, Control> = BTreeMap::new();
+
+    for rec in records {
+        let r = &rec.result;
+        let item_uuid = stable_uuid(&format!("agent:{}", r.agent_id));
+        inventory.entry(item_uuid).or_insert_with(|| json!({
+            "uuid": item_uuid,
+            "description": format!("Agente {} ({})", r.hostname, r.os_name),
+            "props": [
+                prop("asset-id", &r.agent_id.to_string()),
+                prop("fqdn", &r.hostname),
+                prop("asset-type", "operating-system"),
+            ],
+        }));
+
+        let obs_uuid = stable_uuid(&format!("scan:{}:rule:{}", r.scan_id, r.rule_id));
+        let mut observation = json!({
+            "uuid": obs_uuid,
+            "title": r.title,
+            "description": rec.description.clone().unwrap_or_else(|| r.title.clone()),
+            "props": [
+                custom_prop("policy-id", &r.policy_id),
+                custom_prop("rule-id", &r.rule_id.to_string()),
+                custom_prop("severity", &r.severity),
+                custom_prop("status", &r.status),
+            ],
+            "methods": ["TEST"],
+            "subjects": [{ "subject-uuid": item_uuid, "type": "inventory-item", "title": r.hostname }],
+            "collected": timestamp(r.scanned_at),
+        });
+        if let Some(output) = r.output.as_deref().map(str::trim).filter(|o| !o.is_empty()) {
+            observation["relevant-evidence"] = json!([{ "description": output }]);
+        }
+        observations.push(observation);
+
+        // NOT_APPLICABLE entra como observacao mas nao pesa no estado do controle
+        if r.status == "NOT_APPLICABLE" {
+            continue;
+        }
+        for control_id in rec.references.iter().filter_map(|reference| nist_control_id(&reference.framework, &reference.id)) {
+            let control = controls.entry(control_id).or_default();
+            control.observations.push(obs_uuid);
+            control.satisfied &= r.status == "PASS";
+            if !control.rules.contains(&r.title) {
+                control.rules.push(r.title.clone());
+            }
+        }
+    }
+
+    let findings: Vec<Value> = controls.iter().map(|(control_id, control)| json!({
+        "uuid": Uuid::new_v4(),
+        "title": format!("Controle {}", control_id.to_uppercase()),
+        "description": format!("Avaliado pelas regras: {}", control.rules.join("; ")),
+        "target": {
+            "type": "objective-id",
+            "target-id": format!("{}_obj", control_id),
+            "status": { "state": if control.satisfied { "satisfied" } else { "not-satisfied" } },
+        },
+        "related-observations": control.observations.iter()
+            .map(|uuid| json!({ "observation-uuid": uuid }))
+            .collect::<Vec<_>>(),
+    })).collect();
+
+    let control_selection = if controls.is_empty() {
+        json!({ "include-all": {} })
+    } else {
+        json!({ "include-controls": controls.keys().map(|id| json!({ "control-id": id })).collect::<Vec<_>>() })
+    };
+
+    let start = records.iter().map(|r| r.result.scanned_at).min().unwrap_or(generated_at);
+    let end = records.iter().map(|r| r.result.scanned_at).max().unwrap_or(generated_at);
+
+    let mut result = json!({
+        "uuid": Uuid::new_v4(),
+        "title": "Varredura SCA Blue-Taurus",
+        "description": "Resultados da ultima varredura SCA de cada agente.",
+        "start": timestamp(start),
+        "end": timestamp(end),
+        "reviewed-controls": { "control-selections": [control_selection] },
+    });
+    // Arrays OSCAL exigem ao menos um item: listas vazias sao omitidas
+    if !inventory.is_empty() {
+        result["local-definitions"] = json!({ "inventory-items": inventory.into_values().collect::<Vec<_>>() });
+    }
+    if !observations.is_empty() {
+        result["observations"] = json!(observations);
+    }
+    if !findings.is_empty() {
+        result["findings"] = json!(findings);
+    }
+
+    json!({
+        "assessment-results": {
+            "uuid": Uuid::new_v4(),
+            "metadata": {
+                "title": "Blue-Taurus SCA - Assessment Results",
+                "last-modified": timestamp(generated_at),
+                "version": shared::version(),
+                "oscal-version": OSCAL_VERSION,
+            },
+            "import-ap": {
+                "href": "#",
+                "remarks": "Plano de avaliacao implicito: politicas SCA carregadas no servidor.",
+            },
+            "results": [result],
+        }
+    })
+}
+
+struct Control {
+    satisfied: bool,
+    observations: Vec<Uuid>,
+    rules: Vec<String>,
+}
+
+impl Default for Control {
+    fn default() -> Self {
+        Self { satisfied: true, observations: Vec::new(), rules: Vec::new() }
+    }
+}
+
+/// Converte referencias NIST SP 800-53 para o formato de id dos catalogos OSCAL ("AC-2(1)" -> "ac-2.1")
+pub fn nist_control_id(framework: &str, id: &str) -> Option<String> {
+    let framework = framework.to_lowercase();
+    if !framework.contains("800-53") {
+        return None;
+    }
+    let id = id.trim().to_lowercase().replace('(', ".").replace(')', "");
+    let (family, number) = id.split_once('-')?;
+    let valid = family.len() == 2 && family.chars().all(|c| c.is_ascii_lowercase())
+        && !number.is_empty() && number.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    valid.then_some(id)
+}
+
+/// Propriedade do namespace padrao OSCAL
+fn prop(name: &str, value: &str) -> Value {
+    json!({ "name": name, "value": prop_value(value) })
+}
+
+/// Propriedade propria do Blue-Taurus
+fn custom_prop(name: &str, value: &str) -> Value {
+    json!({ "name": name, "ns": PROP_NS, "value": prop_value(value) })
+}
+
+/// Valores de prop nao podem ser vazios nem ter espacos nas pontas
+fn prop_value(value: &str) -> &str {
+    let value = value.trim();
+    if value.is_empty() { "-" } else { value }
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn stable_uuid(name: &str) -> Uuid {
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, format!("{}:{}", NAMESPACE, name).as_bytes())
+}
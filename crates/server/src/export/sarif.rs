@@ -0,0 +1,95 @@
+﻿use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use super::ExportRecord;
+
+/// SARIF 2.1.0: uma regra por (politica, regra) e um resultado por regra/agente.
+/// Cada agente aparece como logicalLocation do tipo "resource".
+pub fn render(records: &[ExportRecord], generated_at: DateTime<Utc>) -> Value {
+    let mut rules: Vec<Value> = Vec::new();
+    let mut rule_index: HashMap<String, usize> = HashMap::new();
+    let mut results: Vec<Value> = Vec::new();
+
+    for rec in records {
+        let r = &rec.result;
+        let id = format!("{}/{}", r.policy_id, r.rule_id);
+
+        let index = *rule_index.entry(id.clone()).or_insert_with(|| {
+            rules.push(json!({
+                "id": id,
+                "name": r.title,
+                "shortDescription": { "text": r.title },
+                "fullDescription": { "text": rec.description.clone().unwrap_or_else(|| r.title.clone()) },
+                "help": { "text": rec.remediation.clone().unwrap_or_default() },
+                "defaultConfiguration": { "level": level(&r.severity) },
+                "properties": {
+                    "severity": r.severity,
+                    "weight": r.weight,
+                    "rationale": rec.rationale,
+                    "references": rec.references,
+                    "tags": rec.tags,
+                    "policy_id": r.policy_id,
+                },
+            }));
+            rules.len() - 1
+        });
+
+        let kind = match r.status.as_str() {
+            "PASS" => "pass",
+            "FAIL" => "fail",
+            "NOT_APPLICABLE" => "notApplicable",
+            _ => "review",
+        };
+
+        let mut result = json!({
+            "ruleId": id,
+            "ruleIndex": index,
+            "kind": kind,
+            "message": { "text": format!("{}: {} em {}", r.status, r.title, r.hostname) },
+            "locations": [{
+                "logicalLocations": [{
+                    "name": r.hostname,
+                    "fullyQualifiedName": format!("agent/{}", r.agent_id),
+                    "kind": "resource",
+                }],
+            }],
+            "properties": {
+                "agent_id": r.agent_id,
+                "hostname": r.hostname,
+                "os_name": r.os_name,
+                "scan_id": r.scan_id,
+                "scanned_at": r.scanned_at,
+                "status": r.status,
+                "output": r.output,
+                "duration_ms": r.duration_ms,
+            },
+        });
+        // level so tem significado para kind = fail
+        result["level"] = json!(if kind == "fail" { level(&r.severity) } else { "none" });
+        results.push(result);
+    }
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "Blue-Taurus SCA",
+                    "version": shared::version(),
+                    "rules": rules,
+                },
+            },
+            "invocations": [{ "executionSuccessful": true, "endTimeUtc": generated_at }],
+            "results": results,
+        }],
+    })
+}
+
+fn level(severity: &str) -> &'static str {
+    match severity {
+        "critical" | "high" => "error",
+        "medium" => "warning",
+        _ => "note",
+    }
+}
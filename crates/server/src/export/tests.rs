@@ -0,0 +1,228 @@
+﻿use chrono::{TimeZone, Utc};
+use jsonschema::{Draft, JSONSchema};
+use serde_json::Value;
+use shared::models::sca::Reference;
+use uuid::Uuid;
+use crate::compliance::LatestResult;
+use super::{csv, oscal, sarif, ExportRecord};
+
+const OSCAL_AR_SCHEMA: &str = include_str!("../../schemas/oscal_assessment-results_schema.json");
+
+fn record(agent_id: Uuid, rule_id: i32, status: &str, references: &[(&str, &str)]) -> ExportRecord {
+    ExportRecord {
+        result: LatestResult {
+            agent_id,
+            hostname: format!("host-{}", &agent_id.to_string()[..8]),
+            os_name: "Ubuntu 22.04".to_string(),
+            policy_id: "cis_linux_baseline".to_string(),
+            scan_id: 42,
+            score: Some(50),
+            scanned_at: Utc.with_ymd_and_hms(2025, 12, 15, 10, 30, 0).unwrap(),
+            rule_id,
+            title: format!("Regra {}", rule_id),
+            severity: "high".to_string(),
+            weight: 7,
+            status: status.to_string(),
+            output: Some(format!("result={}\n", status.to_lowercase())),
+            duration_ms: Some(12),
+        },
+        description: Some("Descricao da regra".to_string()),
+        rationale: None,
+        remediation: Some("sed -i ...".to_string()),
+        references: references.iter()
+            .map(|(framework, id)| Reference { framework: framework.to_string(), id: id.to_string() })
+            .collect(),
+        tags: vec!["ssh".to_string()],
+    }
+}
+
+fn sample() -> Vec<ExportRecord> {
+    let a = Uuid::new_v4();
+    let b = Uuid::new_v4();
+    vec![
+        record(a, 2001, "PASS", &[("CIS", "5.2.1"), ("NIST-800-53", "AC-2(1)")]),
+        record(a, 2002, "FAIL", &[("NIST SP 800-53", "SC-7")]),
+        record(a, 2003, "NOT_APPLICABLE", &[("NIST-800-53", "AU-2")]),
+        record(b, 2001, "PASS", &[("CIS", "5.2.1"), ("NIST-800-53", "AC-2(1)")]),
+        record(b, 2002, "PASS", &[("NIST SP 800-53", "SC-7")]),
+        record(b, 2010, "FAIL", &[]),
+    ]
+}
+
+fn assert_valid(doc: &Value) {
+    let schema: Value = serde_json::from_str(OSCAL_AR_SCHEMA).unwrap();
+    let compiled = JSONSchema::options().with_draft(Draft::Draft7).compile(&schema).unwrap();
+    if let Err(errors) = compiled.validate(doc) {
+        let errors: Vec<String> = errors.map(|e| format!("{} em {}", e, e.instance_path)).collect();
+        panic!("documento OSCAL invalido:\n{}", errors.join("\n"));
+    };
+}
+
+fn findings(doc: &Value) -> &Vec<Value> {
+    doc["assessment-results"]["results"][0]["findings"].as_array().unwrap()
+}
+
+fn finding<'a>(doc: &'a Value, control_id: &str) -> Option<&'a Value> {
+    let target = format!("{}_obj", control_id);
+    findings(doc).iter().find(|f| f["target"]["target-id"] == target.as_str())
+}
+
+#[test]
+fn oscal_export_matches_schema() {
+    let doc = oscal::render(&sample(), Utc::now());
+    assert_valid(&doc);
+}
+
+#[test]
+fn empty_oscal_export_matches_schema() {
+    let doc = oscal::render(&[], Utc::now());
+    assert_valid(&doc);
+
+    let result = &doc["assessment-results"]["results"][0];
+    assert!(result.get("observations").is_none());
+    assert!(result["reviewed-controls"]["control-selections"][0].get("include-all").is_some());
+}
+
+#[test]
+fn every_check_result_becomes_an_observation() {
+    let records = sample();
+    let doc = oscal::render(&records, Utc::now());
+    let result = &doc["assessment-results"]["results"][0];
+
+    assert_eq!(result["observations"].as_array().unwrap().len(), records.len());
+    assert_eq!(result["local-definitions"]["inventory-items"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn findings_follow_rule_status_per_control() {
+    let doc = oscal::render(&sample(), Utc::now());
+
+    // Apenas referencias NIST viram controles; CIS e regras sem referencia ficam so como observacao
+    assert_eq!(findings(&doc).len(), 2);
+    assert_eq!(finding(&doc, "ac-2.1").unwrap()["target"]["status"]["state"], "satisfied");
+    assert_eq!(finding(&doc, "sc-7").unwrap()["target"]["status"]["state"], "not-satisfied");
+    assert_eq!(finding(&doc, "sc-7").unwrap()["related-observations"].as_array().unwrap().len(), 2);
+
+    // NOT_APPLICABLE nao gera finding
+    assert!(finding(&doc, "au-2").is_none());
+}
+
+#[test]
+fn nist_references_are_normalized_to_oscal_control_ids() {
+    assert_eq!(oscal::nist_control_id("NIST-800-53", "AC-2(1)").as_deref(), Some("ac-2.1"));
+    assert_eq!(oscal::nist_control_id("nist sp 800-53 rev5", " SC-7 ").as_deref(), Some("sc-7"));
+    assert_eq!(oscal::nist_control_id("CIS", "5.2.1"), None);
+    assert_eq!(oscal::nist_control_id("NIST-800-53", "invalido"), None);
+}
+
+#[test]
+#[should_panic(expected = "documento OSCAL invalido")]
+fn schema_rejects_broken_documents() {
+    let mut doc = oscal::render(&sample(), Utc::now());
+    doc["assessment-results"]["results"][0]["findings"][0]["target"]["status"]["state"] = "pass".into();
+    assert_valid(&doc);
+}
+
+/// Linhas de dados do CSV (sem cabecalho); campos com quebra de linha ficam entre aspas
+fn csv_rows(records: &[ExportRecord]) -> Vec<String> {
+    let out = csv::render(records);
+    assert!(out.starts_with("agent_id,hostname,os_name,policy_id,"));
+    out.split("\r\n").skip(1).filter(|l| !l.is_empty()).map(str::to_string).collect()
+}
+
+#[test]
+fn csv_has_one_row_per_record() {
+    assert_eq!(csv_rows(&sample()).len(), 6);
+    assert!(csv_rows(&[]).is_empty());
+}
+
+#[test]
+fn csv_neutralizes_formula_prefixes() {
+    for title in ["=1+1", "+1", "-1", "@SUM(A1)"] {
+        let mut rec = record(Uuid::new_v4(), 1, "FAIL", &[]);
+        rec.result.title = title.to_string();
+        let row = &csv_rows(&[rec])[0];
+        assert!(row.contains(&format!(",'{},", title)), "{} nao foi neutralizado: {}", title, row);
+    }
+    // Prefixo no meio do campo nao eh formula
+    let mut rec = record(Uuid::new_v4(), 1, "FAIL", &[]);
+    rec.result.title = "a=b".to_string();
+    assert!(csv_rows(&[rec])[0].contains(",a=b,"));
+}
+
+#[test]
+fn csv_quotes_separators_quotes_and_newlines() {
+    let mut rec = record(Uuid::new_v4(), 1, "FAIL", &[("CIS", "1.1"), ("NIST-800-53", "AC-2")]);
+    rec.result.title = "Senha, \"forte\"".to_string();
+    rec.result.output = Some("linha 1\nlinha 2".to_string());
+    rec.remediation = None;
+    let row = &csv_rows(&[rec])[0];
+
+    assert!(row.contains(r#","Senha, ""forte""","#), "{}", row);
+    assert!(row.contains(",CIS 1.1; NIST-800-53 AC-2,"), "{}", row);
+    // Remediacao ausente vira campo vazio; saida com quebra de linha fica entre aspas
+    assert!(row.ends_with(",ssh,,\"linha 1\nlinha 2\""), "{}", row);
+}
+
+#[test]
+fn csv_formula_guard_runs_before_quoting() {
+    let mut rec = record(Uuid::new_v4(), 1, "FAIL", &[]);
+    rec.result.output = Some("=HYPERLINK(\"http://x\", \"y\")".to_string());
+    let row = &csv_rows(&[rec])[0];
+    assert!(row.ends_with(r#","'=HYPERLINK(""http://x"", ""y"")""#), "{}", row);
+}
+
+#[test]
+fn sarif_kind_and_level_follow_status_and_severity() {
+    let agent = Uuid::new_v4();
+    let with_severity = |rule_id, status, severity: &str| {
+        let mut rec = record(agent, rule_id, status, &[]);
+        rec.result.severity = severity.to_string();
+        rec
+    };
+    let records = vec![
+        with_severity(1, "FAIL", "critical"),
+        with_severity(2, "FAIL", "high"),
+        with_severity(3, "FAIL", "medium"),
+        with_severity(4, "FAIL", "low"),
+        with_severity(5, "PASS", "critical"),
+        with_severity(6, "NOT_APPLICABLE", "high"),
+        with_severity(7, "ERROR", "high"),
+    ];
+    let doc = sarif::render(&records, Utc::now());
+    let results = doc["runs"][0]["results"].as_array().unwrap();
+    let got: Vec<(&str, &str)> = results.iter()
+        .map(|r| (r["kind"].as_str().unwrap(), r["level"].as_str().unwrap()))
+        .collect();
+    assert_eq!(got, vec![
+        ("fail", "error"),
+        ("fail", "error"),
+        ("fail", "warning"),
+        ("fail", "note"),
+        ("pass", "none"),
+        ("notApplicable", "none"),
+        ("review", "none"),
+    ]);
+    // A regra guarda o nivel padrao da severidade, independente do resultado
+    assert_eq!(doc["runs"][0]["tool"]["driver"]["rules"][4]["defaultConfiguration"]["level"], "error");
+}
+
+#[test]
+fn sarif_rules_are_shared_between_agents() {
+    let doc = sarif::render(&sample(), Utc::now());
+    assert_eq!(doc["version"], "2.1.0");
+    let run = &doc["runs"][0];
+    let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+    let results = run["results"].as_array().unwrap();
+
+    let ids: Vec<&str> = rules.iter().map(|r| r["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["cis_linux_baseline/2001", "cis_linux_baseline/2002", "cis_linux_baseline/2003", "cis_linux_baseline/2010"]);
+    assert_eq!(results.len(), 6);
+    for result in results {
+        let index = result["ruleIndex"].as_u64().unwrap() as usize;
+        assert_eq!(rules[index]["id"], result["ruleId"]);
+        let location = &result["locations"][0]["logicalLocations"][0];
+        assert_eq!(location["kind"], "resource");
+        assert_eq!(location["fullyQualifiedName"], format!("agent/{}", result["properties"]["agent_id"].as_str().unwrap()));
+    }
+}
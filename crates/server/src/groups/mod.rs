@@ -0,0 +1,249 @@
+﻿use axum::{extract::{State, Path}, response::Json, http};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::postgres::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::{AgentRow, AppState};
+use crate::auth::{Admin, Analyst};
+use crate::error::{ApiError, ApiResult};
+
+pub mod policies;
+mod query;
+#[cfg(test)]
+mod tests;
+
+/// Tamanho maximo de uma tag
+const MAX_TAG_LEN: usize = 64;
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct AgentGroup {
+    id: i32,
+    name: String,
+    description: Option<String>,
+    query: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct GroupBody {
+    name: String,
+    description: Option<String>,
+    query: String,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct AgentTag {
+    tag: String,
+    source: String,
+}
+
+/// GET /api/groups
+pub async fn list_groups(State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<AgentGroup>>> {
+    let groups = sqlx::query_as::<_, AgentGroup>("SELECT id, name, description, query, created_at, updated_at FROM agent_groups ORDER BY name")
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(groups))
+}
+
+/// POST /api/groups - a consulta eh validada antes de gravar
+pub async fn create_group(_: Analyst, State(state): State<Arc<AppState>>, Json(body): Json<GroupBody>) -> ApiResult<Json<AgentGroup>> {
+    let (name, query) = validate(&body)?;
+    let group = sqlx::query_as::<_, AgentGroup>(
+        r#"INSERT INTO agent_groups (name, description, query) VALUES ($1, $2, $3)
+           RETURNING id, name, description, query, created_at, updated_at"#)
+        .bind(name).bind(&body.description).bind(query)
+        .fetch_one(&state.pg_pool).await
+        .map_err(write_error)?;
+    Ok(Json(group))
+}
+
+/// GET /api/groups/:id
+pub async fn get_group(Path(id): Path<i32>, State(state): State<Arc<AppState>>) -> ApiResult<Json<AgentGroup>> {
+    load_group(&state.pg_pool, id).await.map(Json)
+}
+
+/// PUT /api/groups/:id
+pub async fn update_group(
+    _: Analyst,
+    Path(id): Path<i32>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<GroupBody>,
+) -> ApiResult<Json<AgentGroup>> {
+    let (name, query) = validate(&body)?;
+    sqlx::query_as::<_, AgentGroup>(
+        r#"UPDATE agent_groups SET name = $2, description = $3, query = $4, updated_at = NOW() WHERE id = $1
+           RETURNING id, name, description, query, created_at, updated_at"#)
+        .bind(id).bind(name).bind(&body.description).bind(query)
+        .fetch_optional(&state.pg_pool).await
+        .map_err(write_error)?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("grupo"))
+}
+
+/// DELETE /api/groups/:id
+pub async fn delete_group(_: Admin, Path(id): Path<i32>, State(state): State<Arc<AppState>>) -> ApiResult<http::StatusCode> {
+    let result = sqlx::query("DELETE FROM agent_groups WHERE id = $1").bind(id).execute(&state.pg_pool).await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("grupo"));
+    }
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+/// GET /api/groups/:id/agents - membros atuais do grupo
+pub async fn group_agents(Path(id): Path<i32>, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<AgentRow>>> {
+    let group = load_group(&state.pg_pool, id).await?;
+    let compiled = query::compile(&group.query, 1)
+        .map_err(|e| ApiError::Internal(format!("Consulta invalida no grupo {}: {}", id, e)))?;
+    let sql = format!(
+        "SELECT a.id, a.hostname, a.os_name, a.status, a.lifecycle, a.last_seen_at, c.score AS compliance_score
+         FROM agents a LEFT JOIN compliance_scores c ON c.agent_id = a.id
+         WHERE a.lifecycle IN ('active', 'stale') AND ({}) ORDER BY a.hostname", compiled.sql);
+    let mut q = sqlx::query_as::<_, AgentRow>(&sql);
+    for p in &compiled.params {
+        q = q.bind(p);
+    }
+    Ok(Json(q.fetch_all(&state.pg_pool).await?))
+}
+
+/// Ids dos agentes do grupo, para usar o grupo como alvo de comandos, relatorios e filtros
+pub async fn members(pool: &PgPool, group_id: i32) -> ApiResult<Vec<Uuid>> {
+    let group = load_group(pool, group_id).await?;
+    let compiled = query::compile(&group.query, 1)
+        .map_err(|e| ApiError::Internal(format!("Consulta invalida no grupo {}: {}", group_id, e)))?;
+    let sql = format!(
+        "SELECT a.id FROM agents a LEFT JOIN compliance_scores c ON c.agent_id = a.id
+         WHERE a.lifecycle IN ('active', 'stale') AND ({})",
+        compiled.sql);
+    let mut q = sqlx::query_scalar::<_, Uuid>(&sql);
+    for p in &compiled.params {
+        q = q.bind(p);
+    }
+    Ok(q.fetch_all(pool).await?)
+}
+
+/// O agente atende a consulta do grupo (mesmo criterio de `members`)
+async fn is_member(pool: &PgPool, group_id: i32, query: &str, agent_id: Uuid) -> ApiResult<bool> {
+    let compiled = query::compile(query, 2)
+        .map_err(|e| ApiError::Internal(format!("Consulta invalida no grupo {}: {}", group_id, e)))?;
+    let sql = format!(
+        "SELECT EXISTS (SELECT 1 FROM agents a LEFT JOIN compliance_scores c ON c.agent_id = a.id
+         WHERE a.id = $1 AND a.lifecycle IN ('active', 'stale') AND ({}))",
+        compiled.sql);
+    let mut q = sqlx::query_scalar::<_, bool>(&sql).bind(agent_id);
+    for p in &compiled.params {
+        q = q.bind(p);
+    }
+    Ok(q.fetch_one(pool).await?)
+}
+
+/// Agentes alvo de uma consulta filtrada por agente e/ou grupo (None = frota inteira)
+pub async fn scope(pool: &PgPool, agent_id: Option<Uuid>, group_id: Option<i32>) -> ApiResult<Option<Vec<Uuid>>> {
+    let members = match group_id {
+        Some(id) => Some(members(pool, id).await?),
+        None => None,
+    };
+    Ok(match (agent_id, members) {
+        (Some(id), Some(members)) => Some(members.into_iter().filter(|m| *m == id).collect()),
+        (Some(id), None) => Some(vec![id]),
+        (None, members) => members,
+    })
+}
+
+/// GET /api/agents/:id/tags
+pub async fn agent_tags(Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<AgentTag>>> {
+    let tags = sqlx::query_as::<_, AgentTag>("SELECT tag, source FROM agent_tags WHERE agent_id = $1 ORDER BY tag")
+        .bind(id)
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(tags))
+}
+
+/// PUT /api/agents/:id/tags - substitui as tags definidas via API (as reportadas pelo agente ficam)
+pub async fn set_agent_tags(
+    _: Analyst,
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AppState>>,
+    Json(tags): Json<Vec<String>>,
+) -> ApiResult<Json<Vec<AgentTag>>> {
+    let tags = normalize_tags(&tags)
+        .ok_or_else(|| ApiError::bad_request(format!("tags aceitam ate {} caracteres: letras, digitos e - _ : .", MAX_TAG_LEN)))?;
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM agents WHERE id = $1)")
+        .bind(id).fetch_one(&state.pg_pool).await?;
+    if !exists {
+        return Err(ApiError::not_found("agente"));
+    }
+    replace_tags(&state.pg_pool, id, "api", &tags).await?;
+    agent_tags(Path(id), State(state)).await
+}
+
+/// DELETE /api/agents/:id/tags/:tag
+pub async fn delete_agent_tag(_: Analyst, Path((id, tag)): Path<(Uuid, String)>, State(state): State<Arc<AppState>>) -> ApiResult<http::StatusCode> {
+    let result = sqlx::query("DELETE FROM agent_tags WHERE agent_id = $1 AND tag = $2")
+        .bind(id).bind(tag.to_lowercase())
+        .execute(&state.pg_pool).await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("tag"));
+    }
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+/// Tags reportadas pelo agente no handshake (AGENT_TAGS); tags invalidas sao descartadas
+pub async fn store_reported_tags(pool: &PgPool, agent_id: Uuid, tags: &[String]) -> Result<(), sqlx::Error> {
+    let tags: Vec<String> = tags.iter().filter_map(|t| normalize_tag(t)).collect();
+    replace_tags(pool, agent_id, "agent", &tags).await
+}
+
+/// Substitui as tags de uma origem; tag ja definida pela outra origem eh mantida como esta
+async fn replace_tags(pool: &PgPool, agent_id: Uuid, source: &str, tags: &[String]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM agent_tags WHERE agent_id = $1 AND source = $2")
+        .bind(agent_id).bind(source)
+        .execute(&mut *tx).await?;
+    sqlx::query(
+        r#"INSERT INTO agent_tags (agent_id, tag, source)
+           SELECT $1, t, $3 FROM UNNEST($2::text[]) AS t
+           ON CONFLICT (agent_id, tag) DO NOTHING"#)
+        .bind(agent_id).bind(tags).bind(source)
+        .execute(&mut *tx).await?;
+    tx.commit().await
+}
+
+fn normalize_tags(tags: &[String]) -> Option<Vec<String>> {
+    let mut out: Vec<String> = tags.iter().map(|t| normalize_tag(t)).collect::<Option<_>>()?;
+    out.sort();
+    out.dedup();
+    Some(out)
+}
+
+/// Tags em minusculas com letras, digitos e - _ : . ("env:prod", "pci-dss")
+fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    let valid = !tag.is_empty() && tag.len() <= MAX_TAG_LEN
+        && tag.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'));
+    valid.then_some(tag)
+}
+
+fn validate(body: &GroupBody) -> ApiResult<(&str, &str)> {
+    let name = body.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(ApiError::bad_request("nome do grupo deve ter de 1 a 100 caracteres"));
+    }
+    let query = body.query.trim();
+    query::compile(query, 1).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok((name, query))
+}
+
+async fn load_group(pool: &PgPool, id: i32) -> ApiResult<AgentGroup> {
+    sqlx::query_as::<_, AgentGroup>("SELECT id, name, description, query, created_at, updated_at FROM agent_groups WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool).await?
+        .ok_or_else(|| ApiError::not_found("grupo"))
+}
+
+/// Nome duplicado vira 409 com mensagem propria
+fn write_error(e: sqlx::Error) -> ApiError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => ApiError::conflict("ja existe um grupo com esse nome"),
+        _ => ApiError::from(e),
+    }
+}
@@ -0,0 +1,119 @@
+﻿//! Politicas atribuidas a grupos. Cada membro recebe a politica assinada (Command RunPolicy)
+//! ao se conectar e quando a atribuicao do grupo muda, e a avalia alem da politica local.
+//! O relatorio volta marcado como `assigned`: vai para o historico (compliance_scans) e
+//! para o drift, sem substituir o score atual do agente, que eh sempre o da politica local.
+
+use axum::{extract::{State, Path}, response::Json};
+use serde::Serialize;
+use sqlx::postgres::PgPool;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::crypto;
+use shared::models::sca::PolicyAssignment;
+use shared::protocol::{Message, CommandType, CommandValidity};
+use crate::AppState;
+use crate::auth::Analyst;
+use crate::error::{ApiError, ApiResult};
+
+/// Envio de uma politica a um membro do grupo
+#[derive(Serialize)]
+pub struct PolicyDelivery {
+    agent_id: Uuid,
+    policy_id: String,
+    /// SENT ou OFFLINE (recebe no proximo handshake)
+    status: &'static str,
+}
+
+/// GET /api/groups/:id/policies
+pub async fn list_policies(Path(id): Path<i32>, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<String>>> {
+    super::load_group(&state.pg_pool, id).await?;
+    Ok(Json(policy_ids(&state.pg_pool, id).await?))
+}
+
+/// PUT /api/groups/:id/policies - substitui as politicas do grupo e envia aos membros conectados
+pub async fn set_policies(
+    Analyst(user): Analyst,
+    Path(id): Path<i32>,
+    State(state): State<Arc<AppState>>,
+    Json(policies): Json<Vec<String>>,
+) -> ApiResult<Json<Vec<PolicyDelivery>>> {
+    let policies: BTreeSet<String> = policies.iter().map(|p| p.trim().to_string()).collect();
+    if let Some(unknown) = policies.iter().find(|p| state.policies.policy(p).is_none()) {
+        return Err(ApiError::bad_request(format!("politica desconhecida: {}", unknown)));
+    }
+    if !policies.is_empty() && state.admin_private_key.is_none() {
+        return Err(crate::remediation::no_signing_key());
+    }
+    super::load_group(&state.pg_pool, id).await?;
+
+    let policies: Vec<String> = policies.into_iter().collect();
+    let mut tx = state.pg_pool.begin().await?;
+    sqlx::query("DELETE FROM group_policies WHERE group_id = $1").bind(id).execute(&mut *tx).await?;
+    sqlx::query("INSERT INTO group_policies (group_id, policy_id) SELECT $1, p FROM UNNEST($2::text[]) AS p")
+        .bind(id).bind(&policies)
+        .execute(&mut *tx).await?;
+    tx.commit().await?;
+    tracing::info!("Politicas do grupo {} definidas por {}: {:?}", id, user.username, policies);
+
+    let mut out = Vec::new();
+    for agent_id in super::members(&state.pg_pool, id).await? {
+        for policy_id in &policies {
+            let status = match send_policy(&state, agent_id, policy_id).await {
+                Ok(()) => "SENT",
+                Err(ApiError::Conflict(_)) => "OFFLINE",
+                Err(e) => return Err(e),
+            };
+            out.push(PolicyDelivery { agent_id, policy_id: policy_id.clone(), status });
+        }
+    }
+    Ok(Json(out))
+}
+
+/// Envia ao agente recem-conectado as politicas dos grupos de que ele faz parte
+pub async fn deliver_assigned(state: &AppState, agent_id: Uuid) -> ApiResult<()> {
+    if state.admin_private_key.is_none() {
+        return Ok(());
+    }
+    let groups = sqlx::query_as::<_, (i32, String, Vec<String>)>(
+        r#"SELECT g.id, g.query, ARRAY_AGG(gp.policy_id ORDER BY gp.policy_id)
+           FROM agent_groups g JOIN group_policies gp ON gp.group_id = g.id
+           GROUP BY g.id, g.query"#)
+        .fetch_all(&state.pg_pool).await?;
+
+    let mut assigned = BTreeSet::new();
+    for (group_id, query, policies) in groups {
+        if super::is_member(&state.pg_pool, group_id, &query, agent_id).await? {
+            assigned.extend(policies);
+        }
+    }
+    for policy_id in &assigned {
+        match send_policy(state, agent_id, policy_id).await {
+            Ok(()) => {}
+            // Politica removida dos assets depois da atribuicao
+            Err(ApiError::NotFound(_)) => tracing::warn!("Politica {} atribuida, mas fora do catalogo", policy_id),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+async fn policy_ids(pool: &PgPool, group_id: i32) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT policy_id FROM group_policies WHERE group_id = $1 ORDER BY policy_id")
+        .bind(group_id)
+        .fetch_all(pool).await
+}
+
+/// Assina a politica para o agente e envia pelo socket dele
+async fn send_policy(state: &AppState, agent_id: Uuid, policy_id: &str) -> ApiResult<()> {
+    let key = state.admin_private_key.as_deref().ok_or_else(crate::remediation::no_signing_key)?;
+    let policy = state.policies.policy(policy_id).cloned().ok_or_else(|| ApiError::not_found("politica"))?;
+    let tx = state.connections.read().await.get(&agent_id).cloned().ok_or_else(|| ApiError::conflict("agente offline"))?;
+
+    let args = serde_json::to_string(&PolicyAssignment { agent_id, policy, validity: CommandValidity::issue() })
+        .map_err(|e| ApiError::Internal(format!("Falha ao serializar politica: {e}")))?;
+    let signature = crypto::sign_message(key, &args).map_err(|e| ApiError::Internal(format!("Falha ao assinar politica: {e}")))?;
+    let msg = Message::Command { id: Uuid::new_v4(), cmd_type: CommandType::RunPolicy, args: Some(args), signature };
+    let text = serde_json::to_string(&msg).map_err(|e| ApiError::Internal(format!("Falha ao serializar comando: {e}")))?;
+    tx.send(text).map_err(|_| ApiError::conflict("agente desconectou antes do envio"))
+}
@@ -0,0 +1,316 @@
+﻿//! Linguagem de consulta dos grupos dinamicos, compilada para um WHERE parametrizado
+//! sobre `agents a LEFT JOIN compliance_scores c`:
+//!
+//!   os_name = 'Ubuntu' AND compliance_score < 70
+//!   (tag = 'dmz' OR hostname LIKE 'web-%') AND NOT status = 'OFFLINE'
+//!   tag IN ('prod', 'pci') AND compliance_score IS NOT NULL
+
+use std::fmt;
+
+#[derive(Debug)]
+pub struct QueryError {
+    message: String,
+    /// Coluna (em caracteres, a partir de 1) onde o erro foi detectado
+    position: usize,
+}
+
+impl QueryError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self { message: message.into(), position }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (posicao {})", self.message, self.position)
+    }
+}
+
+/// Consulta compilada: trecho SQL com placeholders $n e os valores (texto) na ordem
+pub struct Compiled {
+    pub sql: String,
+    pub params: Vec<String>,
+}
+
+/// Tamanho maximo aceito para a consulta de um grupo
+const MAX_QUERY_LEN: usize = 2000;
+
+#[derive(Clone, Copy)]
+enum Kind { Text, Number, Time, Tag }
+
+/// Campos consultaveis: nome na linguagem -> expressao SQL e tipo
+const FIELDS: &[(&str, &str, Kind)] = &[
+    ("hostname", "a.hostname", Kind::Text),
+    ("os_name", "a.os_name", Kind::Text),
+    ("os_version", "a.os_version", Kind::Text),
+    ("kernel_version", "a.kernel_version", Kind::Text),
+    ("arch", "a.arch", Kind::Text),
+    ("ip_address", "a.ip_address", Kind::Text),
+    ("status", "a.status", Kind::Text),
+    ("lifecycle", "a.lifecycle", Kind::Text),
+    ("last_seen_at", "a.last_seen_at", Kind::Time),
+    ("policy_id", "c.policy_id", Kind::Text),
+    ("compliance_score", "c.score", Kind::Number),
+    ("tag", "t.tag", Kind::Tag),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// Compila a consulta; os placeholders comecam em $first
+pub fn compile(query: &str, first: usize) -> Result<Compiled, QueryError> {
+    if query.len() > MAX_QUERY_LEN {
+        return Err(QueryError::new(format!("consulta maior que {} caracteres", MAX_QUERY_LEN), MAX_QUERY_LEN + 1));
+    }
+    let tokens = tokenize(query)?;
+    let end = query.chars().count() + 1;
+    let mut parser = Parser { tokens, pos: 0, end, params: Vec::new(), first };
+    let sql = parser.or()?;
+    if let Some((_, t)) = parser.tokens.get(parser.pos) {
+        return Err(parser.error(parser.pos, format!("token inesperado: {:?}", t)));
+    }
+    Ok(Compiled { sql, params: parser.params })
+}
+
+/// Tokens com a coluna (a partir de 1) onde comecam
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let mut push = |token: Token| out.push((start + 1, token));
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => { push(Token::LParen); i += 1; }
+            ')' => { push(Token::RParen); i += 1; }
+            ',' => { push(Token::Comma); i += 1; }
+            '\'' => {
+                // Aspas simples; '' dentro da string vira '
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(QueryError::new("string sem fechamento", start + 1)),
+                        Some('\'') if chars.get(i + 1) == Some(&'\'') => { s.push('\''); i += 2; }
+                        Some('\'') => { i += 1; break; }
+                        Some(ch) => { s.push(*ch); i += 1; }
+                    }
+                }
+                push(Token::Str(s));
+            }
+            '=' => { push(Token::Op("=")); i += 1; }
+            '!' if chars.get(i + 1) == Some(&'=') => { push(Token::Op("!=")); i += 2; }
+            '<' | '>' => {
+                let op = match (c, chars.get(i + 1)) {
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('<', Some('>')) => "!=",
+                    ('<', _) => "<",
+                    _ => ">",
+                };
+                i += if op.len() == 2 { 2 } else { 1 };
+                push(Token::Op(op));
+            }
+            c if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) => {
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') { i += 1; }
+                push(Token::Num(chars[start..i].iter().collect()));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') { i += 1; }
+                push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            c => return Err(QueryError::new(format!("caractere inesperado: '{}'", c), start + 1)),
+        }
+    }
+    Ok(out)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// Coluna apos o ultimo caractere (erros no fim da consulta)
+    end: usize,
+    params: Vec<String>,
+    first: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.peek().cloned();
+        self.pos += 1;
+        t
+    }
+
+    /// Erro apontando para o token `index` (ou para o fim da consulta)
+    fn error(&self, index: usize, message: impl Into<String>) -> QueryError {
+        QueryError::new(message, self.tokens.get(index).map_or(self.end, |(p, _)| *p))
+    }
+
+    /// Erro no token que acabou de ser consumido por `next`
+    fn error_last(&self, message: impl Into<String>) -> QueryError {
+        self.error(self.pos - 1, message)
+    }
+
+    fn keyword(&mut self, kw: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(w)) if w.eq_ignore_ascii_case(kw)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), QueryError> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            other => Err(self.error_last(format!("esperado {:?}, encontrado {:?}", token, other))),
+        }
+    }
+
+    fn param(&mut self, value: String) -> String {
+        self.params.push(value);
+        format!("${}", self.first + self.params.len() - 1)
+    }
+
+    fn or(&mut self) -> Result<String, QueryError> {
+        let mut sql = self.and()?;
+        while self.keyword("OR") {
+            sql = format!("({} OR {})", sql, self.and()?);
+        }
+        Ok(sql)
+    }
+
+    fn and(&mut self) -> Result<String, QueryError> {
+        let mut sql = self.unary()?;
+        while self.keyword("AND") {
+            sql = format!("({} AND {})", sql, self.unary()?);
+        }
+        Ok(sql)
+    }
+
+    fn unary(&mut self) -> Result<String, QueryError> {
+        if self.keyword("NOT") {
+            return Ok(format!("(NOT {})", self.unary()?));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let sql = self.or()?;
+            self.expect(Token::RParen)?;
+            return Ok(sql);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<String, QueryError> {
+        let name = match self.next() {
+            Some(Token::Ident(name)) => name.to_lowercase(),
+            other => return Err(self.error_last(format!("esperado um campo, encontrado {:?}", other))),
+        };
+        let &(_, column, kind) = FIELDS.iter().find(|(f, _, _)| *f == name)
+            .ok_or_else(|| self.error_last(format!("campo desconhecido: {}", name)))?;
+
+        // IS [NOT] NULL
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            if !self.keyword("NULL") {
+                return Err(self.error(self.pos, "esperado NULL apos IS"));
+            }
+            return Ok(match (kind, negated) {
+                (Kind::Tag, false) => "NOT EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id)".to_string(),
+                (Kind::Tag, true) => "EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id)".to_string(),
+                (_, false) => format!("{} IS NULL", column),
+                (_, true) => format!("{} IS NOT NULL", column),
+            });
+        }
+
+        let negated = self.keyword("NOT");
+        let condition = if self.keyword("LIKE") {
+            let pattern = self.string()?;
+            let p = self.param(pattern);
+            format!("{} ILIKE {}", column, p)
+        } else if self.keyword("IN") {
+            self.expect(Token::LParen)?;
+            let mut values = vec![self.literal(kind)?];
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                values.push(self.literal(kind)?);
+            }
+            self.expect(Token::RParen)?;
+            let values: Vec<String> = values.into_iter().map(|v| self.placeholder(kind, v)).collect();
+            format!("{} IN ({})", compare_column(column, kind), values.join(", "))
+        } else if negated {
+            return Err(self.error(self.pos, "NOT so pode preceder LIKE ou IN"));
+        } else {
+            let op = match self.next() {
+                Some(Token::Op(op)) => op,
+                other => return Err(self.error_last(format!("esperado operador apos {}, encontrado {:?}", name, other))),
+            };
+            if matches!(kind, Kind::Tag) && !matches!(op, "=" | "!=") {
+                return Err(self.error_last("tag aceita apenas =, !=, LIKE e IN"));
+            }
+            let value = self.literal(kind)?;
+            let p = self.placeholder(kind, value);
+            // != na tag: o agente nao possui a tag
+            if matches!(kind, Kind::Tag) && op == "!=" {
+                return Ok(format!("NOT EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id AND LOWER(t.tag) = {})", p));
+            }
+            format!("{} {} {}", compare_column(column, kind), if op == "!=" { "<>" } else { op }, p)
+        };
+
+        let condition = match kind {
+            Kind::Tag => format!("EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id AND {})", condition),
+            _ => condition,
+        };
+        Ok(if negated { format!("(NOT {})", condition) } else { condition })
+    }
+
+    fn string(&mut self) -> Result<String, QueryError> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(s),
+            other => Err(self.error_last(format!("esperado texto entre aspas, encontrado {:?}", other))),
+        }
+    }
+
+    fn literal(&mut self, kind: Kind) -> Result<String, QueryError> {
+        match (kind, self.next()) {
+            (Kind::Number, Some(Token::Num(n))) => n.parse::<f64>().map(|_| n.clone())
+                .map_err(|_| self.error_last(format!("numero invalido: {}", n))),
+            (Kind::Number, other) => Err(self.error_last(format!("esperado numero, encontrado {:?}", other))),
+            (_, Some(Token::Str(s))) => Ok(s),
+            (_, Some(Token::Num(n))) => Ok(n),
+            (_, other) => Err(self.error_last(format!("esperado valor, encontrado {:?}", other))),
+        }
+    }
+
+    /// Placeholder com o cast do tipo do campo (texto compara sem diferenciar maiusculas)
+    fn placeholder(&mut self, kind: Kind, value: String) -> String {
+        let p = self.param(value);
+        match kind {
+            Kind::Number => format!("{}::numeric", p),
+            Kind::Time => format!("{}::timestamptz", p),
+            Kind::Text | Kind::Tag => format!("LOWER({})", p),
+        }
+    }
+}
+
+fn compare_column(column: &str, kind: Kind) -> String {
+    match kind {
+        Kind::Text | Kind::Tag => format!("LOWER({})", column),
+        _ => column.to_string(),
+    }
+}
@@ -0,0 +1,110 @@
+﻿use super::query::{compile, Compiled};
+
+fn sql(query: &str) -> Compiled {
+    compile(query, 1).unwrap_or_else(|e| panic!("{}: {}", query, e))
+}
+
+fn error(query: &str) -> String {
+    match compile(query, 1) {
+        Ok(c) => panic!("{} compilou: {}", query, c.sql),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn comparison_uses_placeholders_and_casts() {
+    let c = sql("os_name = 'Ubuntu' AND compliance_score < 70");
+    assert_eq!(c.sql, "(LOWER(a.os_name) = LOWER($1) AND c.score < $2::numeric)");
+    assert_eq!(c.params, vec!["Ubuntu", "70"]);
+
+    let c = sql("last_seen_at >= '2025-01-01T00:00:00Z'");
+    assert_eq!(c.sql, "a.last_seen_at >= $1::timestamptz");
+}
+
+#[test]
+fn placeholders_start_at_first() {
+    let c = compile("hostname = 'a' OR hostname = 'b'", 3).unwrap();
+    assert_eq!(c.sql, "(LOWER(a.hostname) = LOWER($3) OR LOWER(a.hostname) = LOWER($4))");
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    let c = sql("hostname = 'a' OR hostname = 'b' AND status = 'ONLINE'");
+    assert_eq!(c.sql, "(LOWER(a.hostname) = LOWER($1) OR (LOWER(a.hostname) = LOWER($2) AND LOWER(a.status) = LOWER($3)))");
+
+    let c = sql("(hostname = 'a' OR hostname = 'b') AND status = 'ONLINE'");
+    assert_eq!(c.sql, "((LOWER(a.hostname) = LOWER($1) OR LOWER(a.hostname) = LOWER($2)) AND LOWER(a.status) = LOWER($3))");
+}
+
+#[test]
+fn not_applies_to_the_next_term_only() {
+    let c = sql("NOT status = 'OFFLINE' AND arch = 'x86_64'");
+    assert_eq!(c.sql, "((NOT LOWER(a.status) = LOWER($1)) AND LOWER(a.arch) = LOWER($2))");
+}
+
+#[test]
+fn keywords_and_fields_are_case_insensitive() {
+    let c = sql("HOSTNAME like 'web-%' and Status in ('online')");
+    assert_eq!(c.sql, "(a.hostname ILIKE $1 AND LOWER(a.status) IN (LOWER($2)))");
+}
+
+#[test]
+fn quoted_values_never_reach_the_sql() {
+    let c = sql("hostname = 'x'' OR 1=1 --'");
+    assert_eq!(c.sql, "LOWER(a.hostname) = LOWER($1)");
+    assert_eq!(c.params, vec!["x' OR 1=1 --"]);
+}
+
+#[test]
+fn in_like_and_null() {
+    let c = sql("os_name NOT IN ('Ubuntu', 'Debian') AND hostname NOT LIKE 'lab-%'");
+    assert_eq!(c.sql, "((NOT LOWER(a.os_name) IN (LOWER($1), LOWER($2))) AND (NOT a.hostname ILIKE $3))");
+
+    assert_eq!(sql("compliance_score IS NULL").sql, "c.score IS NULL");
+    assert_eq!(sql("policy_id IS NOT NULL").sql, "c.policy_id IS NOT NULL");
+    assert_eq!(sql("compliance_score <> -5").params, vec!["-5"]);
+    assert_eq!(sql("compliance_score != 5").sql, "c.score <> $1::numeric");
+}
+
+#[test]
+fn tags_become_subqueries() {
+    let c = sql("tag = 'prod'");
+    assert_eq!(c.sql, "EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id AND LOWER(t.tag) = LOWER($1))");
+
+    let c = sql("tag != 'prod'");
+    assert_eq!(c.sql, "NOT EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id AND LOWER(t.tag) = LOWER($1))");
+
+    let c = sql("tag IN ('prod', 'pci')");
+    assert_eq!(c.sql, "EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id AND LOWER(t.tag) IN (LOWER($1), LOWER($2)))");
+
+    assert_eq!(sql("tag IS NULL").sql, "NOT EXISTS (SELECT 1 FROM agent_tags t WHERE t.agent_id = a.id)");
+}
+
+#[test]
+fn unknown_fields_and_bad_values_are_rejected() {
+    assert_eq!(error("password = 'x'"), "campo desconhecido: password (posicao 1)");
+    assert_eq!(error("hostname = 'a' AND c.score = 1"), "caractere inesperado: '.' (posicao 21)");
+    assert_eq!(error("compliance_score < 'alto'"), "esperado numero, encontrado Some(Str(\"alto\")) (posicao 20)");
+    assert_eq!(error("compliance_score < 1.2.3"), "numero invalido: 1.2.3 (posicao 20)");
+    assert_eq!(error("tag < 'a'"), "tag aceita apenas =, !=, LIKE e IN (posicao 5)");
+}
+
+#[test]
+fn errors_point_at_the_offending_token() {
+    assert_eq!(error("hostname = 'abc"), "string sem fechamento (posicao 12)");
+    assert_eq!(error("hostname 'a'"), "esperado operador apos hostname, encontrado Some(Str(\"a\")) (posicao 10)");
+    assert_eq!(error("hostname = 'a' status = 'b'"), "token inesperado: Ident(\"status\") (posicao 16)");
+    assert_eq!(error("(hostname = 'a'"), "esperado RParen, encontrado None (posicao 16)");
+    assert_eq!(error("hostname = 'a' AND"), "esperado um campo, encontrado None (posicao 19)");
+    assert_eq!(error("hostname IS 'a'"), "esperado NULL apos IS (posicao 13)");
+    assert_eq!(error("hostname NOT = 'a'"), "NOT so pode preceder LIKE ou IN (posicao 14)");
+    assert_eq!(error("hostname LIKE web"), "esperado texto entre aspas, encontrado Some(Ident(\"web\")) (posicao 15)");
+    // Posicao em caracteres, nao em bytes
+    assert_eq!(error("hostname = 'sao' AND ção = 1"), "caractere inesperado: 'ç' (posicao 22)");
+}
+
+#[test]
+fn oversized_query_is_rejected() {
+    let query = format!("hostname = '{}'", "a".repeat(3000));
+    assert!(error(&query).starts_with("consulta maior que 2000 caracteres"));
+}
@@ -0,0 +1,222 @@
+﻿use axum::{extract::{State, Query}, response::Json};
+use serde::{Serialize, Deserialize};
+use sqlx::postgres::PgPool;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::models::SoftwareInfo;
+use shared::software::{self, Ecosystem, VersionReq};
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+
+pub mod rules;
+
+#[derive(Deserialize)]
+pub struct SoftwareQuery {
+    q: Option<String>,        // Busca em nome, produto e fabricante
+    vendor: Option<String>,
+    product: Option<String>,  // Produto canonico exato (ex.: "openssl")
+    version: Option<String>,  // Restricao de versao: "<3.0", ">=1.1,<3.0"
+    agent_id: Option<Uuid>,
+    group_by: Option<String>, // "product" (padrao), "name", "vendor" ou "version"
+    limit: Option<usize>,
+}
+
+/// Instalacoes agrupadas com contagem e hosts
+#[derive(Serialize)]
+pub struct SoftwareGroup {
+    vendor: Option<String>,
+    product: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+    installs: usize,
+    agents: usize,
+    versions: BTreeSet<String>,
+    hosts: Vec<SoftwareHost>,
+}
+
+#[derive(Serialize)]
+pub struct SoftwareHost {
+    agent_id: Uuid,
+    hostname: String,
+    name: String,
+    version: Option<String>,
+}
+
+/// (fabricante, produto, nome, versao) conforme o group_by
+type GroupKey = (Option<String>, Option<String>, Option<String>, Option<String>);
+
+/// Filtros aplicados no Postgres
+#[derive(Default)]
+pub(crate) struct SearchFilter {
+    q: Option<String>,
+    vendor: Option<String>,
+    product: Option<String>,
+}
+
+/// Inventario com a identidade canonica; linhas gravadas antes da normalizacao caem no
+/// nome em minusculas. $1 agente, $2 busca livre, $3 fabricante, $4 produto
+const INVENTORY: &str = r#"SELECT s.agent_id, a.hostname, a.os_name, s.name, s.version, s.vendor,
+              COALESCE(s.product, LOWER(s.name)) AS product, s.vendor_key
+       FROM software_inventory s
+       JOIN agents a ON a.id = s.agent_id
+       WHERE ($1::uuid IS NULL OR s.agent_id = $1)
+         AND ($2::text IS NULL OR s.name ILIKE '%' || $2 || '%' OR s.product ILIKE '%' || $2 || '%' OR s.vendor ILIKE '%' || $2 || '%')
+         AND ($3::text IS NULL OR s.vendor_key = $3)
+         AND ($4::text IS NULL OR s.product = $4)"#;
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct InstalledRow {
+    agent_id: Uuid,
+    hostname: String,
+    os_name: String,
+    name: String,
+    version: Option<String>,
+    vendor: Option<String>,
+    product: String,
+    vendor_key: Option<String>,
+}
+
+/// Substitui o inventario de software do agente pelo recebido no handshake
+/// (valores sao truncados no tamanho das colunas para nao perder o lote inteiro)
+pub async fn store_software(pool: &PgPool, agent_id: Uuid, software: &[SoftwareInfo]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM software_inventory WHERE agent_id = $1")
+        .bind(agent_id)
+        .execute(&mut *tx).await?;
+
+    let normalized: Vec<_> = software.iter().map(|s| software::normalize(&s.name, s.vendor.as_deref())).collect();
+
+    sqlx::query(
+        r#"INSERT INTO software_inventory (agent_id, name, version, vendor, install_date, product, vendor_key, last_scanned_at)
+           SELECT $1, LEFT(s.name, 255), LEFT(s.version, 100), LEFT(s.vendor, 255), LEFT(s.install_date, 50), LEFT(s.product, 255), LEFT(s.vendor_key, 255), NOW()
+           FROM UNNEST($2::text[], $3::text[], $4::text[], $5::text[], $6::text[], $7::text[]) AS s(name, version, vendor, install_date, product, vendor_key)
+           WHERE s.name <> ''"#)
+        .bind(agent_id)
+        .bind(software.iter().map(|s| s.name.clone()).collect::<Vec<_>>())
+        .bind(software.iter().map(|s| s.version.clone()).collect::<Vec<_>>())
+        .bind(software.iter().map(|s| s.vendor.clone()).collect::<Vec<_>>())
+        .bind(software.iter().map(|s| s.install_date.clone()).collect::<Vec<_>>())
+        .bind(normalized.iter().map(|n| n.product.clone()).collect::<Vec<_>>())
+        .bind(normalized.iter().map(|n| n.vendor.clone()).collect::<Vec<_>>())
+        .execute(&mut *tx).await?;
+
+    tx.commit().await
+}
+
+/// GET /api/software - busca no inventario da frota agrupada por produto, nome, fabricante ou versao
+pub async fn search(Query(q): Query<SoftwareQuery>, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<SoftwareGroup>>> {
+    let req = match q.version.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) => Some(VersionReq::parse(v).ok_or_else(|| ApiError::bad_request(format!("restricao de versao invalida: {v}")))?),
+        None => None,
+    };
+    let group_by = q.group_by.as_deref().unwrap_or("product");
+    if !matches!(group_by, "product" | "name" | "vendor" | "version") {
+        return Err(ApiError::bad_request(format!("group_by invalido: {group_by}")));
+    }
+
+    let filter = SearchFilter {
+        q: q.q.clone(),
+        vendor: q.vendor.as_deref().map(|v| software::canonical_vendor(v).unwrap_or_else(|| v.to_lowercase())),
+        product: q.product.as_deref().map(|p| p.trim().to_lowercase()),
+    };
+    // A comparacao depende do ecossistema: resolve as versoes aceitas antes de agrupar
+    let versions = match &req {
+        Some(req) => Some(matching_versions(&state.pg_pool, q.agent_id, &filter, req).await?),
+        None => None,
+    };
+    let limit = q.limit.unwrap_or(200).clamp(1, 1000);
+    let rows = top_groups(&state.pg_pool, q.agent_id, &filter, versions, group_by, limit).await?;
+
+    let mut groups: BTreeMap<GroupKey, Vec<InstalledRow>> = BTreeMap::new();
+    for row in rows {
+        let key = match group_by {
+            "name" => (None, None, Some(row.name.clone()), None),
+            "vendor" => (row.vendor_key.clone(), None, None, None),
+            "version" => (row.vendor_key.clone(), Some(row.product.clone()), None, row.version.clone()),
+            _ => (row.vendor_key.clone(), Some(row.product.clone()), None, None),
+        };
+        groups.entry(key).or_default().push(row);
+    }
+
+    let mut out: Vec<SoftwareGroup> = groups.into_iter()
+        .map(|((vendor, product, name, version), rows)| SoftwareGroup {
+            vendor, product, name, version,
+            installs: rows.len(),
+            agents: rows.iter().map(|r| r.agent_id).collect::<BTreeSet<_>>().len(),
+            versions: rows.iter().filter_map(|r| r.version.clone()).collect(),
+            hosts: rows.into_iter()
+                .map(|r| SoftwareHost { agent_id: r.agent_id, hostname: r.hostname, name: r.name, version: r.version })
+                .collect(),
+        })
+        .collect();
+    out.sort_by_key(|g| std::cmp::Reverse(g.installs));
+    Ok(Json(out))
+}
+
+/// Inventario (da frota ou de um agente)
+pub(crate) async fn installed(pool: &PgPool, agent_id: Option<Uuid>, filter: &SearchFilter) -> Result<Vec<InstalledRow>, sqlx::Error> {
+    sqlx::query_as::<_, InstalledRow>(&format!("{INVENTORY} ORDER BY a.hostname, s.name"))
+        .bind(agent_id).bind(&filter.q).bind(&filter.vendor).bind(&filter.product)
+        .fetch_all(pool).await
+}
+
+/// Pares (os_name, versao) distintos do filtro que satisfazem a restricao
+async fn matching_versions(
+    pool: &PgPool,
+    agent_id: Option<Uuid>,
+    filter: &SearchFilter,
+    req: &VersionReq,
+) -> Result<(Vec<String>, Vec<String>), sqlx::Error> {
+    let pairs: Vec<(String, String)> = sqlx::query_as(
+        &format!("SELECT DISTINCT inv.os_name, inv.version FROM ({INVENTORY}) inv WHERE inv.version IS NOT NULL"))
+        .bind(agent_id).bind(&filter.q).bind(&filter.vendor).bind(&filter.product)
+        .fetch_all(pool).await?;
+    Ok(pairs.into_iter()
+        .filter(|(os, version)| req.matches(version, Ecosystem::from_os(os)))
+        .unzip())
+}
+
+/// Linhas dos `limit` grupos com mais instalacoes; agrupamento e corte ficam no Postgres
+/// para nao carregar o inventario inteiro da frota
+async fn top_groups(
+    pool: &PgPool,
+    agent_id: Option<Uuid>,
+    filter: &SearchFilter,
+    versions: Option<(Vec<String>, Vec<String>)>,
+    group_by: &str,
+    limit: usize,
+) -> Result<Vec<InstalledRow>, sqlx::Error> {
+    let (os_names, versions) = versions.unzip();
+    sqlx::query_as::<_, InstalledRow>(&format!(
+        r#"WITH inv AS (
+               SELECT * FROM ({INVENTORY}) inv
+               WHERE $5::text[] IS NULL
+                  OR (inv.os_name, inv.version) IN (SELECT * FROM UNNEST($5::text[], $6::text[]))
+           ), keyed AS (
+               SELECT inv.*,
+                      CASE WHEN $7 = 'name' THEN NULL ELSE inv.vendor_key END AS k_vendor,
+                      CASE WHEN $7 IN ('product', 'version') THEN inv.product END AS k_product,
+                      CASE WHEN $7 = 'name' THEN inv.name END AS k_name,
+                      CASE WHEN $7 = 'version' THEN inv.version END AS k_version
+               FROM inv
+           ), top AS (
+               SELECT k_vendor, k_product, k_name, k_version
+               FROM keyed
+               GROUP BY k_vendor, k_product, k_name, k_version
+               ORDER BY COUNT(*) DESC, k_vendor NULLS FIRST, k_product NULLS FIRST, k_name NULLS FIRST, k_version NULLS FIRST
+               LIMIT $8
+           )
+           SELECT keyed.agent_id, keyed.hostname, keyed.os_name, keyed.name, keyed.version, keyed.vendor,
+                  keyed.product, keyed.vendor_key
+           FROM keyed
+           JOIN top ON keyed.k_vendor IS NOT DISTINCT FROM top.k_vendor
+                   AND keyed.k_product IS NOT DISTINCT FROM top.k_product
+                   AND keyed.k_name IS NOT DISTINCT FROM top.k_name
+                   AND keyed.k_version IS NOT DISTINCT FROM top.k_version
+           ORDER BY keyed.hostname, keyed.name"#))
+        .bind(agent_id).bind(&filter.q).bind(&filter.vendor).bind(&filter.product)
+        .bind(os_names).bind(versions).bind(group_by).bind(limit as i64)
+        .fetch_all(pool).await
+}
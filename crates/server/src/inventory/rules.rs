@@ -0,0 +1,177 @@
+﻿use axum::{extract::{State, Path, Query}, response::Json, http};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::software::{Ecosystem, VersionReq};
+use crate::AppState;
+use crate::auth::{Admin, Analyst};
+use crate::error::{ApiError, ApiResult};
+use super::InstalledRow;
+
+#[cfg(test)]
+mod tests;
+
+/// Regra da lista de software: deny marca o software como proibido, allow abre excecao
+#[derive(Serialize, sqlx::FromRow)]
+pub struct SoftwareRule {
+    id: i32,
+    kind: String,
+    pattern: String,
+    vendor: Option<String>,
+    version: Option<String>,
+    reason: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct NewRule {
+    kind: String,
+    pattern: String,
+    vendor: Option<String>,
+    version: Option<String>,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ViolationQuery {
+    agent_id: Option<Uuid>,
+}
+
+/// Software proibido encontrado em um agente
+#[derive(Serialize)]
+pub struct Violation {
+    agent_id: Uuid,
+    hostname: String,
+    name: String,
+    version: Option<String>,
+    vendor: Option<String>,
+    product: String,
+    rule_id: i32,
+    pattern: String,
+    reason: Option<String>,
+}
+
+/// GET /api/software/rules
+pub async fn list_rules(State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<SoftwareRule>>> {
+    load_rules(&state).await.map(Json)
+}
+
+/// POST /api/software/rules
+pub async fn create_rule(
+    _: Analyst,
+    State(state): State<Arc<AppState>>,
+    Json(rule): Json<NewRule>,
+) -> ApiResult<Json<SoftwareRule>> {
+    let pattern = rule.pattern.trim().to_lowercase();
+    let version = rule.version.as_deref().map(str::trim).filter(|v| !v.is_empty());
+    if !matches!(rule.kind.as_str(), "allow" | "deny") {
+        return Err(ApiError::bad_request("kind deve ser allow ou deny"));
+    }
+    if pattern.is_empty() || pattern.len() > 255 {
+        return Err(ApiError::bad_request("pattern deve ter de 1 a 255 caracteres"));
+    }
+    if version.is_some_and(|v| VersionReq::parse(v).is_none()) {
+        return Err(ApiError::bad_request("restricao de versao invalida"));
+    }
+    let vendor = rule.vendor.as_deref().and_then(shared::software::canonical_vendor);
+
+    let rule = sqlx::query_as::<_, SoftwareRule>(
+        r#"INSERT INTO software_rules (kind, pattern, vendor, version, reason)
+           VALUES ($1, $2, $3, $4, $5)
+           RETURNING id, kind, pattern, vendor, version, reason, created_at"#)
+        .bind(&rule.kind).bind(&pattern).bind(&vendor).bind(version).bind(&rule.reason)
+        .fetch_one(&state.pg_pool).await?;
+    Ok(Json(rule))
+}
+
+/// DELETE /api/software/rules/:id
+pub async fn delete_rule(_: Admin, Path(id): Path<i32>, State(state): State<Arc<AppState>>) -> ApiResult<http::StatusCode> {
+    let result = sqlx::query("DELETE FROM software_rules WHERE id = $1").bind(id).execute(&state.pg_pool).await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("regra"));
+    }
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+/// GET /api/software/violations - software proibido na frota (ou em um agente via agent_id)
+pub async fn fleet_violations(
+    Query(q): Query<ViolationQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Json<Vec<Violation>>> {
+    violations(&state, q.agent_id).await.map(Json)
+}
+
+/// GET /api/agents/:id/software/violations
+pub async fn agent_violations(Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<Violation>>> {
+    violations(&state, Some(id)).await.map(Json)
+}
+
+async fn violations(state: &AppState, agent_id: Option<Uuid>) -> ApiResult<Vec<Violation>> {
+    let rules = load_rules(state).await?;
+    if !rules.iter().any(|r| r.kind == "deny") {
+        return Ok(Vec::new());
+    }
+    let installed = super::installed(&state.pg_pool, agent_id, &super::SearchFilter::default()).await?;
+
+    let (allow, deny): (Vec<_>, Vec<_>) = rules.iter().partition(|r| r.kind == "allow");
+    let out = installed.into_iter()
+        .filter_map(|sw| {
+            let rule = deny.iter().find(|r| r.matches(&sw))?;
+            if allow.iter().any(|r| r.matches(&sw)) {
+                return None;
+            }
+            Some(Violation {
+                agent_id: sw.agent_id,
+                hostname: sw.hostname,
+                name: sw.name,
+                version: sw.version,
+                vendor: sw.vendor,
+                product: sw.product,
+                rule_id: rule.id,
+                pattern: rule.pattern.clone(),
+                reason: rule.reason.clone(),
+            })
+        })
+        .collect();
+    Ok(out)
+}
+
+async fn load_rules(state: &AppState) -> ApiResult<Vec<SoftwareRule>> {
+    let rules = sqlx::query_as::<_, SoftwareRule>("SELECT id, kind, pattern, vendor, version, reason, created_at FROM software_rules ORDER BY id")
+        .fetch_all(&state.pg_pool).await?;
+    Ok(rules)
+}
+
+impl SoftwareRule {
+    fn matches(&self, sw: &InstalledRow) -> bool {
+        if !glob(&self.pattern, &sw.product) && !glob(&self.pattern, &sw.name.to_lowercase()) {
+            return false;
+        }
+        if self.vendor.is_some() && self.vendor != sw.vendor_key {
+            return false;
+        }
+        match (&self.version, &sw.version) {
+            (None, _) => true,
+            (Some(req), Some(version)) => VersionReq::parse(req)
+                .is_some_and(|req| req.matches(version, Ecosystem::from_os(&sw.os_name))),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Glob com '*' apenas: "*torrent*", "teamviewer", "java_*"
+fn glob(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else { return rest.is_empty() };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,99 @@
+﻿use chrono::Utc;
+use uuid::Uuid;
+use super::{glob, SoftwareRule};
+use super::super::InstalledRow;
+
+fn rule(pattern: &str, vendor: Option<&str>, version: Option<&str>) -> SoftwareRule {
+    SoftwareRule {
+        id: 1,
+        kind: "deny".to_string(),
+        pattern: pattern.to_string(),
+        vendor: vendor.map(str::to_string),
+        version: version.map(str::to_string),
+        reason: None,
+        created_at: Utc::now(),
+    }
+}
+
+fn installed(name: &str, product: &str, vendor_key: Option<&str>, version: Option<&str>, os_name: &str) -> InstalledRow {
+    InstalledRow {
+        agent_id: Uuid::nil(),
+        hostname: "host".to_string(),
+        os_name: os_name.to_string(),
+        name: name.to_string(),
+        version: version.map(str::to_string),
+        vendor: None,
+        product: product.to_string(),
+        vendor_key: vendor_key.map(str::to_string),
+    }
+}
+
+#[test]
+fn glob_without_wildcard_is_exact() {
+    assert!(glob("teamviewer", "teamviewer"));
+    assert!(!glob("teamviewer", "teamviewer_host"));
+    assert!(!glob("teamviewer", "my_teamviewer"));
+    assert!(glob("", ""));
+    assert!(!glob("", "x"));
+}
+
+#[test]
+fn glob_matches_prefix_suffix_and_middle() {
+    assert!(glob("java_*", "java_runtime"));
+    assert!(glob("java_*", "java_"));
+    assert!(!glob("java_*", "openjdk_java"));
+    assert!(glob("*torrent", "qbittorrent"));
+    assert!(!glob("*torrent", "torrent_client"));
+    assert!(glob("*torrent*", "utorrent_web"));
+    assert!(glob("*", ""));
+    assert!(glob("a*b*c", "a_x_b_y_c"));
+    assert!(!glob("a*b*c", "a_x_c_y_b"));
+}
+
+#[test]
+fn glob_does_not_reuse_characters_between_parts() {
+    // Prefixo e sufixo nao podem se sobrepor: "ab*ba" nao casa "aba"
+    assert!(!glob("ab*ba", "aba"));
+    assert!(glob("ab*ba", "abba"));
+    assert!(!glob("a*aa", "aa"));
+}
+
+#[test]
+fn rule_matches_product_or_lowercased_name() {
+    let deny = rule("*torrent*", None, None);
+    assert!(deny.matches(&installed("uTorrent", "utorrent", None, None, "Windows 11")));
+    assert!(deny.matches(&installed("BitTorrent Web", "web", None, None, "Windows 11")));
+    assert!(!deny.matches(&installed("Firefox", "firefox", None, None, "Windows 11")));
+}
+
+#[test]
+fn rule_vendor_must_match_canonical_vendor() {
+    let deny = rule("chrome", Some("google"), None);
+    assert!(deny.matches(&installed("Google Chrome", "chrome", Some("google"), Some("120.0"), "Windows 11")));
+    assert!(!deny.matches(&installed("Chrome", "chrome", Some("acme"), Some("120.0"), "Windows 11")));
+    assert!(!deny.matches(&installed("Chrome", "chrome", None, Some("120.0"), "Windows 11")));
+}
+
+#[test]
+fn rule_version_uses_the_host_ecosystem() {
+    let deny = rule("openssl", None, Some("<3.0"));
+    assert!(deny.matches(&installed("openssl", "openssl", None, Some("1.1.1f-1ubuntu2"), "Ubuntu 22.04")));
+    assert!(!deny.matches(&installed("openssl", "openssl", None, Some("3.0.2-0ubuntu1"), "Ubuntu 22.04")));
+    // No dpkg o '~' vem antes de tudo: 3.0~rc1 < 3.0
+    assert!(deny.matches(&installed("openssl", "openssl", None, Some("3.0~rc1-1"), "Debian 12")));
+    assert!(!deny.matches(&installed("openssl", "openssl", None, Some("1:3.0.7-27.el9"), "Rocky Linux 9")));
+
+    let range = rule("openssl", None, Some(">=1.1,<3.0"));
+    assert!(range.matches(&installed("openssl", "openssl", None, Some("1.1.1k-9.el8"), "Rocky Linux 8")));
+    assert!(!range.matches(&installed("openssl", "openssl", None, Some("1.0.2k-26.el7"), "CentOS 7")));
+}
+
+#[test]
+fn rule_with_version_skips_unknown_or_invalid_versions() {
+    let deny = rule("putty", None, Some("<0.81"));
+    assert!(deny.matches(&installed("PuTTY", "putty", None, Some("0.80.0.0"), "Windows 10")));
+    assert!(!deny.matches(&installed("PuTTY", "putty", None, None, "Windows 10")));
+    // Restricao gravada invalida nunca casa
+    let broken = rule("putty", None, Some("<"));
+    assert!(!broken.matches(&installed("PuTTY", "putty", None, Some("0.80.0.0"), "Windows 10")));
+}
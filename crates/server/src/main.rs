@@ -1,21 +1,69 @@
-﻿mod socket;
-
-use axum::{routing::{get, delete}, Router, extract::{State, Path}, response::{Json}, http};
+﻿mod agents;
+mod audit;
+mod auth;
+mod compliance;
+mod config;
+mod drift;
+mod error;
+mod events;
+mod export;
+mod groups;
+mod inventory;
+mod openapi;
+mod remediation;
+mod report;
+mod shutdown;
+mod socket;
+mod tls;
+mod vuln;
+
+use axum::{routing::get, Router, extract::{State, Path}, response::{Json}, middleware};
 use tower_http::services::ServeDir;
 use sqlx::postgres::{PgPool, PgPoolOptions};
 use elasticsearch::Elasticsearch;
 use elasticsearch::http::transport::Transport;
-use std::net::SocketAddr;
+use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::{mpsc, RwLock};
+use anyhow::Context;
 use dotenvy::dotenv;
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
-
-pub struct AppState { pub pg_pool: PgPool, pub elastic_client: Elasticsearch }
+use error::{ApiError, ApiResult};
+
+pub struct AppState {
+    pub pg_pool: PgPool,
+    pub elastic_client: Elasticsearch,
+    /// Indice dos relatorios SCA e alertas (elastic.logs_index)
+    pub elastic_logs_index: String,
+    /// Agentes conectados: agent_id -> canal de saida do WebSocket
+    pub connections: RwLock<HashMap<Uuid, mpsc::UnboundedSender<String>>>,
+    /// Chave privada do Admin para assinar comandos (admin_private_key / ADMIN_PRIVATE_KEY)
+    pub admin_private_key: Option<String>,
+    /// Destinos dos alertas de drift de compliance
+    pub notifiers: Vec<Arc<dyn drift::Notifier>>,
+    /// Politicas SCA (assets/*.yaml) com os metadados das regras
+    pub policies: compliance::PolicyCatalog,
+    /// Diretorio com os feeds NVD/OSV em JSON (vuln_feed_dir / VULN_FEED_DIR)
+    pub vuln_feed_dir: std::path::PathBuf,
+    /// Encadeia os hashes da trilha de auditoria (features.audit_hash_chain)
+    pub audit_chain: bool,
+    /// Dias sem contato ate o agente ficar stale (retention.agent_stale_days)
+    pub agent_stale_days: i32,
+    /// Dias que um agente descomissionado fica guardado antes da purga (retention.agent_retention_days)
+    pub agent_retention_days: i32,
+    /// Mensagens recebidas/falhas por tipo no WebSocket dos agentes
+    pub socket_metrics: socket::metrics::SocketMetrics,
+    /// Eventos dos agentes para o stream dos operadores (/api/events)
+    pub events: events::EventBus,
+    /// Sinal de desligamento e tarefas que precisam terminar antes da saida
+    pub shutdown: shutdown::Shutdown,
+}
 
 #[derive(Serialize, sqlx::FromRow)]
 pub struct AgentRow {
     id: Uuid, hostname: String, os_name: String, status: Option<String>,
+    lifecycle: String,
     last_seen_at: Option<chrono::DateTime<chrono::Utc>>,
     compliance_score: Option<i32>
 }
@@ -38,6 +86,7 @@ pub struct SoftwareRow { name: String, version: Option<String>, vendor: Option<S
 pub struct ComplianceDetails { 
     policy_id: Option<String>, 
     score: Option<i32>, 
+    raw_score: Option<i32>,
     details: Option<serde_json::Value>
 }
 
@@ -53,70 +102,146 @@ async fn main() -> anyhow::Result<()> {
     // Tenta carregar .env, mas nao falha se nao existir (Prod usa env vars reais)
     dotenv().ok();
 
-    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL faltando");
-    let pg_pool = PgPoolOptions::new().max_connections(5).connect(&db_url).await.expect("Falha Postgres");
-    
-    // Migrations via Runtime (Funciona no Docker sem preparacao previa)
-    sqlx::migrate!("./migrations").run(&pg_pool).await.ok(); 
+    // config.yaml (ou CONFIG_FILE) + variaveis de ambiente; erros listados de uma vez
+    let config = config::Config::load()?;
+
+    let db = &config.database;
+    let pg_pool = PgPoolOptions::new()
+        .max_connections(db.max_connections)
+        .min_connections(db.min_connections)
+        .acquire_timeout(std::time::Duration::from_secs(db.acquire_timeout_secs))
+        .connect(&db.url).await
+        .with_context(|| format!("conexao com o Postgres em {}", config::redact(&db.url)))?;
+
+    // Migrations via Runtime (Funciona no Docker sem preparacao previa). Schema
+    // desatualizado nao sobe: cada handler falharia com erro de coluna.
+    sqlx::migrate!("./migrations").run(&pg_pool).await
+        .context("falha ao aplicar as migrations do banco")?;
 
-    let es_url = std::env::var("ELASTIC_URL").expect("ELASTIC_URL faltando");
-    let transport = Transport::single_node(&es_url)?;
+    let transport = Transport::single_node(&config.elastic.url)
+        .with_context(|| format!("cliente Elastic para {}", config::redact(&config.elastic.url)))?;
     let elastic_client = Elasticsearch::new(transport);
 
-    let state = Arc::new(AppState { pg_pool, elastic_client });
+    // Opcional: sem a chave o servidor funciona, mas nao envia comandos aos agentes
+    if config.admin_private_key.is_none() {
+        tracing::warn!("ADMIN_PRIVATE_KEY nao definida: envio de comandos desabilitado");
+    }
+
+    // Alertas de drift: log + Elastic + dashboard sempre, webhook se configurado
+    let events = events::EventBus::default();
+    let mut notifiers: Vec<Arc<dyn drift::Notifier>> = vec![
+        Arc::new(drift::LogNotifier),
+        Arc::new(drift::ElasticNotifier::new(elastic_client.clone(), &config.elastic.logs_index)),
+        Arc::new(drift::EventBusNotifier::new(events.clone())),
+    ];
+    if let Some(url) = &config.features.drift_webhook_url {
+        notifiers.push(Arc::new(drift::WebhookNotifier::new(url).context("cliente do webhook de drift")?));
+    }
+
+    let policies = compliance::PolicyCatalog::load(&config.server.assets_dir);
+
+    let state = Arc::new(AppState {
+        pg_pool,
+        elastic_client,
+        elastic_logs_index: config.elastic.logs_index.clone(),
+        connections: RwLock::new(HashMap::new()),
+        admin_private_key: config.admin_private_key.clone(),
+        notifiers,
+        policies,
+        vuln_feed_dir: config.vuln_feed_dir.clone(),
+        audit_chain: config.features.audit_hash_chain,
+        agent_stale_days: config.retention.agent_stale_days,
+        agent_retention_days: config.retention.agent_retention_days,
+        socket_metrics: Default::default(),
+        events,
+        shutdown: Default::default(),
+    });
+
+    // Primeiro admin via ADMIN_USERNAME/ADMIN_PASSWORD
+    auth::bootstrap_admin(&state.pg_pool).await?;
+
+    // Primeira carga da base de vulnerabilidades sem bloquear a subida
+    if config.features.vuln_auto_import {
+        tokio::spawn(vuln::import_if_empty(state.clone()));
+    }
+
+    // Agentes sem contato -> stale; descomissionados alem da retencao -> purga
+    tokio::spawn(agents::lifecycle::maintenance(state.clone()));
 
+    // Rotas /api exigem usuario autenticado (exceto as publicas de OPS); o papel minimo de cada acao
+    // eh exigido no proprio handler (extratores auth::Analyst / auth::Admin).
+    // Toda acao que altera estado fica na trilha de auditoria.
     let app = Router::new()
-        .route("/api/agents", get(list_agents))
-        .route("/api/agents/:id", delete(delete_agent))
-        .route("/api/agents/:id/details", get(get_agent_details))
+        // Rotas /api vem de openapi::OPS, a mesma tabela que gera o documento
+        .merge(openapi::router(false))
+        // Auditoria por fora da autenticacao: tentativas sem credencial (401) tambem ficam
+        // na trilha; o autor das demais vem na resposta (auth::authenticate)
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth::authenticate))
+        .route_layer(middleware::from_fn_with_state(state.clone(), audit::record))
+        // Login, logout e o proprio documento
+        .merge(openapi::router(true).route_layer(middleware::from_fn_with_state(state.clone(), audit::record)))
         .route("/ws", get(socket::ws_handler))
-        .nest_service("/", ServeDir::new("assets"))
-        .with_state(state);
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000)); // Bind 0.0.0.0 para Docker!
-    axum::Server::bind(&addr).serve(app.into_make_service()).await.unwrap();
+        .nest_service("/", ServeDir::new(&config.server.assets_dir))
+        .with_state(state.clone());
+
+    // SIGTERM: para de aceitar conexoes e avisa sockets/streams (shutdown.rs)
+    let signal = {
+        let shutdown = state.shutdown.clone();
+        async move {
+            shutdown::signal().await;
+            tracing::info!("Sinal de desligamento recebido, drenando conexoes...");
+            shutdown.begin();
+        }
+    };
+
+    let addr = config.server.bind;
+    let server = async {
+        match &config.server.tls {
+            Some(tls) => {
+                let acceptor = tls::acceptor(tls)?;
+                let listener = tokio::net::TcpListener::bind(addr).await
+                    .with_context(|| format!("nao foi possivel escutar em {}", addr))?;
+                tracing::info!("Escutando em https://{}", addr);
+                axum::Server::builder(tls::incoming(listener, acceptor))
+                    .serve(app.into_make_service())
+                    .with_graceful_shutdown(signal).await?;
+            }
+            None => {
+                let server = axum::Server::try_bind(&addr).with_context(|| format!("nao foi possivel escutar em {}", addr))?;
+                tracing::info!("Escutando em http://{}", addr);
+                server.serve(app.into_make_service()).with_graceful_shutdown(signal).await?;
+            }
+        }
+        anyhow::Ok(())
+    };
+
+    // Um unico prazo a partir do sinal, dividido entre o HTTP e o dreno dos sockets:
+    // requisicoes presas nao seguram o processo alem dele
+    let timeout = std::time::Duration::from_secs(config.server.shutdown_timeout_secs);
+    tokio::select! {
+        result = server => result?,
+        _ = async { state.shutdown.stopped().await; tokio::time::sleep_until(state.shutdown.deadline(timeout)).await } => {
+            tracing::warn!("Requisicoes HTTP ainda abertas apos {:?}", timeout);
+        }
+    }
+    shutdown::finish(&state, state.shutdown.deadline(timeout)).await;
     Ok(())
 }
 
-async fn list_agents(State(state): State<Arc<AppState>>) -> Json<Vec<AgentRow>> {
-    let sql = r#"
-        SELECT a.id, a.hostname, a.os_name, a.status, a.last_seen_at, c.score as compliance_score
-        FROM agents a
-        LEFT JOIN compliance_scores c ON a.id = c.agent_id
-        ORDER BY a.last_seen_at DESC
-    "#;
-    
-    // Runtime Query (Sem verificacao em tempo de compilacao)
-    let agents = sqlx::query_as::<_, AgentRow>(sql)
-        .fetch_all(&state.pg_pool).await.unwrap_or_default();
-    Json(agents)
-}
-
-async fn delete_agent(Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> http::StatusCode {
+async fn get_agent_details(Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> ApiResult<Json<AgentDetails>> {
     // Runtime Queries
-    let _ = sqlx::query("DELETE FROM compliance_scores WHERE agent_id = $1").bind(id).execute(&state.pg_pool).await;
-    let _ = sqlx::query("DELETE FROM software_inventory WHERE agent_id = $1").bind(id).execute(&state.pg_pool).await;
-    let _ = sqlx::query("DELETE FROM hardware_specs WHERE agent_id = $1").bind(id).execute(&state.pg_pool).await;
-    let _ = sqlx::query("DELETE FROM agents WHERE id = $1").bind(id).execute(&state.pg_pool).await;
-    http::StatusCode::NO_CONTENT
-}
+    let agent = sqlx::query_as::<_, AgentRow>("SELECT id, hostname, os_name, status, lifecycle, last_seen_at, NULL::int as compliance_score FROM agents WHERE id = $1")
+        .bind(id).fetch_optional(&state.pg_pool).await?
+        .ok_or_else(|| ApiError::not_found("agente"))?;
 
-async fn get_agent_details(Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> Json<Option<AgentDetails>> {
-    // Runtime Queries
-    let agent = sqlx::query_as::<_, AgentRow>("SELECT id, hostname, os_name, status, last_seen_at, NULL::int as compliance_score FROM agents WHERE id = $1")
-        .bind(id).fetch_optional(&state.pg_pool).await.unwrap_or(None);
+    let hw = sqlx::query_as::<_, HardwareRow>("SELECT cpu_model, ram_total_mb, disk_total_gb FROM hardware_specs WHERE agent_id = $1")
+        .bind(id).fetch_optional(&state.pg_pool).await?;
 
-    if let Some(ag) = agent {
-        let hw = sqlx::query_as::<_, HardwareRow>("SELECT cpu_model, ram_total_mb, disk_total_gb FROM hardware_specs WHERE agent_id = $1")
-            .bind(id).fetch_optional(&state.pg_pool).await.unwrap_or(None);
-        
-        let sw = sqlx::query_as::<_, SoftwareRow>("SELECT name, version, vendor, install_date FROM software_inventory WHERE agent_id = $1 ORDER BY name ASC")
-            .bind(id).fetch_all(&state.pg_pool).await.unwrap_or_default();
+    let sw = sqlx::query_as::<_, SoftwareRow>("SELECT name, version, vendor, install_date FROM software_inventory WHERE agent_id = $1 ORDER BY name ASC")
+        .bind(id).fetch_all(&state.pg_pool).await?;
 
-        let comp = sqlx::query_as::<_, ComplianceDetails>("SELECT policy_id, score, details FROM compliance_scores WHERE agent_id = $1")
-            .bind(id).fetch_optional(&state.pg_pool).await.unwrap_or(None);
+    let comp = sqlx::query_as::<_, ComplianceDetails>("SELECT policy_id, score, raw_score, details FROM compliance_scores WHERE agent_id = $1")
+        .bind(id).fetch_optional(&state.pg_pool).await?;
 
-        return Json(Some(AgentDetails { agent: ag, hardware: hw, software: sw, compliance: comp }));
-    }
-    Json(None)
+    Ok(Json(AgentDetails { agent, hardware: hw, software: sw, compliance: comp }))
 }
@@ -0,0 +1,305 @@
+﻿//! Documento OpenAPI 3 da API REST, servido em `/api/openapi.json`.
+//!
+//! As operacoes ficam na tabela `OPS` (caminhos com `:param`, como no axum), que
+//! tambem monta as rotas /api do router em `router()`: uma rota so existe se estiver
+//! documentada, e o documento nao lista rota que nao existe.
+//! AgentRow, AgentDetails e SoftwareRow tem esquema completo, conferido contra a
+//! serializacao real; as demais respostas sao descritas como objetos genericos.
+
+use axum::{response::Json, routing::{on, MethodFilter, MethodRouter}, Router};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use crate::AppState;
+use crate::{agents, audit, auth, compliance, drift, events, export, groups, inventory, remediation, report, socket, vuln};
+
+#[cfg(test)]
+mod tests;
+
+/// Formato da resposta de sucesso
+enum Reply {
+    /// 200 com JSON no esquema indicado
+    Json(&'static str),
+    /// 204 sem corpo
+    NoContent,
+    /// 200 com pagina HTML (ou anexo com ?download=true)
+    Html,
+    /// 200 com arquivo de exportacao no formato pedido em ?format=
+    Export,
+    /// 200 com server-sent events
+    Stream,
+}
+
+/// Registra o handler da operacao no metodo indicado
+type Handler = fn(MethodFilter) -> MethodRouter<Arc<AppState>>;
+
+struct Op {
+    method: &'static str,
+    path: &'static str,
+    handler: Handler,
+    tag: &'static str,
+    summary: &'static str,
+    /// Papel minimo: "public", "viewer", "analyst" ou "admin"
+    role: &'static str,
+    query: &'static [(&'static str, &'static str)],
+    body: Option<&'static str>,
+    reply: Reply,
+}
+
+// Tipos aceitos em esquemas: uuid, string, int32, int64, number, bool, date-time,
+// object, [T] para listas e nomes com inicial maiuscula para components.schemas.
+// O sufixo '?' marca o campo como anulavel (e nao obrigatorio).
+
+const AGENT_FILTERS: &[(&str, &str)] = &[
+    ("status", "string"), ("os", "string"), ("hostname", "string"),
+    ("min_score", "int32"), ("max_score", "int32"), ("tag", "string"), ("group_id", "int32"),
+    ("last_seen_after", "date-time"), ("last_seen_before", "date-time"), ("lifecycle", "string"),
+    ("sort", "string"), ("order", "string"), ("cursor", "string"), ("limit", "int64"),
+];
+const HISTORY: &[(&str, &str)] = &[
+    ("from", "date-time"), ("to", "date-time"), ("policy_id", "string"),
+    ("agent_id", "uuid"), ("group_id", "int32"), ("bucket", "string"), ("group_by", "string"),
+];
+const EXPORT: &[(&str, &str)] = &[("format", "string"), ("policy_id", "string"), ("group_id", "int32")];
+const REPORT: &[(&str, &str)] = &[("days", "int64"), ("policy_id", "string"), ("group_id", "int32"), ("download", "bool")];
+const VULNS: &[(&str, &str)] = &[("min_cvss", "number"), ("severity", "string"), ("q", "string"), ("limit", "int64")];
+
+const OPS: &[Op] = &[
+    Op { method: "post", path: "/api/auth/login", handler: |m| on(m, auth::login), tag: "auth", summary: "Abre uma sessao", role: "public", query: &[], body: Some("LoginBody"), reply: Reply::Json("object") },
+    Op { method: "post", path: "/api/auth/logout", handler: |m| on(m, auth::logout), tag: "auth", summary: "Encerra a sessao atual", role: "public", query: &[], body: None, reply: Reply::NoContent },
+    Op { method: "get", path: "/api/auth/me", handler: |m| on(m, auth::me), tag: "auth", summary: "Usuario autenticado", role: "viewer", query: &[], body: None, reply: Reply::Json("object") },
+    Op { method: "get", path: "/api/auth/api-keys", handler: |m| on(m, auth::keys::list_keys), tag: "auth", summary: "Chaves de API do usuario", role: "viewer", query: &[], body: None, reply: Reply::Json("[object]") },
+    Op { method: "post", path: "/api/auth/api-keys", handler: |m| on(m, auth::keys::create_key), tag: "auth", summary: "Cria uma chave de API", role: "viewer", query: &[], body: Some("object"), reply: Reply::Json("object") },
+    Op { method: "delete", path: "/api/auth/api-keys/:id", handler: |m| on(m, auth::keys::revoke_key), tag: "auth", summary: "Revoga uma chave de API", role: "viewer", query: &[], body: None, reply: Reply::NoContent },
+    Op { method: "get", path: "/api/users", handler: |m| on(m, auth::users::list_users), tag: "users", summary: "Lista usuarios", role: "admin", query: &[], body: None, reply: Reply::Json("[object]") },
+    Op { method: "post", path: "/api/users", handler: |m| on(m, auth::users::create_user), tag: "users", summary: "Cria usuario", role: "admin", query: &[], body: Some("object"), reply: Reply::Json("object") },
+    Op { method: "put", path: "/api/users/:id", handler: |m| on(m, auth::users::update_user), tag: "users", summary: "Altera papel, senha ou bloqueio", role: "admin", query: &[], body: Some("object"), reply: Reply::Json("object") },
+    Op { method: "delete", path: "/api/users/:id", handler: |m| on(m, auth::users::delete_user), tag: "users", summary: "Remove usuario", role: "admin", query: &[], body: None, reply: Reply::NoContent },
+    Op { method: "get", path: "/api/audit", handler: |m| on(m, audit::list), tag: "audit", summary: "Consulta o log de auditoria", role: "admin",
+         query: &[("username", "string"), ("action", "string"), ("target_type", "string"), ("target_id", "string"), ("result", "string"),
+                  ("from", "date-time"), ("to", "date-time"), ("before_id", "int64"), ("limit", "int64")],
+         body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/audit/verify", handler: |m| on(m, audit::verify), tag: "audit", summary: "Verifica o encadeamento de hashes", role: "admin", query: &[], body: None, reply: Reply::Json("object") },
+    Op { method: "get", path: "/api/agents", handler: |m| on(m, agents::list_agents), tag: "agents", summary: "Lista agentes com filtros e paginacao", role: "viewer", query: AGENT_FILTERS, body: None, reply: Reply::Json("AgentPage") },
+    Op { method: "delete", path: "/api/agents/:id", handler: |m| on(m, agents::lifecycle::delete_agent), tag: "agents", summary: "Descomissiona (ou expurga com ?purge=true)", role: "admin", query: &[("purge", "bool")], body: None, reply: Reply::NoContent },
+    Op { method: "post", path: "/api/agents/:id/block", handler: |m| on(m, agents::lifecycle::block_agent), tag: "agents", summary: "Bloqueia o reingresso do agente", role: "admin", query: &[], body: Some("object"), reply: Reply::NoContent },
+    Op { method: "post", path: "/api/agents/:id/unblock", handler: |m| on(m, agents::lifecycle::unblock_agent), tag: "agents", summary: "Remove o bloqueio", role: "admin", query: &[], body: None, reply: Reply::NoContent },
+    Op { method: "get", path: "/api/agents/:id/details", handler: |m| on(m, crate::get_agent_details), tag: "agents", summary: "Hardware, software e conformidade do agente", role: "viewer", query: &[], body: None, reply: Reply::Json("AgentDetails") },
+    Op { method: "get", path: "/api/agents/:id/tags", handler: |m| on(m, groups::agent_tags), tag: "groups", summary: "Tags do agente", role: "viewer", query: &[], body: None, reply: Reply::Json("[object]") },
+    Op { method: "put", path: "/api/agents/:id/tags", handler: |m| on(m, groups::set_agent_tags), tag: "groups", summary: "Substitui as tags manuais", role: "analyst", query: &[], body: Some("[string]"), reply: Reply::Json("[object]") },
+    Op { method: "delete", path: "/api/agents/:id/tags/:tag", handler: |m| on(m, groups::delete_agent_tag), tag: "groups", summary: "Remove uma tag", role: "analyst", query: &[], body: None, reply: Reply::NoContent },
+    Op { method: "get", path: "/api/groups", handler: |m| on(m, groups::list_groups), tag: "groups", summary: "Lista grupos", role: "viewer", query: &[], body: None, reply: Reply::Json("[object]") },
+    Op { method: "post", path: "/api/groups", handler: |m| on(m, groups::create_group), tag: "groups", summary: "Cria grupo dinamico", role: "analyst", query: &[], body: Some("object"), reply: Reply::Json("object") },
+    Op { method: "get", path: "/api/groups/:id", handler: |m| on(m, groups::get_group), tag: "groups", summary: "Detalha grupo", role: "viewer", query: &[], body: None, reply: Reply::Json("object") },
+    Op { method: "put", path: "/api/groups/:id", handler: |m| on(m, groups::update_group), tag: "groups", summary: "Altera grupo", role: "analyst", query: &[], body: Some("object"), reply: Reply::Json("object") },
+    Op { method: "delete", path: "/api/groups/:id", handler: |m| on(m, groups::delete_group), tag: "groups", summary: "Remove grupo", role: "admin", query: &[], body: None, reply: Reply::NoContent },
+    Op { method: "get", path: "/api/groups/:id/agents", handler: |m| on(m, groups::group_agents), tag: "groups", summary: "Agentes que atendem a consulta do grupo", role: "viewer", query: &[], body: None, reply: Reply::Json("[AgentRow]") },
+    Op { method: "get", path: "/api/groups/:id/policies", handler: |m| on(m, groups::policies::list_policies), tag: "groups", summary: "Politicas SCA atribuidas ao grupo", role: "viewer", query: &[], body: None, reply: Reply::Json("[string]") },
+    Op { method: "put", path: "/api/groups/:id/policies", handler: |m| on(m, groups::policies::set_policies), tag: "groups", summary: "Substitui as politicas do grupo e envia aos membros conectados", role: "analyst", query: &[], body: Some("[string]"), reply: Reply::Json("[object]") },
+    Op { method: "post", path: "/api/groups/:id/remediate", handler: |m| on(m, remediation::remediate_group), tag: "remediation", summary: "Envia remediacao a todos os membros", role: "analyst", query: &[], body: Some("object"), reply: Reply::Json("object") },
+    Op { method: "post", path: "/api/agents/:id/remediate", handler: |m| on(m, remediation::remediate_agent), tag: "remediation", summary: "Envia remediacao assinada ao agente", role: "analyst", query: &[], body: Some("object"), reply: Reply::Json("object") },
+    Op { method: "get", path: "/api/agents/:id/remediations", handler: |m| on(m, remediation::list_remediations), tag: "remediation", summary: "Historico de remediacoes do agente", role: "viewer", query: &[], body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/agents/:id/compliance/history", handler: |m| on(m, compliance::agent_history), tag: "compliance", summary: "Varreduras do agente", role: "viewer", query: HISTORY, body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/agents/:id/compliance/rules/:rule_id/history", handler: |m| on(m, compliance::rule_history), tag: "compliance", summary: "Resultados de uma regra ao longo do tempo", role: "viewer", query: HISTORY, body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/compliance/trend", handler: |m| on(m, compliance::trend), tag: "compliance", summary: "Serie temporal do score", role: "viewer", query: HISTORY, body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/compliance/matrix/:policy_id", handler: |m| on(m, compliance::compliance_matrix), tag: "compliance", summary: "Matriz regra x agente", role: "viewer",
+         query: &[("os", "string"), ("severity", "string"), ("group_id", "int32")], body: None, reply: Reply::Json("object") },
+    Op { method: "get", path: "/api/compliance/export", handler: |m| on(m, export::export_fleet), tag: "compliance", summary: "Exporta resultados da frota", role: "viewer", query: EXPORT, body: None, reply: Reply::Export },
+    Op { method: "get", path: "/api/agents/:id/compliance/export", handler: |m| on(m, export::export_agent), tag: "compliance", summary: "Exporta resultados do agente", role: "viewer", query: EXPORT, body: None, reply: Reply::Export },
+    Op { method: "get", path: "/api/compliance/report", handler: |m| on(m, report::fleet_report), tag: "compliance", summary: "Relatorio HTML da frota", role: "viewer", query: REPORT, body: None, reply: Reply::Html },
+    Op { method: "get", path: "/api/agents/:id/compliance/report", handler: |m| on(m, report::agent_report), tag: "compliance", summary: "Relatorio HTML do agente", role: "viewer", query: REPORT, body: None, reply: Reply::Html },
+    Op { method: "get", path: "/api/drift", handler: |m| on(m, drift::list_drift), tag: "compliance", summary: "Eventos de regressao e correcao", role: "viewer",
+         query: &[("agent_id", "uuid"), ("policy_id", "string"), ("kind", "string"), ("from", "date-time"), ("to", "date-time"), ("limit", "int64")],
+         body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/events", handler: |m| on(m, events::stream), tag: "agents", summary: "Stream SSE de eventos dos agentes e alertas", role: "viewer", query: &[("agent_id", "uuid")], body: None, reply: Reply::Stream },
+    Op { method: "get", path: "/api/socket/metrics", handler: |m| on(m, socket::metrics::socket_metrics), tag: "agents", summary: "Contadores de mensagens do WebSocket por tipo", role: "viewer", query: &[], body: None, reply: Reply::Json("object") },
+    Op { method: "get", path: "/api/software", handler: |m| on(m, inventory::search), tag: "software", summary: "Busca no inventario da frota", role: "viewer",
+         query: &[("q", "string"), ("vendor", "string"), ("product", "string"), ("version", "string"), ("agent_id", "uuid"), ("group_by", "string"), ("limit", "int64")],
+         body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/software/rules", handler: |m| on(m, inventory::rules::list_rules), tag: "software", summary: "Lista regras de software", role: "viewer", query: &[], body: None, reply: Reply::Json("[object]") },
+    Op { method: "post", path: "/api/software/rules", handler: |m| on(m, inventory::rules::create_rule), tag: "software", summary: "Cria regra de software", role: "analyst", query: &[], body: Some("object"), reply: Reply::Json("object") },
+    Op { method: "delete", path: "/api/software/rules/:id", handler: |m| on(m, inventory::rules::delete_rule), tag: "software", summary: "Remove regra de software", role: "admin", query: &[], body: None, reply: Reply::NoContent },
+    Op { method: "get", path: "/api/software/violations", handler: |m| on(m, inventory::rules::fleet_violations), tag: "software", summary: "Violacoes na frota", role: "viewer", query: &[("agent_id", "uuid")], body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/agents/:id/software/violations", handler: |m| on(m, inventory::rules::agent_violations), tag: "software", summary: "Violacoes do agente", role: "viewer", query: &[], body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/vulnerabilities", handler: |m| on(m, vuln::fleet_vulnerabilities), tag: "vulnerabilities", summary: "Vulnerabilidades na frota", role: "viewer", query: VULNS, body: None, reply: Reply::Json("[object]") },
+    Op { method: "post", path: "/api/vulnerabilities/import", handler: |m| on(m, vuln::import), tag: "vulnerabilities", summary: "Reimporta o feed de vulnerabilidades", role: "analyst", query: &[], body: None, reply: Reply::Json("object") },
+    Op { method: "get", path: "/api/agents/:id/vulnerabilities", handler: |m| on(m, vuln::agent_vulnerabilities), tag: "vulnerabilities", summary: "Vulnerabilidades do agente", role: "viewer", query: VULNS, body: None, reply: Reply::Json("[object]") },
+    Op { method: "get", path: "/api/openapi.json", handler: |m| on(m, spec), tag: "meta", summary: "Este documento", role: "public", query: &[], body: None, reply: Reply::Json("object") },
+];
+
+/// Esquemas nomeados em components.schemas
+fn components() -> Map<String, Value> {
+    let mut schemas = Map::new();
+    schemas.insert("Error".into(), object(&[("error", "string"), ("message", "string")]));
+    schemas.insert("LoginBody".into(), object(&[("username", "string"), ("password", "string")]));
+    schemas.insert("AgentRow".into(), object(&[
+        ("id", "uuid"), ("hostname", "string"), ("os_name", "string"), ("status", "string?"),
+        ("lifecycle", "string"), ("last_seen_at", "date-time?"), ("compliance_score", "int32?"),
+    ]));
+    schemas.insert("AgentPage".into(), object(&[("items", "[AgentRow]"), ("next_cursor", "string?"), ("total", "int64")]));
+    schemas.insert("AgentDetails".into(), object(&[
+        ("agent", "AgentRow"), ("hardware", "HardwareRow?"), ("software", "[SoftwareRow]"), ("compliance", "ComplianceDetails?"),
+    ]));
+    schemas.insert("HardwareRow".into(), object(&[("cpu_model", "string?"), ("ram_total_mb", "int64?"), ("disk_total_gb", "int64?")]));
+    schemas.insert("SoftwareRow".into(), object(&[("name", "string"), ("version", "string?"), ("vendor", "string?"), ("install_date", "string?")]));
+    schemas.insert("ComplianceDetails".into(), object(&[("policy_id", "string?"), ("score", "int32?"), ("raw_score", "int32?"), ("details", "object?")]));
+    schemas
+}
+
+/// Converte a notacao compacta de tipo em esquema
+fn schema(ty: &str) -> Value {
+    let (ty, nullable) = match ty.strip_suffix('?') {
+        Some(t) => (t, true),
+        None => (ty, false),
+    };
+    let mut s = match ty {
+        "uuid" => json!({ "type": "string", "format": "uuid" }),
+        "date-time" => json!({ "type": "string", "format": "date-time" }),
+        "string" => json!({ "type": "string" }),
+        "int32" | "int64" => json!({ "type": "integer", "format": ty }),
+        "number" => json!({ "type": "number" }),
+        "bool" => json!({ "type": "boolean" }),
+        "object" => json!({ "type": "object" }),
+        _ => match ty.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            Some(inner) => json!({ "type": "array", "items": schema(inner) }),
+            None => {
+                let reference = json!({ "$ref": format!("#/components/schemas/{}", ty) });
+                // Em OpenAPI 3.0 $ref ignora irmaos; anulavel precisa de allOf
+                return if nullable { json!({ "allOf": [reference], "nullable": true }) } else { reference };
+            }
+        },
+    };
+    if nullable {
+        s["nullable"] = json!(true);
+    }
+    s
+}
+
+fn object(fields: &[(&str, &str)]) -> Value {
+    let properties: Map<String, Value> = fields.iter().map(|(name, ty)| (name.to_string(), schema(ty))).collect();
+    let required: Vec<&str> = fields.iter().filter(|(_, ty)| !ty.ends_with('?')).map(|(name, _)| *name).collect();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Tipo de um parametro de caminho; `:id` eh inteiro em grupos e regras de software
+fn path_param(path: &str, name: &str) -> &'static str {
+    match name {
+        "id" if path.starts_with("/api/groups") || path.starts_with("/api/software/rules") => "int32",
+        "id" => "uuid",
+        "rule_id" => "int32",
+        _ => "string",
+    }
+}
+
+fn operation(op: &Op) -> Value {
+    let mut parameters: Vec<Value> = op.path.split('/')
+        .filter_map(|seg| seg.strip_prefix(':'))
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": schema(path_param(op.path, name)) }))
+        .collect();
+    parameters.extend(op.query.iter().map(|(name, ty)| json!({ "name": name, "in": "query", "required": false, "schema": schema(ty) })));
+
+    let ok = match op.reply {
+        Reply::Json(ty) => ("200", json!({ "description": "OK", "content": { "application/json": { "schema": schema(ty) } } })),
+        Reply::NoContent => ("204", json!({ "description": "Sem conteudo" })),
+        Reply::Html => ("200", json!({ "description": "Relatorio", "content": { "text/html": { "schema": { "type": "string" } } } })),
+        Reply::Export => ("200", json!({ "description": "Arquivo no formato pedido", "content": {
+            "application/json": { "schema": { "type": "object" } },
+            "text/csv": { "schema": { "type": "string" } },
+            "application/sarif+json": { "schema": { "type": "object" } },
+        } })),
+        Reply::Stream => ("200", json!({ "description": "Eventos agent_online, agent_offline, sca_report, command_result, alert e lagged",
+            "content": { "text/event-stream": { "schema": { "type": "string" } } } })),
+    };
+    let mut responses = Map::new();
+    responses.insert(ok.0.into(), ok.1);
+    if op.role != "public" {
+        responses.insert("401".into(), json!({ "$ref": "#/components/responses/Error" }));
+    }
+    if op.role == "analyst" || op.role == "admin" {
+        responses.insert("403".into(), json!({ "$ref": "#/components/responses/Error" }));
+    }
+    responses.insert("default".into(), json!({ "$ref": "#/components/responses/Error" }));
+
+    let mut o = json!({
+        "tags": [op.tag],
+        "summary": op.summary,
+        "operationId": operation_id(op),
+        "parameters": parameters,
+        "responses": responses,
+        "x-required-role": op.role,
+    });
+    if op.role == "public" {
+        o["security"] = json!([]);
+    }
+    if let Some(ty) = op.body {
+        o["requestBody"] = json!({ "required": true, "content": { "application/json": { "schema": schema(ty) } } });
+    }
+    o
+}
+
+/// "get /api/agents/:id/details" -> "get_agents_id_details"
+fn operation_id(op: &Op) -> String {
+    let rest = op.path.trim_start_matches("/api/").replace([':', '.'], "").replace(['/', '-'], "_");
+    format!("{}_{}", op.method, rest)
+}
+
+/// Caminho no formato OpenAPI: `:id` vira `{id}`
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|seg| seg.strip_prefix(':').map_or(seg.to_string(), |name| format!("{{{}}}", name)))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn document() -> Value {
+    let mut paths = Map::new();
+    for op in OPS {
+        let entry = paths.entry(openapi_path(op.path)).or_insert_with(|| json!({}));
+        entry[op.method] = operation(op);
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Blue-Taurus API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "API REST do servidor. Todas as rotas exigem autenticacao, exceto as marcadas com security vazio; x-required-role indica o papel minimo.",
+        },
+        "paths": paths,
+        "security": [{ "bearer": [] }, { "apiKey": [] }, { "session": [] }],
+        "components": {
+            "schemas": components(),
+            "responses": {
+                "Error": {
+                    "description": "Erro no formato { error, message }",
+                    "content": { "application/json": { "schema": schema("Error") } },
+                },
+            },
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+                "apiKey": { "type": "apiKey", "in": "header", "name": "X-Api-Key" },
+                "session": { "type": "apiKey", "in": "cookie", "name": "bt_session" },
+            },
+        },
+    })
+}
+
+/// Rotas de `OPS` publicas (`public == true`) ou autenticadas; as camadas ficam com main.rs
+pub fn router(public: bool) -> Router<Arc<AppState>> {
+    OPS.iter()
+        .filter(|op| (op.role == "public") == public)
+        .fold(Router::new(), |router, op| router.route(op.path, (op.handler)(method_filter(op.method))))
+}
+
+fn method_filter(method: &str) -> MethodFilter {
+    match method {
+        "get" => MethodFilter::GET,
+        "post" => MethodFilter::POST,
+        "put" => MethodFilter::PUT,
+        "delete" => MethodFilter::DELETE,
+        "patch" => MethodFilter::PATCH,
+        other => panic!("metodo sem suporte em openapi::OPS: {}", other),
+    }
+}
+
+pub async fn spec() -> Json<Value> {
+    Json(document())
+}
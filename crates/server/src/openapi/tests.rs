@@ -0,0 +1,141 @@
+﻿use std::collections::BTreeSet;
+use chrono::{TimeZone, Utc};
+use serde_json::Value;
+use uuid::Uuid;
+use crate::{AgentDetails, AgentRow, ComplianceDetails, HardwareRow, SoftwareRow};
+use super::{document, router, OPS};
+
+fn spec_routes(doc: &Value) -> BTreeSet<(String, String)> {
+    let mut routes = BTreeSet::new();
+    for (path, item) in doc["paths"].as_object().unwrap() {
+        // Volta para a sintaxe do axum: {id} -> :id
+        let axum_path = path.split('/')
+            .map(|seg| seg.strip_prefix('{').and_then(|s| s.strip_suffix('}')).map_or(seg.to_string(), |name| format!(":{}", name)))
+            .collect::<Vec<_>>()
+            .join("/");
+        for method in item.as_object().unwrap().keys() {
+            routes.insert((method.clone(), axum_path.clone()));
+        }
+    }
+    routes
+}
+
+/// Pares (metodo, caminho) da tabela; repetidos fariam o router entrar em panico
+fn table_routes() -> BTreeSet<(String, String)> {
+    let mut routes = BTreeSet::new();
+    for op in OPS {
+        assert!(op.path.starts_with("/api/"), "{} fora de /api", op.path);
+        assert!(routes.insert((op.method.to_string(), op.path.to_string())), "rota repetida: {} {}", op.method, op.path);
+    }
+    routes
+}
+
+#[test]
+fn spec_matches_table() {
+    assert_eq!(spec_routes(&document()), table_routes());
+}
+
+#[test]
+fn router_builds_from_table() {
+    // Metodo repetido no mesmo caminho ou caminho invalido entram em panico aqui
+    let _ = router(false);
+    let _ = router(true);
+}
+
+#[test]
+fn only_login_logout_and_spec_are_public() {
+    let public: BTreeSet<&str> = OPS.iter().filter(|op| op.role == "public").map(|op| op.path).collect();
+    assert_eq!(public, BTreeSet::from(["/api/auth/login", "/api/auth/logout", "/api/openapi.json"]));
+}
+
+fn collect_refs<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(r)) = map.get("$ref") {
+                out.push(r);
+            }
+            map.values().for_each(|v| collect_refs(v, out));
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_refs(v, out)),
+        _ => {}
+    }
+}
+
+#[test]
+fn refs_resolve() {
+    let doc = document();
+    let mut refs = Vec::new();
+    collect_refs(&doc, &mut refs);
+    assert!(!refs.is_empty());
+    for r in refs {
+        let pointer = r.strip_prefix('#').expect("apenas referencias locais");
+        assert!(doc.pointer(pointer).is_some(), "referencia quebrada: {}", r);
+    }
+}
+
+#[test]
+fn path_parameters_are_declared() {
+    let doc = document();
+    for (path, item) in doc["paths"].as_object().unwrap() {
+        let expected: BTreeSet<&str> = path.split('/')
+            .filter_map(|seg| seg.strip_prefix('{').and_then(|s| s.strip_suffix('}')))
+            .collect();
+        for (method, op) in item.as_object().unwrap() {
+            let declared: BTreeSet<&str> = op["parameters"].as_array().unwrap().iter()
+                .filter(|p| p["in"] == "path")
+                .map(|p| p["name"].as_str().unwrap())
+                .collect();
+            assert_eq!(declared, expected, "{} {}", method, path);
+        }
+    }
+}
+
+/// Propriedades do esquema devem coincidir com as chaves serializadas, e as
+/// obrigatorias com os campos nao anulaveis
+fn assert_schema_matches(doc: &Value, name: &str, sample: &Value) {
+    let schema = &doc["components"]["schemas"][name];
+    let properties: BTreeSet<&str> = schema["properties"].as_object()
+        .unwrap_or_else(|| panic!("esquema {} ausente", name))
+        .keys().map(String::as_str).collect();
+    let keys: BTreeSet<&str> = sample.as_object().unwrap().keys().map(String::as_str).collect();
+    assert_eq!(properties, keys, "campos de {} divergem do esquema", name);
+
+    for (field, prop) in schema["properties"].as_object().unwrap() {
+        let required = schema["required"].as_array().unwrap().iter().any(|r| r == field);
+        assert_eq!(required, prop["nullable"] != true, "{}.{}: obrigatorio x anulavel", name, field);
+        if sample[field].is_null() {
+            assert!(!required, "{}.{} veio nulo mas o esquema o exige", name, field);
+        }
+    }
+}
+
+fn agent() -> AgentRow {
+    AgentRow {
+        id: Uuid::new_v4(),
+        hostname: "web-01".to_string(),
+        os_name: "Ubuntu 22.04".to_string(),
+        status: None,
+        lifecycle: "active".to_string(),
+        last_seen_at: Some(Utc.with_ymd_and_hms(2025, 12, 15, 10, 30, 0).unwrap()),
+        compliance_score: None,
+    }
+}
+
+#[test]
+fn agent_types_match_schemas() {
+    let doc = document();
+    let software = SoftwareRow { name: "openssl".to_string(), version: Some("3.0.2".to_string()), vendor: None, install_date: None };
+    let details = AgentDetails {
+        agent: agent(),
+        hardware: Some(HardwareRow { cpu_model: None, ram_total_mb: Some(8192), disk_total_gb: None }),
+        software: vec![software],
+        compliance: Some(ComplianceDetails { policy_id: None, score: Some(80), raw_score: None, details: None }),
+    };
+    let details = serde_json::to_value(details).unwrap();
+
+    assert_schema_matches(&doc, "AgentRow", &serde_json::to_value(agent()).unwrap());
+    assert_schema_matches(&doc, "AgentDetails", &details);
+    assert_schema_matches(&doc, "HardwareRow", &details["hardware"]);
+    assert_schema_matches(&doc, "SoftwareRow", &details["software"][0]);
+    assert_schema_matches(&doc, "ComplianceDetails", &details["compliance"]);
+}
@@ -0,0 +1,141 @@
+﻿use axum::{extract::{State, Path}, response::Json};
+use serde::{Serialize, Deserialize};
+use sqlx::postgres::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::crypto;
+use shared::models::sca::{RemediationRequest, RemediationScope};
+use shared::protocol::{Message, CommandType, CommandValidity};
+use crate::AppState;
+use crate::auth::Analyst;
+use crate::error::{ApiError, ApiResult};
+
+/// Corpo do POST /api/agents/:id/remediate (sem rule_id = politica inteira)
+#[derive(Deserialize)]
+pub struct RemediateBody {
+    policy_id: String,
+    rule_id: Option<u32>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct RemediationRow {
+    id: Uuid,
+    agent_id: Uuid,
+    policy_id: String,
+    rule_id: Option<i32>,
+    dry_run: bool,
+    status: String,
+    results: Option<serde_json::Value>,
+    requested_at: chrono::DateTime<chrono::Utc>,
+    completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Resultado do envio para um membro do grupo
+#[derive(Serialize)]
+pub struct GroupRemediation {
+    agent_id: Uuid,
+    /// SENT, OFFLINE (agente desconectado) ou ERROR
+    status: &'static str,
+    remediation: Option<RemediationRow>,
+}
+
+/// Assina e envia um comando de remediacao para um agente conectado
+pub async fn remediate_agent(
+    Analyst(user): Analyst,
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<RemediateBody>,
+) -> ApiResult<Json<RemediationRow>> {
+    send_remediation(&state, id, &body, &user.username).await.map(Json)
+}
+
+/// POST /api/groups/:id/remediate - envia a remediacao a todos os membros conectados do grupo
+pub async fn remediate_group(
+    Analyst(user): Analyst,
+    Path(group_id): Path<i32>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<RemediateBody>,
+) -> ApiResult<Json<Vec<GroupRemediation>>> {
+    if state.admin_private_key.is_none() {
+        return Err(no_signing_key());
+    }
+    let members = crate::groups::members(&state.pg_pool, group_id).await?;
+
+    let mut out = Vec::with_capacity(members.len());
+    for agent_id in members {
+        let (status, remediation) = match send_remediation(&state, agent_id, &body, &user.username).await {
+            Ok(row) => ("SENT", Some(row)),
+            Err(ApiError::Conflict(_)) => ("OFFLINE", None),
+            Err(_) => ("ERROR", None),
+        };
+        out.push(GroupRemediation { agent_id, status, remediation });
+    }
+    Ok(Json(out))
+}
+
+async fn send_remediation(state: &AppState, id: Uuid, body: &RemediateBody, requested_by: &str) -> ApiResult<RemediationRow> {
+    let key = state.admin_private_key.as_deref().ok_or_else(no_signing_key)?;
+    let tx = state.connections.read().await.get(&id).cloned().ok_or_else(|| ApiError::conflict("agente offline"))?;
+
+    let request = RemediationRequest {
+        agent_id: id,
+        policy_id: body.policy_id.clone(),
+        scope: body.rule_id.map_or(RemediationScope::Policy, RemediationScope::Rule),
+        dry_run: body.dry_run,
+        validity: CommandValidity::issue(),
+    };
+    let args = serde_json::to_string(&request).map_err(|e| ApiError::Internal(format!("Falha ao serializar remediacao: {e}")))?;
+    let signature = crypto::sign_message(key, &args).map_err(|e| ApiError::Internal(format!("Falha ao assinar remediacao: {e}")))?;
+
+    let cmd_id = Uuid::new_v4();
+    let row = sqlx::query_as::<_, RemediationRow>(
+        r#"INSERT INTO remediation_actions (id, agent_id, policy_id, rule_id, dry_run, status)
+           VALUES ($1, $2, $3, $4, $5, 'SENT')
+           RETURNING id, agent_id, policy_id, rule_id, dry_run, status, results, requested_at, completed_at"#)
+        .bind(cmd_id)
+        .bind(id)
+        .bind(&request.policy_id)
+        .bind(body.rule_id.map(|r| r as i32))
+        .bind(request.dry_run)
+        .fetch_one(&state.pg_pool).await?;
+
+    let msg = Message::Command { id: cmd_id, cmd_type: CommandType::Remediate, args: Some(args), signature };
+    let text = serde_json::to_string(&msg).map_err(|e| ApiError::Internal(format!("Falha ao serializar comando: {e}")))?;
+    if tx.send(text).is_err() {
+        // Agente desconectou entre a consulta e o envio
+        record_results(&state.pg_pool, id, cmd_id, "UNDELIVERED", serde_json::Value::Null).await?;
+        return Err(ApiError::conflict("agente desconectou antes do envio"));
+    }
+
+    tracing::info!("Remediacao {} enviada para {} por {} (dry-run: {})", cmd_id, id, requested_by, request.dry_run);
+    Ok(row)
+}
+
+/// Historico de remediacoes do agente (mais recentes primeiro)
+pub async fn list_remediations(Path(id): Path<Uuid>, State(state): State<Arc<AppState>>) -> ApiResult<Json<Vec<RemediationRow>>> {
+    let rows = sqlx::query_as::<_, RemediationRow>(
+        "SELECT id, agent_id, policy_id, rule_id, dry_run, status, results, requested_at, completed_at FROM remediation_actions WHERE agent_id = $1 ORDER BY requested_at DESC")
+        .bind(id).fetch_all(&state.pg_pool).await?;
+    Ok(Json(rows))
+}
+
+/// Grava o resultado reportado pelo agente para o comando `cmd_id`; so o agente
+/// destinatario fecha o comando (cmd_id desconhecido ou de outro agente eh erro)
+pub async fn record_results(pool: &PgPool, agent_id: Uuid, cmd_id: Uuid, status: &str, results: serde_json::Value) -> anyhow::Result<()> {
+    let updated = sqlx::query("UPDATE remediation_actions SET status = $2, results = $3, completed_at = NOW() WHERE id = $1 AND agent_id = $4")
+        .bind(cmd_id)
+        .bind(status)
+        .bind(results)
+        .bind(agent_id)
+        .execute(pool).await?;
+    if updated.rows_affected() == 0 {
+        anyhow::bail!("comando {cmd_id} inexistente ou de outro agente (reportado por {agent_id})");
+    }
+    Ok(())
+}
+
+pub(crate) fn no_signing_key() -> ApiError {
+    ApiError::Unavailable("ADMIN_PRIVATE_KEY nao definida: envio de comandos desabilitado".to_string())
+}
@@ -0,0 +1,372 @@
+﻿use chrono::{DateTime, Duration, Utc};
+use shared::models::sca::{CheckResult, ComplianceReport, Severity};
+use crate::compliance::{PolicyCatalog, TrendPoint};
+use crate::AgentRow;
+use super::{AgentScore, RuleSummary};
+
+/// Estilo embutido: o arquivo abre offline e imprime em A4 pelo navegador (Salvar como PDF)
+const STYLE: &str = r#"
+* { box-sizing: border-box; }
+body { font-family: "Segoe UI", Roboto, Helvetica, Arial, sans-serif; color: #1e293b; margin: 0; background: #f8fafc; font-size: 13px; }
+main { max-width: 1040px; margin: 0 auto; padding: 32px; background: #fff; }
+header { border-bottom: 3px solid #1d4ed8; padding-bottom: 16px; margin-bottom: 24px; display: flex; justify-content: space-between; align-items: flex-end; }
+header h1 { margin: 0; font-size: 22px; }
+header .brand { color: #1d4ed8; font-weight: 700; letter-spacing: .08em; text-transform: uppercase; font-size: 11px; }
+.meta { color: #64748b; font-size: 12px; text-align: right; line-height: 1.6; }
+h2 { font-size: 15px; text-transform: uppercase; letter-spacing: .06em; color: #334155; border-bottom: 1px solid #e2e8f0; padding-bottom: 6px; margin-top: 32px; }
+.cards { display: grid; grid-template-columns: repeat(auto-fit, minmax(150px, 1fr)); gap: 12px; }
+.card { border: 1px solid #e2e8f0; border-radius: 6px; padding: 12px 14px; }
+.card .label { color: #64748b; font-size: 11px; text-transform: uppercase; letter-spacing: .05em; }
+.card .value { font-size: 26px; font-weight: 700; margin-top: 4px; }
+table { width: 100%; border-collapse: collapse; font-size: 12px; }
+th { text-align: left; color: #64748b; font-weight: 600; border-bottom: 2px solid #e2e8f0; padding: 6px 8px; }
+td { border-bottom: 1px solid #f1f5f9; padding: 6px 8px; vertical-align: top; }
+td.num, th.num { text-align: right; white-space: nowrap; }
+.badge { display: inline-block; padding: 1px 8px; border-radius: 10px; font-size: 10px; font-weight: 700; text-transform: uppercase; white-space: nowrap; }
+.good { color: #047857; } .warn { color: #b45309; } .bad { color: #b91c1c; } .muted { color: #94a3b8; }
+.badge.good { background: #d1fae5; } .badge.warn { background: #fef3c7; } .badge.bad { background: #fee2e2; } .badge.muted { background: #f1f5f9; }
+.sev-critical { background: #7f1d1d; color: #fff; } .sev-high { background: #fee2e2; color: #b91c1c; }
+.sev-medium { background: #fef3c7; color: #b45309; } .sev-low { background: #e0f2fe; color: #0369a1; }
+.failure { border: 1px solid #fecaca; border-left: 4px solid #dc2626; border-radius: 6px; padding: 12px 14px; margin-bottom: 12px; }
+.failure h3 { margin: 0 0 6px; font-size: 14px; }
+.failure p { margin: 6px 0; }
+.failure .caption { font-size: 11px; color: #64748b; text-transform: uppercase; margin-top: 8px; }
+pre { background: #f1f5f9; border-radius: 4px; padding: 8px; white-space: pre-wrap; word-break: break-word; font-size: 11px; margin: 4px 0; }
+.empty { color: #64748b; font-style: italic; }
+.print { float: right; background: #1d4ed8; color: #fff; border: 0; border-radius: 4px; padding: 6px 14px; cursor: pointer; }
+footer { margin-top: 32px; color: #94a3b8; font-size: 11px; text-align: center; }
+@page { size: A4; margin: 14mm; }
+@media print {
+  body { background: #fff; -webkit-print-color-adjust: exact; print-color-adjust: exact; }
+  main { padding: 0; max-width: none; }
+  .no-print { display: none; }
+  tr, .failure, .card, svg { break-inside: avoid; }
+  h2 { break-after: avoid; }
+}
+"#;
+
+/// Relatorio de um agente a partir do ComplianceReport gravado em compliance_scores
+pub fn agent_report(
+    agent: &AgentRow,
+    report: Option<&ComplianceReport>,
+    history: &[TrendPoint],
+    catalog: &PolicyCatalog,
+    days: i64,
+    generated_at: DateTime<Utc>,
+) -> String {
+    let mut meta = vec![
+        format!("SO: {}", escape(&agent.os_name)),
+        format!("Agente: {}", agent.id),
+    ];
+    if let Some(seen) = agent.last_seen_at {
+        meta.push(format!("Ultimo contato: {}", seen.format("%d/%m/%Y %H:%M UTC")));
+    }
+
+    let Some(report) = report else {
+        let body = r#"<p class="empty">Nenhuma varredura SCA recebida deste agente.</p>"#.to_string();
+        return page(&agent.hostname, &meta, &body, generated_at);
+    };
+
+    let policy_name = catalog.policy(&report.policy_id).map_or(report.policy_id.as_str(), |p| p.name.as_str());
+    meta.push(format!("Politica: {}", escape(policy_name)));
+
+    let failed = report.total_checks - report.passed_checks;
+    let (score, raw_score) = (report.score.map(|s| s as i32), report.raw_score.map(|s| s as i32));
+    let mut body = cards(&[
+        ("Score ponderado", score_text(score), score.map_or("muted", score_class)),
+        ("Score bruto", score_text(raw_score), raw_score.map_or("muted", score_class)),
+        ("Aprovadas", format!("{}/{}", report.passed_checks, report.total_checks), ""),
+        ("Falhas", failed.to_string(), if failed > 0 { "bad" } else { "good" }),
+        ("Nao aplicaveis", report.not_applicable.to_string(), "muted"),
+    ]);
+
+    body.push_str(&format!("<h2>Historico ({} dias)</h2>", days));
+    body.push_str(&chart(history, days, generated_at));
+
+    // Falhas: mais severas primeiro
+    let mut failures: Vec<&CheckResult> = report.results.iter().filter(|r| is_failure(&r.status)).collect();
+    failures.sort_by_key(|r| std::cmp::Reverse(r.severity.weight()));
+
+    body.push_str(&format!("<h2>Falhas ({})</h2>", failures.len()));
+    if failures.is_empty() {
+        body.push_str(r#"<p class="empty">Todas as regras aplicaveis foram aprovadas.</p>"#);
+    }
+    for r in failures {
+        let rule = catalog.rule(&report.policy_id, r.rule_id);
+        body.push_str(&failure(
+            r.rule_id as i32,
+            &r.title,
+            r.severity.as_str(),
+            rule.and_then(|x| x.rationale.as_deref()),
+            Some(&r.output),
+            None,
+            rule.and_then(|x| x.remediation.as_deref()),
+        ));
+    }
+
+    body.push_str("<h2>Regras</h2><table><thead><tr><th>#</th><th>Regra</th><th>Severidade</th><th>Status</th><th class=\"num\">Tempo</th></tr></thead><tbody>");
+    for r in &report.results {
+        body.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{} ms</td></tr>",
+            r.rule_id, escape(&r.title), severity_badge(r.severity.as_str()), status_badge(&r.status), r.duration_ms,
+        ));
+    }
+    body.push_str("</tbody></table>");
+
+    page(&agent.hostname, &meta, &body, generated_at)
+}
+
+/// Relatorio da frota a partir da ultima varredura de cada agente
+pub fn fleet_report(
+    agents: &[AgentScore],
+    rules: &[RuleSummary],
+    history: &[TrendPoint],
+    catalog: &PolicyCatalog,
+    policy_id: Option<&str>,
+    days: i64,
+    generated_at: DateTime<Utc>,
+) -> String {
+    let mut meta = vec![format!("Agentes avaliados: {}", agents.len())];
+    if let Some(policy_id) = policy_id {
+        let name = catalog.policy(policy_id).map_or(policy_id, |p| p.name.as_str());
+        meta.push(format!("Politica: {}", escape(name)));
+    }
+
+    // Agentes sem score (nenhuma regra aplicavel) ficam fora da media
+    let scores: Vec<i32> = agents.iter().filter_map(|a| a.score).collect();
+    let average = (!scores.is_empty()).then(|| scores.iter().sum::<i32>() / scores.len() as i32);
+    let critical = scores.iter().filter(|s| **s < 50).count();
+    let failing_rules = rules.iter().filter(|r| r.failed > 0).count();
+
+    let mut body = cards(&[
+        ("Score medio", score_text(average), average.map_or("muted", score_class)),
+        ("Agentes", agents.len().to_string(), ""),
+        ("Abaixo de 50%", critical.to_string(), if critical > 0 { "bad" } else { "good" }),
+        ("Regras com falha", format!("{}/{}", failing_rules, rules.len()), if failing_rules > 0 { "warn" } else { "good" }),
+    ]);
+
+    body.push_str(&format!("<h2>Historico da frota ({} dias)</h2>", days));
+    body.push_str(&chart(history, days, generated_at));
+
+    body.push_str("<h2>Agentes</h2>");
+    if agents.is_empty() {
+        body.push_str(r#"<p class="empty">Nenhuma varredura SCA registrada.</p>"#);
+    } else {
+        let mut sorted: Vec<&AgentScore> = agents.iter().collect();
+        sorted.sort_by_key(|a| a.score);
+        body.push_str("<table><thead><tr><th>Host</th><th>SO</th><th>Politica</th><th class=\"num\">Score</th><th class=\"num\">Aprovadas</th><th class=\"num\">Falhas</th><th>Ultima varredura</th></tr></thead><tbody>");
+        for a in sorted {
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num {}\"><b>{}</b></td><td class=\"num\">{}</td><td class=\"num\">{}</td><td>{}</td></tr>",
+                escape(&a.hostname), escape(&a.os_name), escape(&a.policy_id), a.score.map_or("muted", score_class), score_text(a.score),
+                a.passed, a.failed, a.scanned_at.format("%d/%m/%Y %H:%M"),
+            ));
+        }
+        body.push_str("</tbody></table>");
+    }
+
+    if !rules.is_empty() {
+        body.push_str("<h2>Regras</h2><table><thead><tr><th>#</th><th>Regra</th><th>Severidade</th><th class=\"num\">Aprovados</th><th class=\"num\">Falhas</th><th class=\"num\">N/A</th><th class=\"num\">Aprovacao</th></tr></thead><tbody>");
+        for r in rules {
+            let rate = (r.passed * 100).checked_div(r.passed + r.failed).map(|rate| rate as i32);
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num {}\">{}</td></tr>",
+                r.rule_id, escape(&r.title), severity_badge(&r.severity), r.passed, r.failed, r.not_applicable,
+                rate.map_or("muted", score_class), rate.map_or("-".to_string(), |rate| format!("{}%", rate)),
+            ));
+        }
+        body.push_str("</tbody></table>");
+    }
+
+    let failures: Vec<&RuleSummary> = rules.iter().filter(|r| r.failed > 0).collect();
+    body.push_str(&format!("<h2>Falhas ({} regras)</h2>", failures.len()));
+    if failures.is_empty() {
+        body.push_str(r#"<p class="empty">Nenhuma falha na ultima varredura dos agentes.</p>"#);
+    }
+    for r in failures {
+        let rule = catalog.rule(&r.policy_id, r.rule_id as u32);
+        body.push_str(&failure(
+            r.rule_id,
+            &r.title,
+            &r.severity,
+            rule.and_then(|x| x.rationale.as_deref()),
+            None,
+            Some(&r.failing_hosts),
+            rule.and_then(|x| x.remediation.as_deref()),
+        ));
+    }
+
+    page("Frota", &meta, &body, generated_at)
+}
+
+fn page(subject: &str, meta: &[String], body: &str, generated_at: DateTime<Utc>) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="pt-BR">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Relatorio de Conformidade - {subject}</title>
+<style>{style}</style>
+</head>
+<body>
+<main>
+<button class="print no-print" onclick="window.print()">Imprimir / PDF</button>
+<header>
+<div><div class="brand">Blue-Taurus SCA</div><h1>Relatorio de Conformidade - {subject}</h1></div>
+<div class="meta">{meta}<br>Gerado em {generated}</div>
+</header>
+{body}
+<footer>Blue-Taurus v{version} - gerado em {generated}</footer>
+</main>
+</body>
+</html>
+"#,
+        subject = escape(subject),
+        style = STYLE,
+        meta = meta.join("<br>"),
+        generated = generated_at.format("%d/%m/%Y %H:%M UTC"),
+        body = body,
+        version = shared::version(),
+    )
+}
+
+fn cards(items: &[(&str, String, &str)]) -> String {
+    let mut out = String::from(r#"<div class="cards">"#);
+    for (label, value, class) in items {
+        out.push_str(&format!(r#"<div class="card"><div class="label">{}</div><div class="value {}">{}</div></div>"#, label, class, value));
+    }
+    out.push_str("</div>");
+    out
+}
+
+fn failure(
+    rule_id: i32,
+    title: &str,
+    severity: &str,
+    rationale: Option<&str>,
+    evidence: Option<&str>,
+    hosts: Option<&[String]>,
+    remediation: Option<&str>,
+) -> String {
+    const MAX_HOSTS: usize = 25;
+
+    let mut out = format!(r#"<div class="failure"><h3>#{} {} {}</h3>"#, rule_id, escape(title), severity_badge(severity));
+    if let Some(rationale) = rationale {
+        out.push_str(&format!("<p>{}</p>", escape(rationale)));
+    }
+    if let Some(hosts) = hosts {
+        let mut list = hosts.iter().take(MAX_HOSTS).map(|h| escape(h)).collect::<Vec<_>>().join(", ");
+        if hosts.len() > MAX_HOSTS {
+            list.push_str(&format!(" e mais {}", hosts.len() - MAX_HOSTS));
+        }
+        out.push_str(&format!(r#"<div class="caption">Agentes afetados ({})</div><p>{}</p>"#, hosts.len(), list));
+    }
+    if let Some(evidence) = evidence.map(str::trim).filter(|e| !e.is_empty()) {
+        out.push_str(&format!(r#"<div class="caption">Evidencia</div><pre>{}</pre>"#, escape(evidence)));
+    }
+    out.push_str(r#"<div class="caption">Remediacao</div>"#);
+    match remediation {
+        Some(remediation) => out.push_str(&format!("<pre>{}</pre>", escape(remediation.trim()))),
+        None => out.push_str(r#"<p class="empty">Sem remediacao automatica cadastrada na politica.</p>"#),
+    }
+    out.push_str("</div>");
+    out
+}
+
+/// Grafico de linha em SVG (score 0-100 por dia), sem dependencias externas
+fn chart(history: &[TrendPoint], days: i64, generated_at: DateTime<Utc>) -> String {
+    const W: f64 = 960.0;
+    const H: f64 = 220.0;
+    const LEFT: f64 = 36.0;
+    const RIGHT: f64 = 12.0;
+    const TOP: f64 = 12.0;
+    const BOTTOM: f64 = 28.0;
+
+    if history.is_empty() {
+        return r#"<p class="empty">Sem varreduras no periodo.</p>"#.to_string();
+    }
+
+    let start = generated_at - Duration::days(days);
+    let span = (generated_at - start).num_seconds().max(1) as f64;
+    let x = |at: DateTime<Utc>| LEFT + (at - start).num_seconds().clamp(0, span as i64) as f64 / span * (W - LEFT - RIGHT);
+    let y = |score: f64| TOP + (100.0 - score.clamp(0.0, 100.0)) / 100.0 * (H - TOP - BOTTOM);
+
+    let mut svg = format!(r#"<svg viewBox="0 0 {} {}" width="100%" role="img" aria-label="Historico de score" xmlns="http://www.w3.org/2000/svg" font-size="11" font-family="sans-serif">"#, W, H);
+    for (score, color) in [(100.0, "#e2e8f0"), (80.0, "#a7f3d0"), (50.0, "#fde68a"), (0.0, "#cbd5e1")] {
+        svg.push_str(&format!(
+            r##"<line x1="{l}" x2="{r}" y1="{y}" y2="{y}" stroke="{c}" stroke-dasharray="4 3"/><text x="{tx}" y="{ty}" text-anchor="end" fill="#64748b">{s}%</text>"##,
+            l = LEFT, r = W - RIGHT, y = y(score), c = color, tx = LEFT - 6.0, ty = y(score) + 4.0, s = score,
+        ));
+    }
+
+    let points: Vec<(f64, f64, &TrendPoint)> = history.iter().map(|p| (x(p.bucket), y(p.avg_score), p)).collect();
+    let line = points.iter().map(|(px, py, _)| format!("{:.1},{:.1}", px, py)).collect::<Vec<_>>().join(" ");
+    svg.push_str(&format!(r##"<polyline points="{}" fill="none" stroke="#1d4ed8" stroke-width="2"/>"##, line));
+    for (px, py, p) in &points {
+        svg.push_str(&format!(
+            r##"<circle cx="{:.1}" cy="{:.1}" r="3" fill="#1d4ed8"><title>{}: {:.0}% ({} agente(s))</title></circle>"##,
+            px, py, p.bucket.format("%d/%m/%Y"), p.avg_score, p.agents,
+        ));
+    }
+
+    for (at, anchor) in [(start, "start"), (generated_at, "end")] {
+        svg.push_str(&format!(
+            r##"<text x="{:.1}" y="{}" text-anchor="{}" fill="#64748b">{}</text>"##,
+            x(at), H - 8.0, anchor, at.format("%d/%m/%Y"),
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn is_failure(status: &str) -> bool {
+    status != "PASS" && status != "NOT_APPLICABLE"
+}
+
+/// Percentual ou N/A quando nao ha regra aplicavel
+fn score_text(score: Option<i32>) -> String {
+    score.map_or_else(|| "N/A".to_string(), |s| format!("{}%", s))
+}
+
+fn score_class(score: i32) -> &'static str {
+    match score {
+        s if s >= 80 => "good",
+        s if s >= 50 => "warn",
+        _ => "bad",
+    }
+}
+
+fn severity_badge(severity: &str) -> String {
+    let class = match Severity::parse(severity) {
+        Some(s) => format!("sev-{}", s.as_str()),
+        None => "muted".to_string(),
+    };
+    format!(r#"<span class="badge {}">{}</span>"#, class, escape(severity))
+}
+
+fn status_badge(status: &str) -> String {
+    let class = match status {
+        "PASS" => "good",
+        "NOT_APPLICABLE" => "muted",
+        "ERROR" => "warn",
+        _ => "bad",
+    };
+    let label = if status == "NOT_APPLICABLE" { "N/A" } else { status };
+    format!(r#"<span class="badge {}">{}</span>"#, class, escape(label))
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
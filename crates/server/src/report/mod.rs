@@ -0,0 +1,160 @@
+﻿use axum::{extract::{State, Path, Query}, response::{IntoResponse, Response}, http};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::models::sca::{CheckResult, ComplianceReport, Severity};
+use crate::compliance::{self, LatestResult};
+use crate::{AgentRow, AppState, ComplianceDetails};
+use crate::error::{ApiError, ApiResult};
+
+mod html;
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    days: Option<i64>,         // Janela do grafico de historico (padrao 30)
+    policy_id: Option<String>, // Somente no relatorio da frota
+    group_id: Option<i32>,     // Somente no relatorio da frota
+    download: Option<bool>,    // true: baixa como arquivo em vez de abrir no navegador
+}
+
+impl ReportQuery {
+    fn days(&self) -> i64 {
+        self.days.unwrap_or(30).clamp(1, 365)
+    }
+}
+
+/// Ultima varredura de um agente no relatorio da frota
+pub struct AgentScore {
+    pub hostname: String,
+    pub os_name: String,
+    pub policy_id: String,
+    pub score: Option<i32>,
+    pub passed: u32,
+    pub failed: u32,
+    pub scanned_at: DateTime<Utc>,
+}
+
+/// Resultado consolidado de uma regra em todos os agentes
+pub struct RuleSummary {
+    pub policy_id: String,
+    pub rule_id: i32,
+    pub title: String,
+    pub severity: String,
+    pub passed: u32,
+    pub failed: u32,
+    pub not_applicable: u32,
+    pub failing_hosts: Vec<String>,
+}
+
+/// GET /api/agents/:id/compliance/report - relatorio HTML do agente (ultima varredura + historico)
+pub async fn agent_report(
+    Path(id): Path<Uuid>,
+    Query(q): Query<ReportQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Response> {
+    let agent = sqlx::query_as::<_, AgentRow>("SELECT id, hostname, os_name, status, lifecycle, last_seen_at, NULL::int as compliance_score FROM agents WHERE id = $1")
+        .bind(id).fetch_optional(&state.pg_pool).await?
+        .ok_or_else(|| ApiError::not_found("agente"))?;
+
+    let details = sqlx::query_as::<_, ComplianceDetails>("SELECT policy_id, score, raw_score, details FROM compliance_scores WHERE agent_id = $1")
+        .bind(id).fetch_optional(&state.pg_pool).await?;
+    let report = details.and_then(stored_report);
+
+    let now = Utc::now();
+    let days = q.days();
+    let policy_id = report.as_ref().map(|r| r.policy_id.as_str());
+    let history = compliance::trend_points(&state.pg_pool, "day", now - Duration::days(days), now, false, policy_id, Some(&[id])).await?;
+
+    let body = html::agent_report(&agent, report.as_ref(), &history, &state.policies, days, now);
+    Ok(respond(body, &agent.hostname, q.download.unwrap_or(false), now))
+}
+
+/// compliance_scores guarda so os resultados; o score gravado prevalece sobre o recalculado
+fn stored_report(details: ComplianceDetails) -> Option<ComplianceReport> {
+    let results: Vec<CheckResult> = serde_json::from_value(details.details?).ok()?;
+    let mut report = ComplianceReport::compute(details.policy_id.unwrap_or_default(), results);
+    if let Some(score) = details.score {
+        report.score = Some(score as u32);
+    }
+    if let Some(raw_score) = details.raw_score {
+        report.raw_score = Some(raw_score as u32);
+    }
+    Some(report)
+}
+
+/// GET /api/compliance/report - relatorio HTML da frota
+pub async fn fleet_report(Query(q): Query<ReportQuery>, State(state): State<Arc<AppState>>) -> ApiResult<Response> {
+    let agents = crate::groups::scope(&state.pg_pool, None, q.group_id).await?;
+    let rows = compliance::latest_results(&state.pg_pool, agents.as_deref(), q.policy_id.as_deref()).await?;
+
+    let now = Utc::now();
+    let days = q.days();
+    let history = compliance::trend_points(&state.pg_pool, "day", now - Duration::days(days), now, false, q.policy_id.as_deref(), agents.as_deref()).await?;
+
+    let (agents, rules) = summarize(&rows);
+    let body = html::fleet_report(&agents, &rules, &history, &state.policies, q.policy_id.as_deref(), days, now);
+    Ok(respond(body, "frota", q.download.unwrap_or(false), now))
+}
+
+/// Agrupa os resultados por agente/politica e por regra (regras com mais falhas primeiro)
+fn summarize(rows: &[LatestResult]) -> (Vec<AgentScore>, Vec<RuleSummary>) {
+    let mut agents: BTreeMap<(String, Uuid, String), AgentScore> = BTreeMap::new();
+    let mut rules: BTreeMap<(String, i32), RuleSummary> = BTreeMap::new();
+
+    for r in rows {
+        let agent = agents.entry((r.hostname.clone(), r.agent_id, r.policy_id.clone())).or_insert_with(|| AgentScore {
+            hostname: r.hostname.clone(),
+            os_name: r.os_name.clone(),
+            policy_id: r.policy_id.clone(),
+            score: r.score,
+            passed: 0,
+            failed: 0,
+            scanned_at: r.scanned_at,
+        });
+        let rule = rules.entry((r.policy_id.clone(), r.rule_id)).or_insert_with(|| RuleSummary {
+            policy_id: r.policy_id.clone(),
+            rule_id: r.rule_id,
+            title: r.title.clone(),
+            severity: r.severity.clone(),
+            passed: 0,
+            failed: 0,
+            not_applicable: 0,
+            failing_hosts: Vec::new(),
+        });
+
+        match r.status.as_str() {
+            "PASS" => {
+                agent.passed += 1;
+                rule.passed += 1;
+            }
+            "NOT_APPLICABLE" => rule.not_applicable += 1,
+            _ => {
+                agent.failed += 1;
+                rule.failed += 1;
+                rule.failing_hosts.push(r.hostname.clone());
+            }
+        }
+    }
+
+    let mut rules: Vec<RuleSummary> = rules.into_values().collect();
+    // Severidade desconhecida (None) fica abaixo de low
+    rules.sort_by(|a, b| b.failed.cmp(&a.failed).then(Severity::parse(&b.severity).cmp(&Severity::parse(&a.severity))));
+    (agents.into_values().collect(), rules)
+}
+
+/// HTML autocontido; `download` define se o navegador abre ou salva
+fn respond(body: String, name: &str, download: bool, generated_at: DateTime<Utc>) -> Response {
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect();
+    let filename = format!("blue-taurus-relatorio-{}-{}.html", name, generated_at.format("%Y%m%d"));
+    let disposition = if download { "attachment" } else { "inline" };
+
+    (
+        [
+            (http::header::CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+            (http::header::CONTENT_DISPOSITION, format!("{}; filename=\"{}\"", disposition, filename)),
+        ],
+        body,
+    ).into_response()
+}
@@ -0,0 +1,136 @@
+﻿//! Desligamento gracioso (SIGTERM / Ctrl+C).
+//!
+//! Ordem: o HTTP para de aceitar conexoes e conclui as requisicoes em andamento;
+//! cada socket de agente termina a mensagem que esta processando, envia
+//! `Message::Reconnect` e fecha; tarefas de gravacao em segundo plano (seguradas
+//! por `TaskGuard`) concluem; agentes que restarem sao marcados OFFLINE e o pool
+//! do Postgres eh fechado. Tudo limitado por server.shutdown_timeout_secs, contado
+//! uma unica vez a partir do sinal.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+use tokio::time::Instant;
+use uuid::Uuid;
+use crate::AppState;
+
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    stopping: watch::Sender<bool>,
+    /// Momento do sinal; base do prazo de desligamento
+    began: OnceLock<Instant>,
+    /// Sockets e gravacoes em andamento
+    active: AtomicUsize,
+    idle: Notify,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { inner: Arc::new(Inner { stopping: watch::channel(false).0, began: OnceLock::new(), active: AtomicUsize::new(0), idle: Notify::new() }) }
+    }
+}
+
+impl Shutdown {
+    pub fn begin(&self) {
+        self.inner.began.get_or_init(Instant::now);
+        self.inner.stopping.send_replace(true);
+    }
+
+    /// Prazo final do desligamento: `timeout` apos o sinal (ou apos agora, se nao houve sinal)
+    pub fn deadline(&self, timeout: Duration) -> Instant {
+        self.inner.began.get().copied().unwrap_or_else(Instant::now) + timeout
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.inner.stopping.borrow()
+    }
+
+    /// Resolve quando o desligamento comeca
+    pub async fn stopped(&self) {
+        let mut rx = self.inner.stopping.subscribe();
+        let _ = rx.wait_for(|stopping| *stopping).await;
+    }
+
+    /// Marca uma tarefa que precisa terminar antes do processo sair
+    pub fn track(&self) -> TaskGuard {
+        self.inner.active.fetch_add(1, Ordering::SeqCst);
+        TaskGuard(self.inner.clone())
+    }
+
+    /// Espera todas as tarefas rastreadas; false se o prazo acabou antes
+    async fn drained(&self, deadline: Instant) -> bool {
+        tokio::time::timeout_at(deadline, async {
+            loop {
+                let idle = self.inner.idle.notified();
+                tokio::pin!(idle);
+                idle.as_mut().enable();
+                if self.inner.active.load(Ordering::SeqCst) == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        }).await.is_ok()
+    }
+}
+
+pub struct TaskGuard(Arc<Inner>);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if self.0.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+/// Espera SIGTERM (deploys/containers) ou Ctrl+C
+pub async fn signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut s) => { s.recv().await; }
+            Err(e) => {
+                tracing::error!("Sem tratamento de SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+}
+
+/// Espera o dreno das tarefas ate o prazo, fecha as sessoes que restarem e o pool
+pub async fn finish(state: &AppState, deadline: Instant) {
+    if !state.shutdown.drained(deadline).await {
+        tracing::warn!("Desligamento: {} tarefas ainda ativas no fim do prazo, encerrando assim mesmo",
+            state.shutdown.inner.active.load(Ordering::SeqCst));
+    }
+
+    let remaining: Vec<Uuid> = state.connections.write().await.drain().map(|(id, _)| id).collect();
+    if !remaining.is_empty() {
+        if let Err(e) = sqlx::query("UPDATE agents SET status = 'OFFLINE' WHERE id = ANY($1)")
+            .bind(&remaining)
+            .execute(&state.pg_pool).await
+        {
+            tracing::error!("Erro Postgres ao fechar {} sessoes: {}", remaining.len(), e);
+        }
+    }
+    state.pg_pool.close().await;
+    tracing::info!("Desligamento concluido");
+}
+
+/// Atraso sugerido ao agente para reconectar, espalhado pelo id para evitar
+/// que a frota inteira volte no mesmo segundo
+pub fn reconnect_delay(agent_id: Option<Uuid>) -> u64 {
+    5 + agent_id.map_or(0, |id| (id.as_u128() % 25) as u64)
+}
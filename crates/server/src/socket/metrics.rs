@@ -0,0 +1,61 @@
+﻿//! Contadores das mensagens recebidas dos agentes, por tipo: quantas chegaram,
+//! quantas falharam e o ultimo erro. Expostos em GET /api/socket/metrics.
+
+use axum::{extract::State, response::Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use shared::protocol::Message;
+use crate::AppState;
+
+#[derive(Default, Clone, Serialize)]
+pub struct MessageStats {
+    received: u64,
+    failed: u64,
+    last_error: Option<String>,
+    last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+pub struct SocketMetrics {
+    by_kind: Mutex<BTreeMap<&'static str, MessageStats>>,
+}
+
+impl SocketMetrics {
+    /// Conta uma mensagem tratada; `Err` registra a falha e a mensagem de erro
+    pub fn record(&self, kind: &'static str, result: Result<(), &anyhow::Error>) {
+        let mut by_kind = self.by_kind.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = by_kind.entry(kind).or_default();
+        stats.received += 1;
+        if let Err(e) = result {
+            stats.failed += 1;
+            stats.last_error = Some(format!("{e:#}"));
+            stats.last_error_at = Some(Utc::now());
+        }
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<&'static str, MessageStats> {
+        self.by_kind.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Nome do tipo da mensagem (o mesmo do campo "type" no JSON)
+pub fn kind(msg: &Message) -> &'static str {
+    match msg {
+        Message::Handshake { .. } => "Handshake",
+        Message::HandshakeAck { .. } => "HandshakeAck",
+        Message::Heartbeat { .. } => "Heartbeat",
+        Message::InventoryReport { .. } => "InventoryReport",
+        Message::ScaReport { .. } => "ScaReport",
+        Message::Command { .. } => "Command",
+        Message::CommandResult { .. } => "CommandResult",
+        Message::RemediationReport { .. } => "RemediationReport",
+        Message::Reconnect { .. } => "Reconnect",
+    }
+}
+
+/// GET /api/socket/metrics
+pub async fn socket_metrics(State(state): State<Arc<AppState>>) -> Json<BTreeMap<&'static str, MessageStats>> {
+    Json(state.socket_metrics.snapshot())
+}
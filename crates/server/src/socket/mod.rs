@@ -1,65 +1,294 @@
-﻿use axum::{
+﻿use anyhow::Context;
+use axum::{
     extract::{ws::{Message as WsMessage, WebSocket, WebSocketUpgrade}, State},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
-use futures::stream::StreamExt; 
+use futures::{sink::SinkExt, stream::StreamExt};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use shared::models::HostInfo;
+use shared::models::sca::ComplianceReport;
 use shared::protocol::Message;
+use crate::events::AgentEvent;
 use crate::AppState;
 
-pub async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> impl IntoResponse {
+pub mod metrics;
+
+/// Tempo para o writer entregar o que estiver na fila (ex.: Reconnect) antes de fechar
+const WRITER_FLUSH: Duration = Duration::from_secs(5);
+
+pub async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
+    // Durante o desligamento o agente tenta de novo depois (retry do proprio agente)
+    if state.shutdown.is_stopping() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "servidor desligando").into_response();
+    }
     ws.on_upgrade(|socket| handle_socket(socket, state))
 }
 
+/// Estado da conexao de um agente
+struct Session {
+    /// Canal de saida ate o handshake; depois fica so no registro de conexoes
+    tx: Option<mpsc::UnboundedSender<String>>,
+    weak_tx: mpsc::WeakUnboundedSender<String>,
+    agent_id: Option<Uuid>,
+}
+
+/// O que fazer com a conexao depois de tratar uma mensagem
+enum Flow {
+    Continue,
+    Close,
+}
+
 async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
-    let (mut _sender, mut receiver) = socket.split();
-
-    while let Some(Ok(msg)) = receiver.next().await {
-        if let WsMessage::Text(text) = msg {
-            if let Ok(protocol_msg) = serde_json::from_str::<Message>(&text) {
-                match protocol_msg {
-                    Message::Handshake { agent_id, host_info, .. } => {
-                        tracing::info!("ðŸ¤ Handshake: {}", host_info.hostname);
-                        // FIX: Usando sqlx::query() (funcao) em vez de sqlx::query!() (macro)
-                        let _ = sqlx::query("INSERT INTO agents (id, hostname, os_name, status, last_seen_at) VALUES ($1, $2, $3, 'ONLINE', NOW()) ON CONFLICT(id) DO UPDATE SET last_seen_at = NOW(), status='ONLINE'")
-                            .bind(agent_id)
-                            .bind(host_info.hostname)
-                            .bind(host_info.os_name)
-                            .execute(&state.pg_pool).await;
-                    },
-                    Message::ScaReport { agent_id, report } => {
-                        tracing::info!("ðŸ›¡ï¸ SCA Report recebido de {}: Score {}%", agent_id, report.score);
-
-                        let details_json = serde_json::to_value(&report.results).unwrap_or_default();
-
-                        let q = r#"INSERT INTO compliance_scores (agent_id, policy_id, score, total_checks, passed_checks, details, last_scan_at)
-                               VALUES ($1, $2, $3, $4, $5, $6, NOW())
-                               ON CONFLICT (agent_id) DO UPDATE SET 
-                               score = EXCLUDED.score, passed_checks = EXCLUDED.passed_checks, details = EXCLUDED.details, last_scan_at = NOW()"#;
-                        
-                        // FIX: Runtime Query
-                        let _ = sqlx::query(q)
-                            .bind(agent_id)
-                            .bind(&report.policy_id)
-                            .bind(report.score as i32)
-                            .bind(report.total_checks as i32)
-                            .bind(report.passed_checks as i32)
-                            .bind(details_json)
-                            .execute(&state.pg_pool).await
-                            .map_err(|e| tracing::error!("Erro Postgres SCA: {}", e));
-
-                        // Elastic Indexing (Mantido igual)
-                        let mut doc = serde_json::to_value(&report).unwrap();
-                        if let Some(obj) = doc.as_object_mut() {
-                            obj.insert("@timestamp".to_string(), serde_json::json!(chrono::Utc::now()));
-                            obj.insert("event_type".to_string(), serde_json::json!("sca_report"));
-                            obj.insert("agent_id".to_string(), serde_json::json!(agent_id));
-                        }
-                        let _ = state.elastic_client.index(elasticsearch::IndexParts::Index("bt-logs-v1")).body(doc).send().await;
-                    },
-                    _ => {}
+    // O desligamento espera este socket terminar a mensagem atual e fechar
+    let _guard = state.shutdown.track();
+    let (mut sender, mut receiver) = socket.split();
+
+    // Canal de saida: permite que os handlers REST enviem comandos para este agente.
+    // Sem remetentes, o writer entrega o que restou na fila e fecha o WebSocket.
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    let mut writer = tokio::spawn(async move {
+        while let Some(text) = rx.recv().await {
+            if sender.send(WsMessage::Text(text)).await.is_err() { return; }
+        }
+        let _ = sender.close().await;
+    });
+    let mut writer_done = false;
+    // O socket so guarda uma referencia fraca ao canal: removido do registro
+    // (agente descomissionado/bloqueado), o canal fecha e a conexao eh encerrada
+    let mut session = Session { weak_tx: tx.downgrade(), tx: Some(tx), agent_id: None };
+
+    loop {
+        let msg = tokio::select! {
+            msg = receiver.next() => msg,
+            _ = &mut writer => {
+                writer_done = true;
+                break;
+            }
+            _ = state.shutdown.stopped() => {
+                let hint = Message::Reconnect {
+                    after_secs: crate::shutdown::reconnect_delay(session.agent_id),
+                    reason: "servidor reiniciando".to_string(),
+                };
+                if let (Some(tx), Ok(text)) = (session.weak_tx.upgrade(), serde_json::to_string(&hint)) {
+                    let _ = tx.send(text);
                 }
+                break;
+            }
+        };
+        let Some(Ok(msg)) = msg else { break };
+        let WsMessage::Text(text) = msg else { continue };
+
+        let protocol_msg = match serde_json::from_str::<Message>(&text) {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::warn!("Mensagem invalida de {:?}: {}", session.agent_id, e);
+                state.socket_metrics.record("invalid", Err(&anyhow::Error::from(e)));
+                continue;
+            }
+        };
+        let kind = metrics::kind(&protocol_msg);
+        let result = handle_message(&state, &mut session, protocol_msg).await;
+        state.socket_metrics.record(kind, result.as_ref().map(|_| ()));
+        match result {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Close) => break,
+            Err(e) => {
+                tracing::error!("Falha ao processar {} de {:?}: {:#}", kind, session.agent_id, e);
+                // Sem handshake concluido a conexao nao tem utilidade
+                if session.agent_id.is_none() { break; }
             }
         }
     }
+
+    // Remove a conexao do registro (somente se ainda for a deste socket, o agente pode ter reconectado)
+    if let Some(agent_id) = session.agent_id {
+        let reconnected = {
+            let mut connections = state.connections.write().await;
+            match connections.get(&agent_id) {
+                Some(c) if session.weak_tx.upgrade().is_some_and(|tx| c.same_channel(&tx)) => {
+                    connections.remove(&agent_id);
+                    false
+                }
+                // Ja removido pelo ciclo de vida (exclusao/bloqueio) ou substituido por nova conexao
+                current => current.is_some(),
+            }
+        };
+        if !reconnected {
+            disconnected(&state, agent_id).await;
+        }
+    }
+    // Libera o remetente de socket sem handshake para o writer terminar
+    drop(session);
+    if !writer_done && tokio::time::timeout(WRITER_FLUSH, &mut writer).await.is_err() {
+        writer.abort();
+    }
+}
+
+async fn disconnected(state: &AppState, agent_id: Uuid) {
+    if let Err(e) = sqlx::query("UPDATE agents SET status = 'OFFLINE' WHERE id = $1").bind(agent_id).execute(&state.pg_pool).await {
+        tracing::error!("Erro Postgres ao marcar {} offline: {}", agent_id, e);
+    }
+    state.events.publish(AgentEvent::AgentOffline { agent_id });
+}
+
+async fn handle_message(state: &Arc<AppState>, session: &mut Session, msg: Message) -> anyhow::Result<Flow> {
+    // Dados so depois do handshake (onde enroll recusa bloqueados) e somente do
+    // proprio agente: sem isso um socket novo poderia escrever em nome de qualquer id
+    let claimed = match &msg {
+        Message::Handshake { .. } | Message::HandshakeAck { .. } | Message::Command { .. } | Message::Reconnect { .. } => None,
+        Message::Heartbeat { agent_id, .. }
+        | Message::InventoryReport { agent_id, .. }
+        | Message::ScaReport { agent_id, .. }
+        | Message::RemediationReport { agent_id, .. } => Some(Some(*agent_id)),
+        Message::CommandResult { .. } => Some(session.agent_id),
+    };
+    if let Some(claimed) = claimed {
+        if session.agent_id.is_none() || claimed != session.agent_id {
+            tracing::warn!("Mensagem de {:?} recusada na sessao de {:?}: handshake ausente ou id divergente", claimed, session.agent_id);
+            return Ok(Flow::Close);
+        }
+    }
+
+    match msg {
+        Message::Handshake { agent_id, host_info, .. } => handshake(state, session, agent_id, host_info).await,
+        Message::Heartbeat { agent_id, .. } => {
+            sqlx::query("UPDATE agents SET last_seen_at = NOW(), status = 'ONLINE' WHERE id = $1 AND lifecycle <> 'blocked'")
+                .bind(agent_id)
+                .execute(&state.pg_pool).await
+                .context("heartbeat")?;
+            Ok(Flow::Continue)
+        }
+        Message::InventoryReport { agent_id, software } => {
+            store_inventory(state, agent_id, &software).await?;
+            Ok(Flow::Continue)
+        }
+        Message::ScaReport { agent_id, report, assigned } => {
+            sca_report(state, agent_id, &report, assigned).await?;
+            Ok(Flow::Continue)
+        }
+        Message::RemediationReport { agent_id, cmd_id, results, .. } => {
+            tracing::info!("Remediacao {} concluida em {} ({} regras)", cmd_id, agent_id, results.len());
+            let results = serde_json::to_value(&results)?;
+            crate::remediation::record_results(&state.pg_pool, agent_id, cmd_id, "COMPLETED", results).await
+                .with_context(|| format!("resultado da remediacao {cmd_id}"))?;
+            state.events.publish(AgentEvent::CommandResult { agent_id: Some(agent_id), cmd_id, status: "COMPLETED".to_string() });
+            Ok(Flow::Continue)
+        }
+        Message::CommandResult { cmd_id, status, stderr, .. } => {
+            tracing::warn!("Comando {} retornou {}: {}", cmd_id, status, stderr);
+            let agent_id = session.agent_id.context("resultado de comando sem handshake")?;
+            crate::remediation::record_results(&state.pg_pool, agent_id, cmd_id, &status, serde_json::json!({ "error": stderr })).await
+                .with_context(|| format!("resultado do comando {cmd_id}"))?;
+            state.events.publish(AgentEvent::CommandResult { agent_id: Some(agent_id), cmd_id, status });
+            Ok(Flow::Continue)
+        }
+        // Mensagens do servidor para o agente: nao esperadas aqui
+        Message::HandshakeAck { .. } | Message::Command { .. } | Message::Reconnect { .. } => Ok(Flow::Continue),
+    }
+}
+
+async fn handshake(state: &Arc<AppState>, session: &mut Session, agent_id: Uuid, host_info: HostInfo) -> anyhow::Result<Flow> {
+    tracing::info!("ðŸ¤ Handshake: {}", host_info.hostname);
+    let enrolled = crate::agents::lifecycle::enroll(&state.pg_pool, agent_id, &host_info.hostname, &host_info.os_name).await
+        .context("registro do agente")?;
+    if !enrolled {
+        tracing::warn!("Handshake recusado: agente {} bloqueado", agent_id);
+        return Ok(Flow::Close);
+    }
+
+    let Some(tx) = session.tx.take().or_else(|| session.weak_tx.upgrade()) else {
+        return Ok(Flow::Close);
+    };
+    state.connections.write().await.insert(agent_id, tx);
+    session.agent_id = Some(agent_id);
+    state.events.publish(AgentEvent::AgentOnline {
+        agent_id,
+        hostname: host_info.hostname.clone(),
+        os_name: host_info.os_name.clone(),
+    });
+
+    crate::groups::store_reported_tags(&state.pg_pool, agent_id, &host_info.tags).await
+        .context("tags reportadas")?;
+    store_inventory(state, agent_id, &host_info.software).await?;
+    // Depois das tags: a participacao nos grupos pode depender delas
+    crate::groups::policies::deliver_assigned(state, agent_id).await
+        .context("politicas atribuidas")?;
+    Ok(Flow::Continue)
+}
+
+/// Inventario de software + recalculo das vulnerabilidades do agente
+async fn store_inventory(state: &Arc<AppState>, agent_id: Uuid, software: &[shared::models::SoftwareInfo]) -> anyhow::Result<()> {
+    crate::inventory::store_software(&state.pg_pool, agent_id, software).await
+        .context("inventario de software")?;
+    let pool = state.pg_pool.clone();
+    let guard = state.shutdown.track();
+    tokio::spawn(async move {
+        let _guard = guard;
+        if let Err(e) = crate::vuln::match_agent(&pool, agent_id).await {
+            tracing::error!("Erro Postgres vulnerabilidades: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// `assigned`: politica de grupo, que nao substitui o score atual (politica local do agente)
+async fn sca_report(state: &AppState, agent_id: Uuid, report: &ComplianceReport, assigned: bool) -> anyhow::Result<()> {
+    tracing::info!("ðŸ›¡ï¸ SCA Report recebido de {}: Score {}", agent_id,
+        report.score.map_or_else(|| "N/A".to_string(), |s| format!("{}%", s)));
+
+    if !assigned {
+        store_current_score(state, agent_id, report).await?;
+    }
+
+    // Historico append-only (scan + resultado por regra) e deteccao de drift
+    let scan_id = crate::compliance::record_scan(&state.pg_pool, agent_id, report).await
+        .context("historico SCA")?;
+    crate::drift::check_drift(state, agent_id, report, scan_id).await
+        .context("drift SCA")?;
+    state.events.publish(AgentEvent::ScaReport {
+        agent_id,
+        policy_id: report.policy_id.clone(),
+        score: report.score.map(|s| s as i32),
+        scan_id,
+    });
+
+    // Elastic Indexing
+    let mut doc = serde_json::to_value(report)?;
+    if let Some(obj) = doc.as_object_mut() {
+        obj.insert("@timestamp".to_string(), serde_json::json!(chrono::Utc::now()));
+        obj.insert("event_type".to_string(), serde_json::json!("sca_report"));
+        obj.insert("agent_id".to_string(), serde_json::json!(agent_id));
+    }
+    state.elastic_client.index(elasticsearch::IndexParts::Index(&state.elastic_logs_index)).body(doc).send().await
+        .and_then(|r| r.error_for_status_code())
+        .context("indexacao no Elastic")?;
+    Ok(())
+}
+
+/// Ultimo estado da politica local do agente (filtros, detalhes e relatorio do agente)
+async fn store_current_score(state: &AppState, agent_id: Uuid, report: &ComplianceReport) -> anyhow::Result<()> {
+    let details_json = serde_json::to_value(&report.results)?;
+
+    let q = r#"INSERT INTO compliance_scores (agent_id, policy_id, score, raw_score, total_checks, passed_checks, details, last_scan_at)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+           ON CONFLICT (agent_id) DO UPDATE SET 
+           policy_id = EXCLUDED.policy_id, score = EXCLUDED.score, raw_score = EXCLUDED.raw_score, total_checks = EXCLUDED.total_checks,
+           passed_checks = EXCLUDED.passed_checks, details = EXCLUDED.details, last_scan_at = NOW()"#;
+
+    // FIX: Runtime Query
+    sqlx::query(q)
+        .bind(agent_id)
+        .bind(&report.policy_id)
+        .bind(report.score.map(|s| s as i32))
+        .bind(report.raw_score.map(|s| s as i32))
+        .bind(report.total_checks as i32)
+        .bind(report.passed_checks as i32)
+        .bind(details_json)
+        .execute(&state.pg_pool).await
+        .context("score SCA")?;
+    Ok(())
 }
@@ -0,0 +1,52 @@
+﻿//! HTTPS/WSS direto no servidor (server.tls na configuracao), via native-tls.
+
+use anyhow::Context;
+use hyper::server::accept::{self, Accept};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_native_tls::{native_tls, TlsAcceptor, TlsStream};
+use crate::config::TlsConfig;
+
+/// Tempo maximo para o cliente concluir o handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn acceptor(config: &TlsConfig) -> anyhow::Result<TlsAcceptor> {
+    let cert = std::fs::read(&config.cert).with_context(|| format!("leitura do certificado {}", config.cert.display()))?;
+    let key = std::fs::read(&config.key).with_context(|| format!("leitura da chave {}", config.key.display()))?;
+    let identity = native_tls::Identity::from_pkcs8(&cert, &key)
+        .context("certificado/chave TLS invalidos (esperado PEM com chave PKCS#8)")?;
+    Ok(native_tls::TlsAcceptor::new(identity).context("configuracao TLS")?.into())
+}
+
+/// Conexoes ja com TLS estabelecido. Os handshakes rodam em paralelo para que
+/// um cliente lento nao segure os demais; quando o servidor descarta o acceptor
+/// (desligamento) o listener eh fechado.
+pub fn incoming(listener: TcpListener, acceptor: TlsAcceptor) -> impl Accept<Conn = TlsStream<TcpStream>, Error = std::io::Error> {
+    let (tx, rx) = mpsc::channel::<std::io::Result<TlsStream<TcpStream>>>(64);
+    tokio::spawn(async move {
+        loop {
+            let (tcp, peer) = tokio::select! {
+                conn = listener.accept() => match conn {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        tracing::warn!("Falha ao aceitar conexao: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                },
+                _ = tx.closed() => return,
+            };
+            let acceptor = acceptor.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp)).await {
+                    Ok(Ok(stream)) => { let _ = tx.send(Ok(stream)).await; }
+                    Ok(Err(e)) => tracing::debug!("Handshake TLS com {} falhou: {}", peer, e),
+                    Err(_) => tracing::debug!("Handshake TLS com {} expirou", peer),
+                }
+            });
+        }
+    });
+    accept::from_stream(futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|conn| (conn, rx)) }))
+}
@@ -0,0 +1,65 @@
+﻿//! Calculo do score base CVSS v3.0/v3.1 a partir do vetor (OSV publica so o vetor).
+
+/// "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H" -> 9.8
+pub fn base_score(vector: &str) -> Option<f32> {
+    if !vector.starts_with("CVSS:3") {
+        return None;
+    }
+
+    let metric = |name: &str| -> Option<&str> {
+        vector.split('/').find_map(|p| p.strip_prefix(name).and_then(|v| v.strip_prefix(':')))
+    };
+    let scope_changed = match metric("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return None,
+    };
+    let av = match metric("AV")? { "N" => 0.85, "A" => 0.62, "L" => 0.55, "P" => 0.2, _ => return None };
+    let ac = match metric("AC")? { "L" => 0.77, "H" => 0.44, _ => return None };
+    let pr = match (metric("PR")?, scope_changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let ui = match metric("UI")? { "N" => 0.85, "R" => 0.62, _ => return None };
+    let cia = |v: &str| match v { "H" => Some(0.56), "L" => Some(0.22), "N" => Some(0.0), _ => None };
+    let (c, i, a) = (cia(metric("C")?)?, cia(metric("I")?)?, cia(metric("A")?)?);
+
+    let iss = 1.0 - (1.0 - c) * (1.0 - i) * (1.0 - a);
+    let impact = if scope_changed {
+        7.52 * (iss - 0.029) - 3.25 * f64::powi(iss - 0.02, 15)
+    } else {
+        6.42 * iss
+    };
+    let exploitability = 8.22 * av * ac * pr * ui;
+
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let score = if scope_changed { 1.08 * (impact + exploitability) } else { impact + exploitability };
+    Some(roundup(score.min(10.0)) as f32)
+}
+
+/// Faixa qualitativa do CVSS v3
+pub fn severity(score: f32) -> &'static str {
+    match score {
+        s if s >= 9.0 => "critical",
+        s if s >= 7.0 => "high",
+        s if s >= 4.0 => "medium",
+        s if s > 0.0 => "low",
+        _ => "none",
+    }
+}
+
+/// Roundup da especificacao 3.1 (evita erros de ponto flutuante)
+fn roundup(value: f64) -> f64 {
+    let int_input = (value * 100_000.0).round() as i64;
+    if int_input % 10_000 == 0 {
+        int_input as f64 / 100_000.0
+    } else {
+        (int_input / 10_000 + 1) as f64 / 10.0
+    }
+}
@@ -0,0 +1,251 @@
+﻿//! Leitura dos feeds offline: NVD CVE API 2.0 (arquivos com "vulnerabilities") e OSV
+//! (um registro por arquivo ou lista de registros).
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::Value;
+use std::path::Path;
+use super::cvss;
+
+pub struct Advisory {
+    pub id: String,
+    pub source: &'static str,
+    pub aliases: Vec<String>,
+    pub summary: Option<String>,
+    pub cvss_score: Option<f32>,
+    pub cvss_vector: Option<String>,
+    pub severity: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub affected: Vec<AffectedRange>,
+}
+
+/// Faixa afetada: sem limites e sem `versions` = todas as versoes
+pub struct AffectedRange {
+    pub ecosystem: String,        // "cpe" (NVD) ou ecossistema OSV em minusculas ("debian", "ubuntu"...)
+    pub vendor: Option<String>,
+    pub product: String,
+    pub introduced: Option<String>,
+    pub introduced_inclusive: bool,
+    pub fixed: Option<String>,
+    pub last_affected: Option<String>,
+    pub versions: Vec<String>,
+}
+
+/// Le todos os `*.json` do diretorio (recursivo). Retorna (arquivos lidos, advisories).
+pub fn load_dir(dir: &Path) -> anyhow::Result<(usize, Vec<Advisory>)> {
+    let mut files = Vec::new();
+    collect_json(dir, &mut files)?;
+
+    let mut advisories = Vec::new();
+    for path in &files {
+        let text = std::fs::read_to_string(path)?;
+        match serde_json::from_str::<Value>(&text) {
+            Ok(json) => advisories.extend(parse(&json)),
+            Err(e) => tracing::warn!("Feed de vulnerabilidades ignorado {}: {}", path.display(), e),
+        }
+    }
+    Ok((files.len(), advisories))
+}
+
+fn collect_json(dir: &Path, out: &mut Vec<std::path::PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_json(&path, out)?;
+        } else if path.extension().is_some_and(|e| e == "json") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub fn parse(json: &Value) -> Vec<Advisory> {
+    if let Some(items) = json["vulnerabilities"].as_array() {
+        items.iter().filter_map(|item| parse_nvd(&item["cve"])).collect()
+    } else if let Some(items) = json.as_array() {
+        items.iter().filter_map(parse_osv).collect()
+    } else {
+        parse_osv(json).into_iter().collect()
+    }
+}
+
+fn parse_nvd(cve: &Value) -> Option<Advisory> {
+    let id = cve["id"].as_str()?.to_string();
+    let summary = cve["descriptions"].as_array()
+        .and_then(|d| d.iter().find(|x| x["lang"] == "en"))
+        .and_then(|d| d["value"].as_str())
+        .map(str::to_string);
+
+    // Preferencia: v3.1 > v3.0 > v2, metrica "Primary" (NVD) quando houver
+    let metric = ["cvssMetricV31", "cvssMetricV30", "cvssMetricV2"].iter()
+        .filter_map(|k| cve["metrics"][k].as_array())
+        .find_map(|m| m.iter().find(|x| x["type"] == "Primary").or(m.first()));
+    let cvss_score = metric.and_then(|m| m["cvssData"]["baseScore"].as_f64()).map(|s| s as f32);
+    let cvss_vector = metric.and_then(|m| m["cvssData"]["vectorString"].as_str()).map(str::to_string);
+
+    let mut affected = Vec::new();
+    for config in cve["configurations"].as_array().into_iter().flatten() {
+        for node in config["nodes"].as_array().into_iter().flatten() {
+            for m in node["cpeMatch"].as_array().into_iter().flatten() {
+                if m["vulnerable"] != true {
+                    continue;
+                }
+                if let Some(range) = m["criteria"].as_str().and_then(|cpe| nvd_range(cpe, m)) {
+                    affected.push(range);
+                }
+            }
+        }
+    }
+
+    Some(Advisory {
+        id,
+        source: "NVD",
+        aliases: Vec::new(),
+        summary,
+        severity: cvss_score.map(|s| cvss::severity(s).to_string()),
+        cvss_score,
+        cvss_vector,
+        published: cve["published"].as_str().and_then(parse_date),
+        affected,
+    })
+}
+
+/// cpe:2.3:a:vendor:product:version:... + limites versionStart*/versionEnd*
+fn nvd_range(cpe: &str, m: &Value) -> Option<AffectedRange> {
+    let fields = split_cpe(cpe);
+    if fields.len() < 6 || fields[0] != "cpe" {
+        return None;
+    }
+    let (vendor, product, version) = (&fields[3], &fields[4], &fields[5]);
+    if version == "-" {
+        return None;
+    }
+
+    let bound = |k: &str| m[k].as_str().map(str::to_string);
+    let (introduced, introduced_inclusive) = match (bound("versionStartIncluding"), bound("versionStartExcluding")) {
+        (Some(v), _) => (Some(v), true),
+        (None, Some(v)) => (Some(v), false),
+        (None, None) => (None, true),
+    };
+
+    Some(AffectedRange {
+        ecosystem: "cpe".to_string(),
+        vendor: Some(vendor.clone()).filter(|v| v != "*"),
+        product: product.clone(),
+        introduced,
+        introduced_inclusive,
+        fixed: bound("versionEndExcluding"),
+        last_affected: bound("versionEndIncluding"),
+        versions: if version == "*" { Vec::new() } else { vec![version.clone()] },
+    })
+}
+
+/// Separa os campos da CPE 2.3 respeitando "\:" e remove os escapes
+fn split_cpe(cpe: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut escaped = false;
+    for c in cpe.chars() {
+        match c {
+            _ if escaped => {
+                fields.last_mut().unwrap().push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            ':' => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+fn parse_osv(v: &Value) -> Option<Advisory> {
+    let id = v["id"].as_str()?.to_string();
+    let aliases = v["aliases"].as_array().into_iter().flatten()
+        .filter_map(|a| a.as_str().map(str::to_string))
+        .collect();
+    let summary = v["summary"].as_str()
+        .or_else(|| v["details"].as_str().and_then(|d| d.lines().next()))
+        .map(str::to_string);
+
+    let cvss_vector = v["severity"].as_array().into_iter().flatten()
+        .find(|s| s["type"] == "CVSS_V3")
+        .and_then(|s| s["score"].as_str())
+        .map(str::to_string);
+    let cvss_score = cvss_vector.as_deref().and_then(cvss::base_score);
+    let severity = cvss_score.map(|s| cvss::severity(s).to_string())
+        .or_else(|| v["database_specific"]["severity"].as_str().map(osv_severity));
+
+    let mut affected = Vec::new();
+    for a in v["affected"].as_array().into_iter().flatten() {
+        let Some(name) = a["package"]["name"].as_str() else { continue };
+        let ecosystem = a["package"]["ecosystem"].as_str().unwrap_or_default();
+        // "Debian:12" -> "debian"
+        let ecosystem = ecosystem.split(':').next().unwrap_or_default().to_lowercase();
+        let range = |introduced: Option<String>, fixed: Option<String>, last_affected: Option<String>, versions: Vec<String>| AffectedRange {
+            ecosystem: ecosystem.clone(),
+            vendor: None,
+            product: name.to_lowercase(),
+            introduced,
+            introduced_inclusive: true,
+            fixed,
+            last_affected,
+            versions,
+        };
+
+        let before = affected.len();
+        for r in a["ranges"].as_array().into_iter().flatten() {
+            if !matches!(r["type"].as_str(), Some("ECOSYSTEM" | "SEMVER")) {
+                continue;
+            }
+            let mut introduced: Option<Option<String>> = None;
+            for event in r["events"].as_array().into_iter().flatten() {
+                if let Some(v) = event["introduced"].as_str() {
+                    introduced = Some(Some(v.to_string()).filter(|v| v != "0"));
+                } else if let Some(v) = event["fixed"].as_str() {
+                    affected.push(range(introduced.take().flatten(), Some(v.to_string()), None, Vec::new()));
+                } else if let Some(v) = event["last_affected"].as_str() {
+                    affected.push(range(introduced.take().flatten(), None, Some(v.to_string()), Vec::new()));
+                }
+            }
+            // Introduzida e ainda sem correcao
+            if let Some(open) = introduced {
+                affected.push(range(open, None, None, Vec::new()));
+            }
+        }
+
+        // Sem faixas comparaveis: usa a lista explicita de versoes
+        if affected.len() == before {
+            let versions: Vec<String> = a["versions"].as_array().into_iter().flatten()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect();
+            if !versions.is_empty() {
+                affected.push(range(None, None, None, versions));
+            }
+        }
+    }
+
+    Some(Advisory {
+        id,
+        source: "OSV",
+        aliases,
+        summary,
+        cvss_score,
+        cvss_vector,
+        severity,
+        published: v["published"].as_str().and_then(parse_date),
+        affected,
+    })
+}
+
+/// Severidade textual do GHSA/OSV ("MODERATE" -> "medium")
+fn osv_severity(value: &str) -> String {
+    match value.to_lowercase().as_str() {
+        "moderate" => "medium".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// RFC 3339 (OSV) ou "2024-01-02T03:04:05.123" sem fuso (NVD, em UTC)
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).map(|d| d.with_timezone(&Utc)).ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").ok().map(|d| d.and_utc()))
+}
@@ -0,0 +1,355 @@
+﻿use axum::{extract::{State, Path, Query}, response::Json};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::postgres::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+use shared::software::{self, Ecosystem};
+use crate::AppState;
+use crate::auth::Analyst;
+use crate::error::ApiResult;
+
+mod cvss;
+mod feed;
+#[cfg(test)]
+mod tests;
+
+use feed::Advisory;
+
+/// Lote de advisories por INSERT na importacao
+const IMPORT_CHUNK: usize = 1000;
+
+#[derive(Serialize)]
+pub struct ImportSummary {
+    files: usize,
+    advisories: usize,
+    ranges: usize,
+    agents_matched: usize,
+    findings: usize,
+}
+
+#[derive(Deserialize)]
+pub struct VulnQuery {
+    min_cvss: Option<f32>,
+    severity: Option<String>, // critical, high, medium, low
+    q: Option<String>,        // Busca em id, resumo e nome do software
+    limit: Option<i64>,
+}
+
+/// Achado de um agente
+#[derive(Serialize, sqlx::FromRow)]
+pub struct AgentFinding {
+    vuln_id: String,
+    aliases: Vec<String>,
+    summary: Option<String>,
+    severity: Option<String>,
+    cvss_score: Option<f32>,
+    cvss_vector: Option<String>,
+    software_name: String,
+    software_version: Option<String>,
+    fixed_version: Option<String>,
+    detected_at: DateTime<Utc>,
+}
+
+/// Vulnerabilidade consolidada na frota
+#[derive(Serialize, sqlx::FromRow)]
+pub struct FleetFinding {
+    vuln_id: String,
+    summary: Option<String>,
+    severity: Option<String>,
+    cvss_score: Option<f32>,
+    affected_agents: i64,
+    hosts: Vec<String>,
+    software: Vec<String>,
+    fixed_versions: Vec<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct RangeRow {
+    vuln_id: String,
+    ecosystem: String,
+    vendor: Option<String>,
+    product: String,
+    introduced: Option<String>,
+    introduced_inclusive: bool,
+    fixed: Option<String>,
+    last_affected: Option<String>,
+    versions: Vec<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct InstalledRow {
+    name: String,
+    version: Option<String>,
+    vendor: Option<String>,
+}
+
+/// POST /api/vulnerabilities/import - recarrega a base do diretorio do feed e recalcula a frota
+pub async fn import(_: Analyst, State(state): State<Arc<AppState>>) -> ApiResult<Json<ImportSummary>> {
+    Ok(Json(import_feed(&state).await?))
+}
+
+/// Na subida: importa o feed somente se a base estiver vazia
+pub async fn import_if_empty(state: Arc<AppState>) {
+    let empty = match sqlx::query_scalar::<_, bool>("SELECT NOT EXISTS (SELECT 1 FROM vulnerabilities)")
+        .fetch_one(&state.pg_pool).await
+    {
+        Ok(empty) => empty,
+        Err(e) => {
+            tracing::error!("Erro Postgres vulnerabilidades: {}", e);
+            return;
+        }
+    };
+    if !empty || !state.vuln_feed_dir.is_dir() {
+        return;
+    }
+    match import_feed(&state).await {
+        Ok(s) => tracing::info!("Feed de vulnerabilidades importado: {} advisories, {} achados", s.advisories, s.findings),
+        Err(e) => tracing::error!("Falha ao importar feed de vulnerabilidades: {:#}", e),
+    }
+}
+
+async fn import_feed(state: &AppState) -> anyhow::Result<ImportSummary> {
+    let dir = state.vuln_feed_dir.clone();
+    let (files, advisories) = tokio::task::spawn_blocking(move || feed::load_dir(&dir)).await??;
+
+    // NVD e OSV podem repetir ids: junta as faixas afetadas no primeiro registro
+    let mut merged: Vec<Advisory> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for advisory in advisories {
+        match index.get(&advisory.id) {
+            Some(&i) => merged[i].affected.extend(advisory.affected),
+            None => {
+                index.insert(advisory.id.clone(), merged.len());
+                merged.push(advisory);
+            }
+        }
+    }
+
+    let ranges = store_advisories(&state.pg_pool, &merged).await?;
+
+    let agents: Vec<Uuid> = sqlx::query_scalar("SELECT DISTINCT agent_id FROM software_inventory")
+        .fetch_all(&state.pg_pool).await?;
+    let mut findings = 0;
+    for agent_id in &agents {
+        findings += match_agent(&state.pg_pool, *agent_id).await?;
+    }
+
+    Ok(ImportSummary { files, advisories: merged.len(), ranges, agents_matched: agents.len(), findings })
+}
+
+/// O feed eh um snapshot completo: substitui a base inteira em uma transacao
+async fn store_advisories(pool: &PgPool, advisories: &[Advisory]) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM vulnerability_findings").execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM vulnerability_ranges").execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM vulnerabilities").execute(&mut *tx).await?;
+
+    let mut ranges = 0;
+    for chunk in advisories.chunks(IMPORT_CHUNK) {
+        sqlx::query(
+            r#"INSERT INTO vulnerabilities (id, source, aliases, summary, severity, cvss_score, cvss_vector, published_at, imported_at)
+               SELECT v.id, v.source, string_to_array(v.aliases, E'\n'), v.summary, v.severity, v.cvss_score, v.cvss_vector, v.published_at, NOW()
+               FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::text[], $6::real[], $7::text[], $8::timestamptz[])
+                    AS v(id, source, aliases, summary, severity, cvss_score, cvss_vector, published_at)"#)
+            .bind(chunk.iter().map(|a| a.id.clone()).collect::<Vec<_>>())
+            .bind(chunk.iter().map(|a| a.source.to_string()).collect::<Vec<_>>())
+            .bind(chunk.iter().map(|a| a.aliases.join("\n")).collect::<Vec<_>>())
+            .bind(chunk.iter().map(|a| a.summary.clone()).collect::<Vec<_>>())
+            .bind(chunk.iter().map(|a| a.severity.clone()).collect::<Vec<_>>())
+            .bind(chunk.iter().map(|a| a.cvss_score).collect::<Vec<_>>())
+            .bind(chunk.iter().map(|a| a.cvss_vector.clone()).collect::<Vec<_>>())
+            .bind(chunk.iter().map(|a| a.published).collect::<Vec<_>>())
+            .execute(&mut *tx).await?;
+
+        let affected: Vec<(&str, &feed::AffectedRange)> = chunk.iter()
+            .flat_map(|a| a.affected.iter().map(move |r| (a.id.as_str(), r)))
+            .collect();
+        ranges += affected.len();
+        sqlx::query(
+            r#"INSERT INTO vulnerability_ranges (vuln_id, ecosystem, vendor, product, introduced, introduced_inclusive, fixed, last_affected, versions)
+               SELECT r.vuln_id, r.ecosystem, r.vendor, r.product, r.introduced, r.introduced_inclusive, r.fixed, r.last_affected, string_to_array(r.versions, E'\n')
+               FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::text[], $6::bool[], $7::text[], $8::text[], $9::text[])
+                    AS r(vuln_id, ecosystem, vendor, product, introduced, introduced_inclusive, fixed, last_affected, versions)"#)
+            .bind(affected.iter().map(|(id, _)| id.to_string()).collect::<Vec<_>>())
+            .bind(affected.iter().map(|(_, r)| r.ecosystem.clone()).collect::<Vec<_>>())
+            .bind(affected.iter().map(|(_, r)| r.vendor.clone()).collect::<Vec<_>>())
+            .bind(affected.iter().map(|(_, r)| r.product.clone()).collect::<Vec<_>>())
+            .bind(affected.iter().map(|(_, r)| r.introduced.clone()).collect::<Vec<_>>())
+            .bind(affected.iter().map(|(_, r)| r.introduced_inclusive).collect::<Vec<_>>())
+            .bind(affected.iter().map(|(_, r)| r.fixed.clone()).collect::<Vec<_>>())
+            .bind(affected.iter().map(|(_, r)| r.last_affected.clone()).collect::<Vec<_>>())
+            .bind(affected.iter().map(|(_, r)| r.versions.join("\n")).collect::<Vec<_>>())
+            .execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+    Ok(ranges)
+}
+
+/// Recalcula os achados do agente a partir do inventario atual. Retorna a quantidade de achados.
+pub async fn match_agent(pool: &PgPool, agent_id: Uuid) -> Result<usize, sqlx::Error> {
+    let os_name: String = sqlx::query_scalar("SELECT os_name FROM agents WHERE id = $1")
+        .bind(agent_id).fetch_optional(pool).await?.unwrap_or_default();
+    let installed = sqlx::query_as::<_, InstalledRow>("SELECT name, version, vendor FROM software_inventory WHERE agent_id = $1")
+        .bind(agent_id).fetch_all(pool).await?;
+
+    let candidates: Vec<(&InstalledRow, Vec<String>)> = installed.iter()
+        .map(|sw| (sw, software::candidates(&sw.name, sw.vendor.as_deref())))
+        .collect();
+    let products: Vec<String> = candidates.iter().flat_map(|(_, c)| c.iter().cloned()).collect::<HashSet<_>>().into_iter().collect();
+
+    let ranges = sqlx::query_as::<_, RangeRow>(
+        r#"SELECT vuln_id, ecosystem, vendor, product, introduced, introduced_inclusive, fixed, last_affected, versions
+           FROM vulnerability_ranges WHERE product = ANY($1)"#)
+        .bind(&products)
+        .fetch_all(pool).await?;
+    let mut by_product: HashMap<&str, Vec<&RangeRow>> = HashMap::new();
+    for r in &ranges {
+        by_product.entry(r.product.as_str()).or_default().push(r);
+    }
+
+    let os = os_name.to_lowercase();
+    let native = Ecosystem::from_os(&os_name);
+    let mut findings: HashMap<(&str, &str), (&InstalledRow, Option<&str>)> = HashMap::new();
+    for (sw, products) in &candidates {
+        let Some(version) = sw.version.as_deref().filter(|v| !v.is_empty() && *v != "N/A") else { continue };
+        let vendor = software::normalize(&sw.name, sw.vendor.as_deref()).vendor;
+        for product in products {
+            for r in by_product.get(product.as_str()).into_iter().flatten() {
+                if ecosystem_applies(&r.ecosystem, &os) && vendor_matches(r, vendor.as_deref()) && affects(r, version, native) {
+                    findings.entry((r.vuln_id.as_str(), sw.name.as_str())).or_insert((sw, r.fixed.as_deref()));
+                }
+            }
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM vulnerability_findings WHERE agent_id = $1")
+        .bind(agent_id).execute(&mut *tx).await?;
+    let rows: Vec<_> = findings.iter().collect();
+    sqlx::query(
+        r#"INSERT INTO vulnerability_findings (agent_id, vuln_id, software_name, software_version, fixed_version, detected_at)
+           SELECT $1, f.vuln_id, f.software_name, f.software_version, f.fixed_version, NOW()
+           FROM UNNEST($2::text[], $3::text[], $4::text[], $5::text[]) AS f(vuln_id, software_name, software_version, fixed_version)
+           ON CONFLICT DO NOTHING"#)
+        .bind(agent_id)
+        .bind(rows.iter().map(|((id, _), _)| id.to_string()).collect::<Vec<_>>())
+        .bind(rows.iter().map(|((_, name), _)| name.to_string()).collect::<Vec<_>>())
+        .bind(rows.iter().map(|(_, (sw, _))| sw.version.clone()).collect::<Vec<_>>())
+        .bind(rows.iter().map(|(_, (_, fixed))| fixed.map(str::to_string)).collect::<Vec<_>>())
+        .execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    Ok(findings.len())
+}
+
+/// GET /api/agents/:id/vulnerabilities
+pub async fn agent_vulnerabilities(
+    Path(id): Path<Uuid>,
+    Query(q): Query<VulnQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Json<Vec<AgentFinding>>> {
+    let rows = sqlx::query_as::<_, AgentFinding>(
+        r#"SELECT v.id AS vuln_id, v.aliases, v.summary, v.severity, v.cvss_score, v.cvss_vector,
+                  f.software_name, f.software_version, f.fixed_version, f.detected_at
+           FROM vulnerability_findings f
+           JOIN vulnerabilities v ON v.id = f.vuln_id
+           WHERE f.agent_id = $1
+             AND ($2::real IS NULL OR v.cvss_score >= $2)
+             AND ($3::text IS NULL OR v.severity = $3)
+             AND ($4::text IS NULL OR v.id ILIKE '%' || $4 || '%' OR v.summary ILIKE '%' || $4 || '%' OR f.software_name ILIKE '%' || $4 || '%')
+           ORDER BY v.cvss_score DESC NULLS LAST, v.id
+           LIMIT $5"#)
+        .bind(id).bind(q.min_cvss).bind(&q.severity).bind(&q.q).bind(q.limit())
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(rows))
+}
+
+/// GET /api/vulnerabilities - vulnerabilidades presentes na frota com hosts afetados
+pub async fn fleet_vulnerabilities(
+    Query(q): Query<VulnQuery>,
+    State(state): State<Arc<AppState>>,
+) -> ApiResult<Json<Vec<FleetFinding>>> {
+    let rows = sqlx::query_as::<_, FleetFinding>(
+        r#"SELECT v.id AS vuln_id, v.summary, v.severity, v.cvss_score,
+                  COUNT(DISTINCT f.agent_id) AS affected_agents,
+                  ARRAY_AGG(DISTINCT a.hostname) AS hosts,
+                  ARRAY_AGG(DISTINCT f.software_name) AS software,
+                  COALESCE(ARRAY_AGG(DISTINCT f.fixed_version) FILTER (WHERE f.fixed_version IS NOT NULL), '{}') AS fixed_versions
+           FROM vulnerability_findings f
+           JOIN vulnerabilities v ON v.id = f.vuln_id
+           JOIN agents a ON a.id = f.agent_id
+           WHERE ($1::real IS NULL OR v.cvss_score >= $1)
+             AND ($2::text IS NULL OR v.severity = $2)
+             AND ($3::text IS NULL OR v.id ILIKE '%' || $3 || '%' OR v.summary ILIKE '%' || $3 || '%' OR f.software_name ILIKE '%' || $3 || '%')
+           GROUP BY v.id, v.summary, v.severity, v.cvss_score
+           ORDER BY v.cvss_score DESC NULLS LAST, affected_agents DESC
+           LIMIT $4"#)
+        .bind(q.min_cvss).bind(&q.severity).bind(&q.q).bind(q.limit())
+        .fetch_all(&state.pg_pool).await?;
+    Ok(Json(rows))
+}
+
+impl VulnQuery {
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(200).clamp(1, 1000)
+    }
+}
+
+/// Faixas CPE exigem o mesmo fabricante quando ambos sao conhecidos
+fn vendor_matches(range: &RangeRow, vendor: Option<&str>) -> bool {
+    match (&range.vendor, vendor) {
+        (Some(expected), Some(vendor)) if range.ecosystem == "cpe" => expected == vendor || *expected == range.product,
+        _ => true,
+    }
+}
+
+/// Ecossistemas OSV de pacotes do SO valem somente para a distribuicao correspondente
+fn ecosystem_applies(ecosystem: &str, os: &str) -> bool {
+    match ecosystem {
+        "cpe" => true,
+        "debian" | "ubuntu" | "alpine" | "wolfi" | "mageia" => os.contains(ecosystem),
+        "red hat" => os.contains("red hat") || os.contains("rhel"),
+        "rocky linux" => os.contains("rocky"),
+        "almalinux" => os.contains("alma"),
+        "suse" | "opensuse" => os.contains("suse"),
+        _ => false,
+    }
+}
+
+/// Faixas OSV usam as regras do proprio ecossistema; faixas CPE sao versoes upstream,
+/// entao a versao do pacote perde epoch e revisao da distribuicao antes da comparacao
+fn affects(range: &RangeRow, installed: &str, native: Ecosystem) -> bool {
+    use std::cmp::Ordering::*;
+
+    let (installed, ecosystem) = match range.ecosystem.as_str() {
+        "cpe" if native == Ecosystem::Windows => (installed, Ecosystem::Windows),
+        "cpe" => (software::version::upstream(installed, native), Ecosystem::Generic),
+        osv => (installed, Ecosystem::from_osv(osv)),
+    };
+    let compare = |other: &str| software::compare(installed, other, ecosystem);
+
+    if !range.versions.is_empty() {
+        return range.versions.iter().any(|v| compare(v) == Equal);
+    }
+    if let Some(introduced) = &range.introduced {
+        match compare(introduced) {
+            Less => return false,
+            Equal if !range.introduced_inclusive => return false,
+            _ => {}
+        }
+    }
+    if let Some(fixed) = &range.fixed {
+        if compare(fixed) != Less {
+            return false;
+        }
+    }
+    if let Some(last) = &range.last_affected {
+        if compare(last) == Greater {
+            return false;
+        }
+    }
+    true
+}
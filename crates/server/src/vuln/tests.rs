@@ -0,0 +1,212 @@
+﻿use serde_json::json;
+use shared::software::Ecosystem;
+use super::feed::{self, AffectedRange};
+use super::{affects, cvss, ecosystem_applies, vendor_matches, RangeRow};
+
+fn row(range: AffectedRange) -> RangeRow {
+    RangeRow {
+        vuln_id: "TEST".to_string(),
+        ecosystem: range.ecosystem,
+        vendor: range.vendor,
+        product: range.product,
+        introduced: range.introduced,
+        introduced_inclusive: range.introduced_inclusive,
+        fixed: range.fixed,
+        last_affected: range.last_affected,
+        versions: range.versions,
+    }
+}
+
+/// Faixas de um unico registro NVD com o cpeMatch indicado
+fn nvd(matches: serde_json::Value) -> Vec<RangeRow> {
+    let doc = json!({ "vulnerabilities": [{ "cve": {
+        "id": "CVE-2021-0001",
+        "configurations": [{ "nodes": [{ "cpeMatch": matches }] }],
+    } }] });
+    feed::parse(&doc).remove(0).affected.into_iter().map(row).collect()
+}
+
+/// Faixas de um registro OSV com os eventos indicados
+fn osv(ecosystem: &str, events: serde_json::Value) -> Vec<RangeRow> {
+    let doc = json!({
+        "id": "OSV-2021-0001",
+        "affected": [{ "package": { "name": "OpenSSL", "ecosystem": ecosystem }, "ranges": [{ "type": "ECOSYSTEM", "events": events }] }],
+    });
+    feed::parse(&doc).remove(0).affected.into_iter().map(row).collect()
+}
+
+fn affected(ranges: &[RangeRow], version: &str, native: Ecosystem) -> bool {
+    ranges.iter().any(|r| affects(r, version, native))
+}
+
+#[test]
+fn cvss_base_scores() {
+    let cases = [
+        ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", Some(9.8)),
+        ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H", Some(10.0)),
+        ("CVSS:3.1/AV:N/AC:L/PR:L/UI:N/S:C/C:H/I:H/A:H", Some(9.9)),
+        ("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N", Some(6.1)),
+        ("CVSS:3.1/AV:L/AC:L/PR:L/UI:N/S:U/C:H/I:H/A:H", Some(7.8)),
+        ("CVSS:3.0/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:N/A:N", Some(5.9)),
+        ("CVSS:3.1/AV:P/AC:H/PR:H/UI:R/S:U/C:L/I:N/A:N", Some(1.6)),
+        ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N", Some(0.0)),
+        // v2, metrica ausente ou valor invalido
+        ("AV:N/AC:L/Au:N/C:P/I:P/A:P", None),
+        ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/C:H/I:H/A:H", None),
+        ("CVSS:3.1/AV:X/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", None),
+    ];
+    for (vector, expected) in cases {
+        assert_eq!(cvss::base_score(vector), expected, "{}", vector);
+    }
+}
+
+#[test]
+fn cvss_severity_bands() {
+    assert_eq!(cvss::severity(9.0), "critical");
+    assert_eq!(cvss::severity(8.9), "high");
+    assert_eq!(cvss::severity(7.0), "high");
+    assert_eq!(cvss::severity(4.0), "medium");
+    assert_eq!(cvss::severity(0.1), "low");
+    assert_eq!(cvss::severity(0.0), "none");
+}
+
+#[test]
+fn nvd_start_including_end_excluding() {
+    let ranges = nvd(json!([{
+        "vulnerable": true,
+        "criteria": "cpe:2.3:a:apache:http_server:*:*:*:*:*:*:*:*",
+        "versionStartIncluding": "2.4.0",
+        "versionEndExcluding": "2.4.52",
+    }]));
+    assert_eq!(ranges.len(), 1);
+    assert_eq!(ranges[0].vendor.as_deref(), Some("apache"));
+    assert!(!affected(&ranges, "2.3.9", Ecosystem::Generic));
+    assert!(affected(&ranges, "2.4.0", Ecosystem::Generic));
+    assert!(affected(&ranges, "2.4.51", Ecosystem::Generic));
+    assert!(!affected(&ranges, "2.4.52", Ecosystem::Generic));
+}
+
+#[test]
+fn nvd_start_excluding_end_including() {
+    let ranges = nvd(json!([{
+        "vulnerable": true,
+        "criteria": "cpe:2.3:a:haxx:curl:*:*:*:*:*:*:*:*",
+        "versionStartExcluding": "7.0",
+        "versionEndIncluding": "7.81.0",
+    }]));
+    assert!(!affected(&ranges, "7.0", Ecosystem::Generic));
+    assert!(affected(&ranges, "7.0.1", Ecosystem::Generic));
+    assert!(affected(&ranges, "7.81.0", Ecosystem::Generic));
+    assert!(!affected(&ranges, "7.82.0", Ecosystem::Generic));
+}
+
+#[test]
+fn nvd_exact_version_and_ignored_entries() {
+    let ranges = nvd(json!([
+        { "vulnerable": true, "criteria": "cpe:2.3:a:sudo_project:sudo:1.9.5\\:p1:*:*:*:*:*:*:*" },
+        { "vulnerable": false, "criteria": "cpe:2.3:o:linux:linux_kernel:*:*:*:*:*:*:*:*" },
+        { "vulnerable": true, "criteria": "cpe:2.3:a:vendor:product:-:*:*:*:*:*:*:*" },
+    ]));
+    assert_eq!(ranges.len(), 1);
+    assert_eq!(ranges[0].versions, vec!["1.9.5:p1".to_string()]);
+    assert!(affected(&ranges, "1.9.5:p1", Ecosystem::Generic));
+    assert!(!affected(&ranges, "1.9.5", Ecosystem::Generic));
+}
+
+#[test]
+fn cpe_ranges_compare_dpkg_and_rpm_packages_upstream() {
+    let ranges = nvd(json!([{
+        "vulnerable": true,
+        "criteria": "cpe:2.3:a:openssl:openssl:*:*:*:*:*:*:*:*",
+        "versionStartIncluding": "1.1.1",
+        "versionEndExcluding": "1.1.1n",
+    }]));
+    // Epoch e revisao do empacotador nao entram na comparacao com a versao upstream
+    assert!(affected(&ranges, "1:1.1.1f-1ubuntu2.19", Ecosystem::Dpkg));
+    assert!(!affected(&ranges, "1:1.1.1n-0+deb11u5", Ecosystem::Dpkg));
+    assert!(affected(&ranges, "1.1.1k-9.el8_7", Ecosystem::Rpm));
+    assert!(!affected(&ranges, "1:3.0.7-27.el9", Ecosystem::Rpm));
+}
+
+#[test]
+fn osv_introduced_zero_and_fixed() {
+    let ranges = osv("Debian:11", json!([{ "introduced": "0" }, { "fixed": "1.1.1n-0+deb11u1" }]));
+    assert_eq!(ranges.len(), 1);
+    assert_eq!(ranges[0].ecosystem, "debian");
+    assert_eq!(ranges[0].introduced, None);
+    assert_eq!(ranges[0].product, "openssl");
+    assert!(affected(&ranges, "1.1.1k-1+deb11u1", Ecosystem::Dpkg));
+    assert!(affected(&ranges, "1.1.1n-0", Ecosystem::Dpkg));
+    assert!(!affected(&ranges, "1.1.1n-0+deb11u1", Ecosystem::Dpkg));
+    // Epoch maior vence a versao upstream no dpkg
+    assert!(!affected(&ranges, "1:1.0.0-1", Ecosystem::Dpkg));
+}
+
+#[test]
+fn osv_last_affected_is_inclusive() {
+    let ranges = osv("Ubuntu:22.04", json!([{ "introduced": "3.0.0" }, { "last_affected": "3.0.2-0ubuntu1.9" }]));
+    assert!(!affected(&ranges, "2.9.9-1", Ecosystem::Dpkg));
+    assert!(affected(&ranges, "3.0.0", Ecosystem::Dpkg));
+    assert!(affected(&ranges, "3.0.2-0ubuntu1.9", Ecosystem::Dpkg));
+    assert!(!affected(&ranges, "3.0.2-0ubuntu1.10", Ecosystem::Dpkg));
+}
+
+#[test]
+fn osv_multiple_intervals_and_open_range() {
+    let ranges = osv("Rocky Linux:9", json!([
+        { "introduced": "1.0" }, { "fixed": "1.5" },
+        { "introduced": "2.0" },
+    ]));
+    assert_eq!(ranges.len(), 2);
+    assert!(affected(&ranges, "1.4-1.el9", Ecosystem::Rpm));
+    assert!(!affected(&ranges, "1.5-1.el9", Ecosystem::Rpm));
+    assert!(!affected(&ranges, "1.9-1.el9", Ecosystem::Rpm));
+    assert!(affected(&ranges, "2.0-1.el9", Ecosystem::Rpm));
+    assert!(affected(&ranges, "9.0-1.el9", Ecosystem::Rpm));
+}
+
+#[test]
+fn osv_falls_back_to_explicit_versions() {
+    let doc = json!({
+        "id": "OSV-2021-0002",
+        "affected": [{
+            "package": { "name": "left-pad", "ecosystem": "npm" },
+            "ranges": [{ "type": "GIT", "events": [{ "introduced": "abc" }] }],
+            "versions": ["1.0.0", "1.0.1"],
+        }],
+    });
+    let ranges: Vec<RangeRow> = feed::parse(&doc).remove(0).affected.into_iter().map(row).collect();
+    assert_eq!(ranges.len(), 1);
+    assert!(affected(&ranges, "1.0.1", Ecosystem::Generic));
+    assert!(!affected(&ranges, "1.0.2", Ecosystem::Generic));
+}
+
+#[test]
+fn os_ecosystems_only_apply_to_their_distribution() {
+    assert!(ecosystem_applies("cpe", "windows 11 pro"));
+    assert!(ecosystem_applies("debian", "debian gnu/linux 12"));
+    assert!(!ecosystem_applies("debian", "ubuntu 22.04"));
+    assert!(ecosystem_applies("ubuntu", "ubuntu 22.04.3 lts"));
+    assert!(ecosystem_applies("red hat", "rhel 9.2"));
+    assert!(ecosystem_applies("rocky linux", "rocky linux 9"));
+    assert!(ecosystem_applies("almalinux", "almalinux 8.8"));
+    assert!(ecosystem_applies("suse", "opensuse leap 15.5"));
+    assert!(!ecosystem_applies("npm", "ubuntu 22.04"));
+}
+
+#[test]
+fn cpe_vendor_must_match_when_known() {
+    let ranges = nvd(json!([{ "vulnerable": true, "criteria": "cpe:2.3:a:apache:http_server:*:*:*:*:*:*:*:*" }]));
+    let range = &ranges[0];
+    assert!(vendor_matches(range, Some("apache")));
+    assert!(vendor_matches(range, None));
+    assert!(!vendor_matches(range, Some("acme")));
+
+    // CPE com fabricante igual ao produto nao restringe o fabricante instalado
+    let ranges = nvd(json!([{ "vulnerable": true, "criteria": "cpe:2.3:a:putty:putty:*:*:*:*:*:*:*:*" }]));
+    assert!(vendor_matches(&ranges[0], Some("simon_tatham")));
+
+    // Faixas OSV nao trazem fabricante
+    let ranges = osv("Debian:12", json!([{ "introduced": "0" }]));
+    assert!(vendor_matches(&ranges[0], Some("acme")));
+}
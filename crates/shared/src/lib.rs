@@ -1,6 +1,8 @@
 ﻿pub mod models;
 pub mod protocol;
 pub mod crypto;
+pub mod xccdf;
+pub mod software;
 
 pub fn version() -> &'static str {
     "0.1.0"
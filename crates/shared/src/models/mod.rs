@@ -1,4 +1,6 @@
 ﻿pub mod sca;
+#[cfg(test)]
+mod tests;
 use serde::{Deserialize, Serialize};
 
 // --- MANTENDO MODELS ANTIGOS ---
@@ -13,6 +15,9 @@ pub struct HostInfo {
     pub hardware: HardwareInfo,
     pub peripherals: Vec<String>,
     pub software: Vec<SoftwareInfo>,
+    /// Tags definidas na configuracao do agente (AGENT_TAGS)
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -1,4 +1,6 @@
 ﻿use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::protocol::CommandValidity;
 
 /// Define uma Politica de Seguranca (Lida do YAML)
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -6,6 +8,8 @@ pub struct Policy {
     pub id: String,
     pub name: String,
     pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applies_to: Option<Applicability>,  // Pre-condicoes para a politica inteira
     pub rules: Vec<Rule>,
 }
 
@@ -14,26 +18,196 @@ pub struct Policy {
 pub struct Rule {
     pub id: u32,
     pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub command: String,      // Comando PowerShell a executar
     pub expect: String,       // O que esperamos ver na saida (Regex simples ou String)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub remediation: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,  // Sobrescreve o peso padrao da severidade
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applies_to: Option<Applicability>,
+}
+
+impl Rule {
+    /// Peso efetivo da regra no score ponderado
+    pub fn effective_weight(&self) -> u32 {
+        self.weight.unwrap_or_else(|| self.severity.weight())
+    }
+}
+
+/// Criticidade de uma regra
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Peso padrao usado no score quando a regra nao define `weight`
+    pub fn weight(&self) -> u32 {
+        match self {
+            Severity::Low => 1,
+            Severity::Medium => 3,
+            Severity::High => 7,
+            Severity::Critical => 10,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// Inverso de `as_str` (colunas de texto do banco)
+    pub fn parse(severity: &str) -> Option<Self> {
+        match severity {
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// Pre-condicoes avaliadas antes da regra: todas as listas preenchidas precisam ser satisfeitas
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Applicability {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os_family: Vec<String>,         // "windows", "linux", "macos" (basta uma)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_os_version: Option<String>, // Inclusivo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_os_version: Option<String>, // Inclusivo
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,          // Todos precisam estar instalados
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,             // Todos precisam existir
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,             // O agente precisa ter pelo menos uma
+}
+
+/// Referencia externa da regra (ex: framework "CIS" id "9.1.1", framework "NIST-800-53" id "SC-7")
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Reference {
+    pub framework: String,
+    pub id: String,
 }
 
 /// Relatorio de Execucao da Politica
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ComplianceReport {
     pub policy_id: String,
-    pub score: u32,           // Porcentagem de aprovacao
-    pub total_checks: u32,
+    pub score: Option<u32>,       // Porcentagem ponderada pelo peso das regras (None se nenhuma regra se aplica)
+    #[serde(default)]
+    pub raw_score: Option<u32>,   // Porcentagem simples de regras aprovadas
+    pub total_checks: u32,    // Apenas regras aplicaveis
     pub passed_checks: u32,
+    #[serde(default)]
+    pub not_applicable: u32,
     pub results: Vec<CheckResult>,
 }
 
+impl ComplianceReport {
+    /// Monta o relatorio calculando o score ponderado e o percentual bruto
+    /// Regras NOT_APPLICABLE ficam fora do score; sem nenhuma regra aplicavel nao ha score.
+    pub fn compute(policy_id: String, results: Vec<CheckResult>) -> Self {
+        let applicable = || results.iter().filter(|r| r.status != "NOT_APPLICABLE");
+        let total = applicable().count() as u32;
+        let passed = applicable().filter(|r| r.status == "PASS").count() as u32;
+        let total_weight: u32 = applicable().map(|r| r.weight).sum();
+        let passed_weight: u32 = applicable().filter(|r| r.status == "PASS").map(|r| r.weight).sum();
+        let not_applicable = results.len() as u32 - total;
+
+        Self {
+            policy_id,
+            score: percent(passed_weight, total_weight),
+            raw_score: percent(passed, total),
+            total_checks: total,
+            passed_checks: passed,
+            not_applicable,
+            results,
+        }
+    }
+}
+
+fn percent(part: u32, total: u32) -> Option<u32> {
+    (total > 0).then(|| (part as f32 / total as f32 * 100.0) as u32)
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CheckResult {
     pub rule_id: u32,
     pub title: String,
-    pub status: String,       // "PASS" ou "FAIL"
+    pub status: String,       // "PASS", "FAIL", "ERROR" ou "NOT_APPLICABLE"
     pub output: String,       // O que o comando retornou
+    #[serde(default)]
+    pub duration_ms: u64,     // Tempo de execucao da regra
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default = "default_weight")]
+    pub weight: u32,          // Peso efetivo usado no score
+}
+
+fn default_weight() -> u32 {
+    Severity::default().weight()
+}
+
+/// Alvo de uma remediacao: uma regra especifica ou a politica inteira
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", content = "rule_id")]
+pub enum RemediationScope {
+    Rule(u32),
+    Policy,
+}
+
+/// Pedido de remediacao enviado pelo servidor (vai assinado no `args` do Command)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemediationRequest {
+    pub agent_id: Uuid,        // Evita replay do comando assinado em outro agente
+    pub policy_id: String,
+    pub scope: RemediationScope,
+    pub dry_run: bool,
+    #[serde(flatten)]
+    pub validity: CommandValidity,  // Nonce e expiracao assinados junto com o pedido
+}
+
+/// Politica atribuida ao agente por um grupo (vai assinada no `args` do Command RunPolicy).
+/// O agente avalia a politica recebida alem da sua politica local.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyAssignment {
+    pub agent_id: Uuid,        // Evita replay do comando assinado em outro agente
+    pub policy: Policy,
+    #[serde(flatten)]
+    pub validity: CommandValidity,
+}
+
+/// Resultado da remediacao de uma regra (status antes/depois)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemediationResult {
+    pub rule_id: u32,
+    pub title: String,
+    pub action: String,               // "APPLIED", "DRY_RUN", "SKIPPED", "NO_REMEDIATION" ou "ERROR"
+    pub before_status: String,
+    pub after_status: Option<String>, // Preenchido somente quando a remediacao foi aplicada
+    pub remediation: Option<String>,  // Comando de remediacao da regra
+    pub output: String,               // Saida do comando de remediacao
 }
@@ -0,0 +1,47 @@
+﻿use super::sca::{CheckResult, ComplianceReport, Severity};
+
+fn result(rule_id: u32, status: &str, severity: Severity) -> CheckResult {
+    CheckResult {
+        rule_id,
+        title: format!("Regra {}", rule_id),
+        status: status.to_string(),
+        output: String::new(),
+        duration_ms: 0,
+        severity,
+        weight: severity.weight(),
+    }
+}
+
+#[test]
+fn compute_weights_score_and_skips_not_applicable() {
+    let report = ComplianceReport::compute("p".into(), vec![
+        result(1, "PASS", Severity::Critical),
+        result(2, "FAIL", Severity::Low),
+        result(3, "ERROR", Severity::Low),
+        result(4, "NOT_APPLICABLE", Severity::Critical),
+    ]);
+    // 10 de 12 pontos aplicaveis; 1 de 3 regras aplicaveis
+    assert_eq!(report.score, Some(83));
+    assert_eq!(report.raw_score, Some(33));
+    assert_eq!((report.total_checks, report.passed_checks, report.not_applicable), (3, 1, 1));
+}
+
+#[test]
+fn compute_has_no_score_when_no_rule_applies() {
+    let report = ComplianceReport::compute("p".into(), vec![
+        result(1, "NOT_APPLICABLE", Severity::High),
+        result(2, "NOT_APPLICABLE", Severity::Low),
+    ]);
+    assert_eq!(report.score, None);
+    assert_eq!(report.raw_score, None);
+    assert_eq!((report.total_checks, report.passed_checks, report.not_applicable), (0, 0, 2));
+
+    let empty = ComplianceReport::compute("p".into(), Vec::new());
+    assert_eq!((empty.score, empty.raw_score), (None, None));
+}
+
+#[test]
+fn compute_all_failing_scores_zero() {
+    let report = ComplianceReport::compute("p".into(), vec![result(1, "FAIL", Severity::High), result(2, "NOT_APPLICABLE", Severity::High)]);
+    assert_eq!((report.score, report.raw_score), (Some(0), Some(0)));
+}
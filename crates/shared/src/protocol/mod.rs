@@ -2,7 +2,7 @@
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use crate::models::{HostInfo, SoftwareInfo};
-use crate::models::sca::ComplianceReport;
+use crate::models::sca::{ComplianceReport, RemediationResult};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
@@ -27,6 +27,10 @@ pub enum Message {
     ScaReport {
         agent_id: Uuid,
         report: ComplianceReport,
+        /// Politica atribuida por grupo (RunPolicy): entra no historico, mas o score
+        /// atual do agente continua sendo o da politica local
+        #[serde(default)]
+        assigned: bool,
     },
     Command {
         id: Uuid,
@@ -40,11 +44,45 @@ pub enum Message {
         stdout: String,
         stderr: String,
     },
+    RemediationReport {
+        agent_id: Uuid,
+        cmd_id: Uuid,
+        policy_id: String,
+        dry_run: bool,
+        results: Vec<RemediationResult>,
+    },
+    /// Servidor desligando (deploy): fechar e reconectar depois de `after_secs`
+    Reconnect {
+        after_secs: u64,
+        reason: String,
+    },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum CommandType {
     RunScript,
     UpdateConfig,
     RestartAgent,
+    Remediate,    // args: RemediationRequest em JSON
+    RunPolicy,    // args: PolicyAssignment em JSON
+}
+
+/// Validade padrao de um comando assinado
+pub const COMMAND_TTL_SECS: i64 = 300;
+
+/// Validade de um comando assinado, embutida no JSON do `args` (mesmo nivel dos demais campos).
+/// O agente recusa comandos expirados e nonces ja vistos.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct CommandValidity {
+    pub nonce: Uuid,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl CommandValidity {
+    /// Nonce novo valido por COMMAND_TTL_SECS a partir de agora
+    pub fn issue() -> Self {
+        let issued_at = Utc::now();
+        Self { nonce: Uuid::new_v4(), issued_at, expires_at: issued_at + chrono::Duration::seconds(COMMAND_TTL_SECS) }
+    }
 }
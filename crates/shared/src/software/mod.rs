@@ -0,0 +1,182 @@
+﻿//! Normalizacao do inventario de software: nomes do registro do Windows e dos
+//! gerenciadores de pacote Linux viram um par canonico fabricante/produto
+//! (convencao CPE, ex.: google/chrome) e versoes sao comparadas conforme o ecossistema.
+
+use serde::{Serialize, Deserialize};
+
+pub mod version;
+#[cfg(test)]
+mod tests;
+
+pub use version::{compare, VersionReq};
+
+/// Regras de comparacao de versao
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    /// Debian/Ubuntu: epoch:upstream-revisao, '~' antes de tudo
+    Dpkg,
+    /// RHEL/SUSE: epoch:versao-release (rpmvercmp)
+    Rpm,
+    /// Pacotes de linguagem (npm, crates.io, Go...)
+    Semver,
+    /// Builds do Windows: major.minor.build.revision numericos
+    Windows,
+    Generic,
+}
+
+impl Ecosystem {
+    /// Ecossistema dos pacotes nativos a partir do os_name informado pelo agente
+    pub fn from_os(os_name: &str) -> Self {
+        let os = os_name.to_lowercase();
+        const DPKG: &[&str] = &["debian", "ubuntu", "mint", "kali", "pop!_os", "raspbian"];
+        const RPM: &[&str] = &["red hat", "rhel", "centos", "fedora", "rocky", "alma", "suse", "oracle linux", "amazon linux", "mageia"];
+        if os.contains("windows") {
+            Ecosystem::Windows
+        } else if DPKG.iter().any(|d| os.contains(d)) {
+            Ecosystem::Dpkg
+        } else if RPM.iter().any(|d| os.contains(d)) {
+            Ecosystem::Rpm
+        } else {
+            Ecosystem::Generic
+        }
+    }
+
+    /// Ecossistema de um advisory OSV ("Debian:12", "npm", "Rocky Linux"...)
+    pub fn from_osv(ecosystem: &str) -> Self {
+        let name = ecosystem.split(':').next().unwrap_or_default().trim().to_lowercase();
+        match name.as_str() {
+            "debian" | "ubuntu" => Ecosystem::Dpkg,
+            "red hat" | "rocky linux" | "almalinux" | "suse" | "opensuse" | "mageia" => Ecosystem::Rpm,
+            "npm" | "crates.io" | "go" | "packagist" | "hex" | "pub" | "nuget" | "rubygems" => Ecosystem::Semver,
+            _ => Ecosystem::Generic,
+        }
+    }
+}
+
+/// Identidade canonica de um software instalado
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Normalized {
+    pub vendor: Option<String>,
+    pub product: String,
+}
+
+/// Apelidos conhecidos: nome de pacote/produto (ja normalizado) -> fabricante/produto canonicos
+const ALIASES: &[(&[&str], &str, &str)] = &[
+    (&["chrome", "google_chrome"], "google", "chrome"),
+    // Chromium das distribuicoes tem versao e CVEs proprios (cpe:/a:google:chromium)
+    (&["chromium", "chromium_browser"], "google", "chromium"),
+    (&["firefox", "firefox_esr", "mozilla_firefox"], "mozilla", "firefox"),
+    (&["thunderbird", "mozilla_thunderbird"], "mozilla", "thunderbird"),
+    (&["edge", "microsoft_edge", "msedge"], "microsoft", "edge"),
+    (&["openssh", "openssh_server", "openssh_client", "openssh_clients"], "openbsd", "openssh"),
+    (&["openssl", "libssl", "libssl3", "libssl1.1", "openssl_libs"], "openssl", "openssl"),
+    (&["sudo"], "sudo_project", "sudo"),
+    (&["bash"], "gnu", "bash"),
+    (&["glibc", "libc6", "libc_bin"], "gnu", "glibc"),
+    (&["curl", "libcurl4", "libcurl"], "haxx", "curl"),
+    (&["apache2", "httpd", "apache_http_server"], "apache", "http_server"),
+    (&["nginx", "nginx_core"], "f5", "nginx"),
+    (&["git", "git_core"], "git_scm", "git"),
+    (&["python3", "python"], "python", "python"),
+    (&["openjdk", "java", "jre", "jdk"], "oracle", "openjdk"),
+    (&["acrobat_reader", "adobe_acrobat_reader", "acrobat_reader_dc", "reader_dc"], "adobe", "acrobat_reader"),
+    (&["7_zip", "7zip", "p7zip", "p7zip_full"], "7_zip", "7_zip"),
+    (&["notepad++", "notepadplusplus"], "notepad-plus-plus", "notepad++"),
+    (&["vlc", "vlc_media_player"], "videolan", "vlc_media_player"),
+    (&["libreoffice"], "libreoffice", "libreoffice"),
+    (&["zoom", "zoom_workplace"], "zoom", "zoom"),
+    (&["teamviewer"], "teamviewer", "teamviewer"),
+    (&["putty"], "putty", "putty"),
+    (&["winrar"], "rarlab", "winrar"),
+];
+
+/// Sufixos que nao identificam o produto (canal, arquitetura, idioma, tipo de pacote)
+const NOISE: &[&str] = &[
+    "x64", "x86", "amd64", "arm64", "aarch64", "i386", "i686", "noarch", "bit", "64bit", "32bit", "stable", "bin", "common",
+];
+
+/// Sufixos societarios removidos do fabricante
+const LEGAL: &[&str] = &[
+    "inc", "llc", "ltd", "limited", "corp", "corporation", "co", "gmbh", "ag", "sa", "s.a", "bv", "foundation", "project", "the",
+];
+
+/// "Google Chrome (64-bit)" / "google-chrome-stable:amd64" -> google/chrome
+pub fn normalize(name: &str, vendor: Option<&str>) -> Normalized {
+    let words = product_words(name);
+    let vendor = vendor.and_then(canonical_vendor);
+
+    // Nome comecando pelo fabricante: "Mozilla Firefox" -> "firefox"
+    let own = match words.split_first() {
+        Some((first, rest)) if !rest.is_empty() && vendor.as_deref() == Some(first.as_str()) => rest,
+        _ => &words[..],
+    };
+    let product = if own.is_empty() { name.trim().to_lowercase() } else { own.join("_") };
+
+    let full = words.join("_");
+    match ALIASES.iter().find(|(names, _, _)| names.contains(&product.as_str()) || names.contains(&full.as_str())) {
+        Some((_, v, p)) => Normalized { vendor: Some(v.to_string()), product: p.to_string() },
+        None => Normalized { vendor, product },
+    }
+}
+
+/// Chaves de produto candidatas para casar com CPE/OSV: canonica, nome de pacote e variantes
+pub fn candidates(name: &str, vendor: Option<&str>) -> Vec<String> {
+    let normalized = normalize(name, vendor);
+    let words = product_words(name);
+    let mut out = vec![name.trim().to_lowercase(), normalized.product.replace('_', "-"), normalized.product];
+    if !words.is_empty() {
+        out.push(words.join("_"));
+        out.push(words.join("-"));
+        out.push(words.join(" "));
+    }
+    // Pacote de distribuicao sem o sufixo de arquitetura: "openssl:amd64" -> "openssl"
+    if let Some((package, _)) = name.trim().to_lowercase().split_once(':') {
+        out.push(package.to_string());
+    }
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// "Google LLC" -> "google"; "Microsoft Corporation" -> "microsoft".
+/// Mantenedores de distribuicao ("Ubuntu Developers <...@...>") nao sao fabricantes.
+pub fn canonical_vendor(vendor: &str) -> Option<String> {
+    if vendor.contains('@') {
+        return None;
+    }
+    let vendor = vendor.to_lowercase();
+    let words: Vec<&str> = vendor
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '<' || c == '>')
+        .map(|w| w.trim_matches('.'))
+        .filter(|w| !w.is_empty() && !LEGAL.contains(w))
+        .collect();
+    words.first().map(|w| w.to_string())
+}
+
+/// Palavras significativas do nome: sem parenteses, arquitetura, canal e tokens de versao
+fn product_words(name: &str) -> Vec<String> {
+    let lower = name.trim().to_lowercase();
+    // Multiarch do dpkg: "libssl3:amd64"
+    let lower = lower.split_once(':').map_or(lower.as_str(), |(p, _)| p);
+
+    // Fechamento sem abertura ("foo) bar") nao pode esconder o resto do nome
+    let mut depth = 0u32;
+    let without_parens: String = lower.chars().filter(|c| {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ => return depth == 0,
+        }
+        false
+    }).collect();
+
+    without_parens
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_' || c == ',')
+        .filter(|w| !w.is_empty() && !NOISE.contains(w))
+        .enumerate()
+        // Tokens de versao ("120.0.1", "v2", "2019"), exceto no inicio do nome ("7-Zip")
+        .filter(|(i, w)| *i == 0 || !w.trim_start_matches('v').chars().all(|c| c.is_ascii_digit() || c == '.'))
+        .map(|(_, w)| w.to_string())
+        .collect()
+}
@@ -0,0 +1,165 @@
+﻿use std::cmp::Ordering::{self, Equal, Greater, Less};
+use super::{candidates, canonical_vendor, compare, normalize, Ecosystem, Normalized};
+
+/// Cada caso vale nos dois sentidos: compare(b, a) eh o inverso de compare(a, b)
+fn check(ecosystem: Ecosystem, cases: &[(&str, &str, Ordering)]) {
+    for &(a, b, expected) in cases {
+        assert_eq!(compare(a, b, ecosystem), expected, "{:?}: {} vs {}", ecosystem, a, b);
+        assert_eq!(compare(b, a, ecosystem), expected.reverse(), "{:?}: {} vs {}", ecosystem, b, a);
+    }
+}
+
+// Casos de lib/dpkg/t/t-version.c e deb-version(7)
+#[test]
+fn dpkg_verrevcmp() {
+    check(Ecosystem::Dpkg, &[
+        ("1.0", "1.0", Equal),
+        ("0:1.0", "1.0", Equal),
+        ("1.0-0", "1.0", Equal),
+        ("1.001", "1.1", Equal),
+        ("1.0", "1.1", Less),
+        ("1.2.3", "1.10", Less),
+        ("1:1.0", "2.0", Greater),
+        ("2:0.1", "1:9.9", Greater),
+        ("1.0-1", "1.0-2", Less),
+        ("2.30-1ubuntu1", "2.30-1", Greater),
+        ("1.0-1ubuntu1", "1.0-1ubuntu10", Less),
+        ("1.0~rc1", "1.0", Less),
+        ("1.0~rc1", "1.0~rc2", Less),
+        ("1.0~~", "1.0~", Less),
+        ("1.0~~a", "1.0~", Less),
+        ("1.0~", "1.0", Less),
+        ("1.0a", "1.0", Greater),
+        ("1.0a", "1.0+", Less),
+        ("1.0", "1.0+b1", Less),
+        ("1.0.0", "1.0", Greater),
+        ("a", "b", Less),
+        ("3.0.2-0ubuntu1.10", "3.0.2-0ubuntu1.9", Greater),
+    ]);
+}
+
+// Casos de tests/rpmvercmp.at
+#[test]
+fn rpm_rpmvercmp() {
+    check(Ecosystem::Rpm, &[
+        ("1.0", "1.0", Equal),
+        ("1.0", "2.0", Less),
+        ("2.0.1", "2.0", Greater),
+        ("2.0.1a", "2.0.1", Greater),
+        ("5.5p1", "5.5p2", Less),
+        ("5.5p10", "5.5p2", Greater),
+        ("5.5p1", "5.5", Greater),
+        ("10xyz", "10.1xyz", Less),
+        ("xyz10", "xyz10.1", Less),
+        ("xyz.4", "8", Less),
+        ("xyz.4", "xyz.4", Equal),
+        ("1.002", "1.2", Equal),
+        ("a", "b", Less),
+        ("1.0~rc1", "1.0", Less),
+        ("1.0~rc1", "1.0~rc2", Less),
+        ("1.0~rc1~git123", "1.0~rc1", Less),
+        ("1.0^", "1.0", Greater),
+        ("1.0^git1", "1.0", Greater),
+        ("1.0^git1", "1.01", Less),
+        ("1.0^git1", "1.0.1", Less),
+        ("1.0^git1~pre", "1.0^git1", Less),
+        ("1.0^git2", "1.0^git1", Greater),
+    ]);
+}
+
+#[test]
+fn rpm_epoch_and_release() {
+    check(Ecosystem::Rpm, &[
+        ("1.0-1", "1.0-2", Less),
+        ("1:1.0-1", "2.0-1", Greater),
+        ("3.0.7-16.el9", "3.0.7-24.el9", Less),
+        ("3.0.7-24.el9_3", "3.0.7-24.el9", Greater),
+        // Faixa sem release casa qualquer release da mesma versao
+        ("1.0-5", "1.0", Equal),
+    ]);
+}
+
+// Exemplo de precedencia da secao 11 de semver.org
+#[test]
+fn semver_precedence() {
+    let chain = ["1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0", "1.0.1", "1.1.0", "2.0.0"];
+    for pair in chain.windows(2) {
+        check(Ecosystem::Semver, &[(pair[0], pair[1], Less)]);
+    }
+    check(Ecosystem::Semver, &[
+        ("1.0.0+build.1", "1.0.0+build.2", Equal),
+        ("v1.2.3", "1.2.3", Equal),
+        ("1.2", "1.2.0", Equal),
+        ("1.9.0", "1.10.0", Less),
+        // Fora do formato cai na comparacao generica
+        ("1.2.3.4", "1.2.3.5", Less),
+    ]);
+}
+
+#[test]
+fn windows_builds() {
+    check(Ecosystem::Windows, &[
+        ("10.0.19045.1234", "10.0.19045.2000", Less),
+        ("10.0", "10.0.0.0", Equal),
+        ("6.3.9600", "10.0", Less),
+        ("120.0.6099.71", "120.0.6099.109", Less),
+        // Nao numerica cai na comparacao generica
+        ("23.1 beta", "23.1", Greater),
+    ]);
+}
+
+#[test]
+fn generic_segments() {
+    check(Ecosystem::Generic, &[
+        ("1.10", "1.9", Greater),
+        ("1.0a", "1.0", Greater),
+        ("1.0", "1.0.1", Less),
+        ("2.0.a", "2.0.1", Less),
+        ("007", "7", Equal),
+    ]);
+}
+
+#[test]
+fn upstream_strips_epoch_and_revision() {
+    use super::version::upstream;
+    assert_eq!(upstream("1:2.3.4-1ubuntu2", Ecosystem::Dpkg), "2.3.4");
+    assert_eq!(upstream("2.3.4-5.el9", Ecosystem::Rpm), "2.3.4");
+    assert_eq!(upstream("2.3.4-rc.1", Ecosystem::Semver), "2.3.4-rc.1");
+}
+
+fn canonical(vendor: &str, product: &str) -> Normalized {
+    Normalized { vendor: Some(vendor.to_string()), product: product.to_string() }
+}
+
+#[test]
+fn normalize_aliases() {
+    assert_eq!(normalize("Google Chrome (64-bit)", Some("Google LLC")), canonical("google", "chrome"));
+    assert_eq!(normalize("google-chrome-stable:amd64", None), canonical("google", "chrome"));
+    assert_eq!(normalize("chromium", None), canonical("google", "chromium"));
+    assert_eq!(normalize("chromium-browser", None), canonical("google", "chromium"));
+    assert_eq!(normalize("Mozilla Firefox (x64 pt-BR)", Some("Mozilla")), canonical("mozilla", "firefox"));
+    assert_eq!(normalize("7-Zip 23.01 (x64)", Some("Igor Pavlov")), canonical("7_zip", "7_zip"));
+}
+
+#[test]
+fn normalize_unknown_product_keeps_vendor() {
+    assert_eq!(normalize("Acme Widget 2.1", Some("Acme Inc.")), canonical("acme", "widget"));
+    assert_eq!(normalize("foo", Some("Ubuntu Developers <ubuntu-devel@lists.ubuntu.com>")), Normalized { vendor: None, product: "foo".into() });
+}
+
+#[test]
+fn unbalanced_parens_do_not_hide_the_name() {
+    assert_eq!(normalize("foo) bar", None).product, "foo_bar");
+    assert_eq!(normalize("Tool (x64", None).product, "tool");
+    assert_eq!(normalize("a] (b) c", None).product, "a_c");
+}
+
+#[test]
+fn vendor_and_candidates() {
+    assert_eq!(canonical_vendor("Microsoft Corporation").as_deref(), Some("microsoft"));
+    assert_eq!(canonical_vendor("The Git Development Community").as_deref(), Some("git"));
+    let keys = candidates("libssl3:amd64", None);
+    for key in ["libssl3", "openssl", "libssl3:amd64"] {
+        assert!(keys.contains(&key.to_string()), "{:?} sem {}", keys, key);
+    }
+}
@@ -0,0 +1,328 @@
+﻿use std::cmp::Ordering;
+use super::Ecosystem;
+
+/// Compara duas versoes segundo as regras do ecossistema
+pub fn compare(a: &str, b: &str, ecosystem: Ecosystem) -> Ordering {
+    let (a, b) = (a.trim(), b.trim());
+    match ecosystem {
+        Ecosystem::Dpkg => dpkg(a, b),
+        Ecosystem::Rpm => rpm(a, b),
+        Ecosystem::Semver => semver(a, b).unwrap_or_else(|| generic(a, b)),
+        Ecosystem::Windows => windows(a, b).unwrap_or_else(|| generic(a, b)),
+        Ecosystem::Generic => generic(a, b),
+    }
+}
+
+/// Versao "upstream" sem epoch e sem revisao/release do empacotador:
+/// "1:2.3.4-1ubuntu2" -> "2.3.4" (dpkg), "2.3.4-5.el9" -> "2.3.4" (rpm)
+pub fn upstream(version: &str, ecosystem: Ecosystem) -> &str {
+    let version = version.trim();
+    match ecosystem {
+        Ecosystem::Dpkg | Ecosystem::Rpm => {
+            let (_, rest) = split_epoch(version);
+            rest.rsplit_once('-').map_or(rest, |(v, _)| v)
+        }
+        _ => version,
+    }
+}
+
+/// Restricao de versao: lista de comparacoes separadas por virgula, todas obrigatorias.
+/// ">=1.1, <3.0", "<3", "=2.4.1" (sem operador vale igualdade)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq(Vec<(Op, String)>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op { Lt, Le, Gt, Ge, Eq }
+
+impl VersionReq {
+    pub fn parse(req: &str) -> Option<Self> {
+        let mut out = Vec::new();
+        for part in req.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (op, version) = [("<=", Op::Le), (">=", Op::Ge), ("==", Op::Eq), ("<", Op::Lt), (">", Op::Gt), ("=", Op::Eq)]
+                .iter()
+                .find_map(|(prefix, op)| part.strip_prefix(prefix).map(|v| (*op, v)))
+                .unwrap_or((Op::Eq, part));
+            let version = version.trim();
+            if version.is_empty() {
+                return None;
+            }
+            out.push((op, version.to_string()));
+        }
+        (!out.is_empty()).then_some(Self(out))
+    }
+
+    pub fn matches(&self, version: &str, ecosystem: Ecosystem) -> bool {
+        self.0.iter().all(|(op, other)| {
+            let ord = compare(version, other, ecosystem);
+            match op {
+                Op::Lt => ord == Ordering::Less,
+                Op::Le => ord != Ordering::Greater,
+                Op::Gt => ord == Ordering::Greater,
+                Op::Ge => ord != Ordering::Less,
+                Op::Eq => ord == Ordering::Equal,
+            }
+        })
+    }
+}
+
+fn split_epoch(version: &str) -> (u64, &str) {
+    match version.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => (epoch.parse().unwrap_or(0), rest),
+        _ => (0, version),
+    }
+}
+
+// --- dpkg (deb-version(7)) ---
+
+fn dpkg(a: &str, b: &str) -> Ordering {
+    let (ea, a) = split_epoch(a);
+    let (eb, b) = split_epoch(b);
+    let (ua, ra) = a.rsplit_once('-').unwrap_or((a, ""));
+    let (ub, rb) = b.rsplit_once('-').unwrap_or((b, ""));
+    ea.cmp(&eb)
+        .then_with(|| verrevcmp(ua, ub))
+        .then_with(|| verrevcmp(ra, rb))
+}
+
+/// Peso de um caractere na parte nao numerica: '~' antes de tudo (inclusive do fim),
+/// letras antes dos demais simbolos
+fn dpkg_order(c: Option<u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(b'~') => -1,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => c as i32,
+        Some(c) => c as i32 + 256,
+    }
+}
+
+fn verrevcmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let (ac, bc) = (dpkg_order(a.get(i).copied()), dpkg_order(b.get(j).copied()));
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+        while a.get(i) == Some(&b'0') { i += 1; }
+        while b.get(j) == Some(&b'0') { j += 1; }
+        let mut first_diff = Ordering::Equal;
+        while i < a.len() && j < b.len() && a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if a.get(i).is_some_and(u8::is_ascii_digit) {
+            return Ordering::Greater;
+        }
+        if b.get(j).is_some_and(u8::is_ascii_digit) {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+// --- rpm (rpmvercmp) ---
+
+fn rpm(a: &str, b: &str) -> Ordering {
+    let (ea, a) = split_epoch(a);
+    let (eb, b) = split_epoch(b);
+    let (va, ra) = a.rsplit_once('-').map_or((a, None), |(v, r)| (v, Some(r)));
+    let (vb, rb) = b.rsplit_once('-').map_or((b, None), |(v, r)| (v, Some(r)));
+    ea.cmp(&eb)
+        .then_with(|| rpmvercmp(va, vb))
+        // Sem release de um dos lados (ex.: faixa "1.2.3") compara so a versao
+        .then_with(|| match (ra, rb) {
+            (Some(ra), Some(rb)) => rpmvercmp(ra, rb),
+            _ => Ordering::Equal,
+        })
+}
+
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    let separator = |c: u8| !c.is_ascii_alphanumeric() && c != b'~' && c != b'^';
+    loop {
+        while i < a.len() && separator(a[i]) { i += 1; }
+        while j < b.len() && separator(b[j]) { j += 1; }
+
+        // '~' ordena antes de qualquer coisa (pre-release)
+        if a.get(i) == Some(&b'~') || b.get(j) == Some(&b'~') {
+            if a.get(i) != Some(&b'~') { return Ordering::Greater; }
+            if b.get(j) != Some(&b'~') { return Ordering::Less; }
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // '^' ordena depois do fim da string, mas antes de qualquer outro segmento
+        if a.get(i) == Some(&b'^') || b.get(j) == Some(&b'^') {
+            if i >= a.len() { return Ordering::Less; }
+            if j >= b.len() { return Ordering::Greater; }
+            if a[i] != b'^' { return Ordering::Greater; }
+            if b[j] != b'^' { return Ordering::Less; }
+            i += 1;
+            j += 1;
+            continue;
+        }
+        if i >= a.len() || j >= b.len() {
+            break;
+        }
+
+        let numeric = a[i].is_ascii_digit();
+        let class = |c: &u8| if numeric { c.is_ascii_digit() } else { c.is_ascii_alphabetic() };
+        let (si, sj) = (i, j);
+        while i < a.len() && class(&a[i]) { i += 1; }
+        while j < b.len() && class(&b[j]) { j += 1; }
+        let (sa, sb) = (&a[si..i], &b[sj..j]);
+
+        // Tipos diferentes: numero eh maior que letra
+        if sb.is_empty() {
+            return if numeric { Ordering::Greater } else { Ordering::Less };
+        }
+        let ord = if numeric {
+            let sa = trim_zeros(sa);
+            let sb = trim_zeros(sb);
+            sa.len().cmp(&sb.len()).then_with(|| sa.cmp(sb))
+        } else {
+            sa.cmp(sb)
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    match (i >= a.len(), j >= b.len()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        _ => Ordering::Greater,
+    }
+}
+
+fn trim_zeros(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(|&c| c != b'0').unwrap_or(s.len());
+    &s[start..]
+}
+
+// --- semver 2.0 ---
+
+fn semver(a: &str, b: &str) -> Option<Ordering> {
+    let (ca, pa) = parse_semver(a)?;
+    let (cb, pb) = parse_semver(b)?;
+    let ord = ca.cmp(&cb).then_with(|| match (pa, pb) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(pa), Some(pb)) => prerelease(pa, pb),
+    });
+    Some(ord)
+}
+
+/// "v1.2.3-rc.1+build" -> ([1, 2, 3], Some("rc.1")); partes ausentes valem 0
+fn parse_semver(version: &str) -> Option<([u64; 3], Option<&str>)> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let version = version.split_once('+').map_or(version, |(v, _)| v);
+    let (core, pre) = version.split_once('-').map_or((version, None), |(c, p)| (c, Some(p)));
+    let mut out = [0u64; 3];
+    let mut parts = core.split('.');
+    for slot in out.iter_mut() {
+        match parts.next() {
+            Some(p) => *slot = p.parse().ok()?,
+            None => break,
+        }
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((out, pre))
+}
+
+fn prerelease(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => x.cmp(y),
+                };
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+        }
+    }
+}
+
+// --- Windows (major.minor.build.revision) ---
+
+fn windows(a: &str, b: &str) -> Option<Ordering> {
+    let parse = |v: &str| -> Option<Vec<u64>> {
+        let mut parts: Vec<u64> = v.split('.').map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+        while parts.last() == Some(&0) { parts.pop(); }
+        Some(parts)
+    };
+    // Zeros a direita nao contam: "10.0" == "10.0.0.0"
+    Some(parse(a)?.cmp(&parse(b)?))
+}
+
+// --- Generico ---
+
+/// Comparacao por segmentos: numeros como inteiros, letras em ordem lexica,
+/// numero > letra e, com o prefixo igual, a versao com mais segmentos eh maior
+fn generic(a: &str, b: &str) -> Ordering {
+    let (a, b) = (segments(a), segments(b));
+    for (x, y) in a.iter().zip(b.iter()) {
+        let ord = match (x, y) {
+            (Segment::Num(x), Segment::Num(y)) => x.len().cmp(&y.len()).then_with(|| x.cmp(y)),
+            (Segment::Alpha(x), Segment::Alpha(y)) => x.cmp(y),
+            (Segment::Num(_), Segment::Alpha(_)) => Ordering::Greater,
+            (Segment::Alpha(_), Segment::Num(_)) => Ordering::Less,
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+enum Segment<'a> {
+    Num(&'a str),   // Sem zeros a esquerda
+    Alpha(&'a str),
+}
+
+fn segments(version: &str) -> Vec<Segment<'_>> {
+    let mut out = Vec::new();
+    let bytes = version.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        if bytes[i].is_ascii_digit() {
+            while i < bytes.len() && bytes[i].is_ascii_digit() { i += 1; }
+            let digits = version[start..i].trim_start_matches('0');
+            out.push(Segment::Num(digits));
+        } else if bytes[i].is_ascii_alphabetic() {
+            while i < bytes.len() && bytes[i].is_ascii_alphabetic() { i += 1; }
+            out.push(Segment::Alpha(&version[start..i]));
+        } else {
+            i += 1;
+        }
+    }
+    out
+}
@@ -0,0 +1,243 @@
+﻿//! Importador de benchmarks XCCDF (subconjunto) para o formato de politica SCA.
+//!
+//! Suporta regras com titulo, descricao, racional, severidade, idents/referencias, fix em shell
+//! e checks OVAL simples (ver `oval`). O que nao pode ser traduzido volta em `ImportReport::skipped`.
+
+use roxmltree::{Document, Node};
+use thiserror::Error;
+use crate::models::sca::{Applicability, Policy, Reference, Rule, Severity};
+
+mod oval;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Error, Debug)]
+pub enum XccdfError {
+    #[error("XML invalido: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("Documento nao contem um <Benchmark> XCCDF")]
+    NoBenchmark,
+}
+
+/// Opcoes da importacao
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub policy_id: Option<String>, // Padrao: id do Benchmark normalizado
+    pub first_rule_id: u32,        // Ids sao sequenciais na ordem do benchmark (inclusive regras ignoradas)
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self { policy_id: None, first_rule_id: 1 }
+    }
+}
+
+/// Resultado da importacao: politica gerada + regras que nao puderam ser traduzidas
+#[derive(Debug)]
+pub struct ImportReport {
+    pub policy: Policy,
+    pub total_rules: usize,
+    pub skipped: Vec<SkippedRule>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkippedRule {
+    pub xccdf_id: String,
+    pub title: String,
+    pub reason: String,
+}
+
+/// Converte um benchmark XCCDF. As definicoes OVAL podem estar embutidas no proprio documento
+/// (data stream) ou em documentos separados passados em `oval`.
+pub fn import(xccdf: &str, oval: &[&str], opts: &ImportOptions) -> Result<ImportReport, XccdfError> {
+    let doc = Document::parse(xccdf)?;
+    let oval_docs = oval.iter().map(|xml| Document::parse(xml)).collect::<Result<Vec<_>, _>>()?;
+
+    let benchmark = doc.descendants().find(|n| is(n, "Benchmark")).ok_or(XccdfError::NoBenchmark)?;
+    let index = oval::Index::new(std::iter::once(&doc).chain(oval_docs.iter()));
+    let benchmark_id = benchmark.attribute("id").unwrap_or("benchmark");
+    let is_cis = benchmark_id.contains("cisecurity");
+
+    let mut rules = Vec::new();
+    let mut skipped = Vec::new();
+    let xccdf_rules: Vec<Node> = benchmark.descendants().filter(|n| is(n, "Rule")).collect();
+
+    for (i, node) in xccdf_rules.iter().enumerate() {
+        let xccdf_id = node.attribute("id").unwrap_or_default().to_string();
+        let title = child_text(*node, "title").unwrap_or_else(|| xccdf_id.clone());
+
+        match translate_rule(*node, &index, is_cis) {
+            Ok(mut rule) => {
+                rule.id = opts.first_rule_id + i as u32;
+                rule.title = title;
+                rules.push(rule);
+            }
+            Err(reason) => skipped.push(SkippedRule { xccdf_id, title, reason }),
+        }
+    }
+
+    let policy = Policy {
+        id: opts.policy_id.clone().unwrap_or_else(|| policy_id(benchmark_id)),
+        name: child_text(benchmark, "title").unwrap_or_else(|| benchmark_id.to_string()),
+        description: child_text(benchmark, "description").unwrap_or_else(|| format!("Importado de {}", benchmark_id)),
+        applies_to: platform(benchmark),
+        rules,
+    };
+
+    Ok(ImportReport { policy, total_rules: xccdf_rules.len(), skipped })
+}
+
+/// `href` dos check-content-ref OVAL (documentos externos que a CLI precisa carregar)
+pub fn oval_hrefs(xccdf: &str) -> Result<Vec<String>, XccdfError> {
+    let doc = Document::parse(xccdf)?;
+    let mut hrefs: Vec<String> = doc.descendants()
+        .filter(|n| is(n, "check") && n.attribute("system").is_some_and(|s| s.contains("oval")))
+        .flat_map(|check| check.children().filter(|n| is(n, "check-content-ref")))
+        .filter_map(|r| r.attribute("href").map(str::to_string))
+        .collect();
+    hrefs.sort();
+    hrefs.dedup();
+    Ok(hrefs)
+}
+
+fn translate_rule(node: Node, index: &oval::Index, is_cis: bool) -> Result<Rule, String> {
+    if node.attribute("selected") == Some("false") {
+        return Err("desabilitada no benchmark (selected=false)".to_string());
+    }
+
+    let check = node.children().filter(|n| is(n, "check")).collect::<Vec<_>>();
+    if check.is_empty() {
+        return Err("regra sem <check>".to_string());
+    }
+    let Some(check) = check.iter().find(|c| c.attribute("system").is_some_and(|s| s.contains("oval"))) else {
+        let system = check[0].attribute("system").unwrap_or_default();
+        return Err(if system.contains("ocil") {
+            "verificacao manual (OCIL)".to_string()
+        } else {
+            format!("sistema de check nao suportado: {}", system)
+        });
+    };
+    let definition = check.children()
+        .find(|n| is(n, "check-content-ref"))
+        .and_then(|r| r.attribute("name"))
+        .ok_or("check OVAL sem check-content-ref/@name")?;
+
+    let condition = index.definition(definition)?;
+    // O id vem do arquivo do benchmark: sempre entre aspas, como os demais argumentos
+    let command = format!("echo {}; if {}; then echo result=ok; else echo result=fail; fi",
+        oval::quote(&format!("oval={}", definition)), condition);
+
+    let mut description = child_text(node, "description");
+    if let Some(fixtext) = child_text(node, "fixtext") {
+        description = Some(match description {
+            Some(d) => format!("{}\nCorrecao: {}", d, fixtext),
+            None => format!("Correcao: {}", fixtext),
+        });
+    }
+
+    // Somente fix em shell vira remediacao executavel; texto livre fica na descricao
+    let remediation = node.children()
+        .find(|n| is(n, "fix") && n.attribute("system").is_some_and(|s| s.contains("script:sh") || s.contains("bash")))
+        .and_then(|fix| fix.text())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    Ok(Rule {
+        id: 0,
+        title: String::new(),
+        description,
+        command,
+        expect: "result=ok".to_string(),
+        remediation,
+        severity: severity(node.attribute("severity")),
+        weight: None,
+        rationale: child_text(node, "rationale"),
+        references: references(node, is_cis),
+        tags: Vec::new(),
+        applies_to: None,
+    })
+}
+
+/// XCCDF nao tem "critical": info/low -> low, unknown -> medium
+fn severity(value: Option<&str>) -> Severity {
+    match value {
+        Some("info") | Some("low") => Severity::Low,
+        Some("high") => Severity::High,
+        _ => Severity::Medium,
+    }
+}
+
+fn references(node: Node, is_cis: bool) -> Vec<Reference> {
+    let xccdf_id = node.attribute("id").unwrap_or_default();
+    let mut refs = vec![Reference { framework: "XCCDF".to_string(), id: xccdf_id.to_string() }];
+
+    // CIS: "xccdf_org.cisecurity.benchmarks_rule_5.2.10_Ensure_..." -> CIS 5.2.10
+    if is_cis {
+        if let Some(number) = xccdf_id.split("_rule_").nth(1).and_then(|rest| rest.split('_').next()) {
+            if number.chars().all(|c| c.is_ascii_digit() || c == '.') && !number.is_empty() {
+                refs.push(Reference { framework: "CIS".to_string(), id: number.to_string() });
+            }
+        }
+    }
+
+    for n in node.children() {
+        let Some(id) = n.text().map(str::trim).filter(|t| !t.is_empty()) else { continue };
+        let framework = if is(&n, "ident") {
+            let system = n.attribute("system").unwrap_or_default();
+            if system.contains("cce") { "CCE".to_string() } else { system.to_string() }
+        } else if is(&n, "reference") {
+            let href = n.attribute("href").unwrap_or_default();
+            if href.contains("800-53") || id.contains("800-53") { "NIST-800-53".to_string() } else { "REF".to_string() }
+        } else {
+            continue;
+        };
+        let id = id.trim_start_matches("NIST SP 800-53").trim();
+        refs.push(Reference { framework, id: id.to_string() });
+    }
+    refs
+}
+
+/// Plataformas CPE do benchmark -> familia de SO
+fn platform(benchmark: Node) -> Option<Applicability> {
+    let cpes: Vec<String> = benchmark.children()
+        .filter(|n| is(n, "platform"))
+        .filter_map(|n| n.attribute("idref").map(str::to_lowercase))
+        .collect();
+
+    let mut os_family = Vec::new();
+    if cpes.iter().any(|c| c.contains("windows")) {
+        os_family.push("windows".to_string());
+    }
+    let linux = ["linux", "redhat", "debian", "ubuntu", "canonical", "centos", "suse", "rocky", "alma", "oracle"];
+    if cpes.iter().any(|c| linux.iter().any(|l| c.contains(l))) {
+        os_family.push("linux".to_string());
+    }
+    (!os_family.is_empty()).then(|| Applicability { os_family, ..Default::default() })
+}
+
+/// "xccdf_org.cisecurity.benchmarks_benchmark_2.0.0_CIS_Ubuntu" -> "cis_ubuntu_..." (somente [a-z0-9_])
+fn policy_id(benchmark_id: &str) -> String {
+    let id = benchmark_id.trim_start_matches("xccdf_").to_lowercase();
+    let mut out = String::new();
+    for c in id.chars() {
+        let c = if c.is_ascii_alphanumeric() { c } else { '_' };
+        if !(c == '_' && out.ends_with('_')) {
+            out.push(c);
+        }
+    }
+    out.trim_matches('_').to_string()
+}
+
+/// Compara pelo nome local (ignora namespace/prefixo)
+fn is(node: &Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name
+}
+
+/// Texto do primeiro filho com o nome, incluindo XHTML interno, com espacos normalizados
+fn child_text(node: Node, name: &str) -> Option<String> {
+    let child = node.children().find(|n| is(n, name))?;
+    let text = child.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect::<Vec<_>>().join(" ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
@@ -0,0 +1,239 @@
+﻿//! OVAL "lite": traduz definicoes OVAL simples em uma condicao de shell POSIX.
+//!
+//! Suportado: criteria AND/OR (com negate e extend_definition) e os testes
+//! file_test, textfilecontent54_test, dpkginfo_test, rpminfo_test (existencia) e sysctl_test (valor igual).
+//! Caminhos sempre sao prefixados por `$SCA_ROOT`, como nas politicas nativas.
+
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use super::is;
+
+const MAX_DEPTH: usize = 16;
+
+/// Elementos OVAL indexados por id ("oval:ns:tipo:n") de todos os documentos carregados
+pub struct Index<'a, 'input> {
+    nodes: HashMap<&'a str, Node<'a, 'input>>,
+}
+
+impl<'a, 'input: 'a> Index<'a, 'input> {
+    pub fn new(docs: impl Iterator<Item = &'a Document<'input>>) -> Self {
+        let mut nodes = HashMap::new();
+        for doc in docs {
+            for node in doc.descendants().filter(|n| n.is_element()) {
+                if let Some(id) = node.attribute("id").filter(|id| id.starts_with("oval:")) {
+                    nodes.insert(id, node);
+                }
+            }
+        }
+        Self { nodes }
+    }
+
+    /// Condicao de shell equivalente a definicao (sucesso = definicao verdadeira)
+    pub fn definition(&self, id: &str) -> Result<String, String> {
+        self.definition_at(id, 0)
+    }
+
+    fn definition_at(&self, id: &str, depth: usize) -> Result<String, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("extend_definition aninhado demais em {}", id));
+        }
+        let definition = self.get(id, "definition")?;
+        let criteria = definition.children().find(|n| is(n, "criteria"))
+            .ok_or_else(|| format!("definicao OVAL {} sem criteria", id))?;
+        Ok(negate(&criteria, self.criteria(criteria, depth)?))
+    }
+
+    fn criteria(&self, node: Node, depth: usize) -> Result<String, String> {
+        let operator = match node.attribute("operator").unwrap_or("AND") {
+            "AND" => " && ",
+            "OR" => " || ",
+            other => return Err(format!("operador de criteria nao suportado: {}", other)),
+        };
+
+        let mut parts = Vec::new();
+        for child in node.children().filter(|n| n.is_element()) {
+            let part = match child.tag_name().name() {
+                "criterion" => self.test(child.attribute("test_ref").ok_or("criterion sem test_ref")?)?,
+                "extend_definition" => {
+                    let id = child.attribute("definition_ref").ok_or("extend_definition sem definition_ref")?;
+                    self.definition_at(id, depth + 1)?
+                }
+                "criteria" if depth < MAX_DEPTH => self.criteria(child, depth + 1)?,
+                "criteria" => return Err("criteria aninhado demais".to_string()),
+                _ => continue,
+            };
+            parts.push(negate(&child, part));
+        }
+
+        if parts.is_empty() {
+            return Err("criteria vazio".to_string());
+        }
+        Ok(format!("{{ {}; }}", parts.join(operator)))
+    }
+
+    fn test(&self, id: &str) -> Result<String, String> {
+        let test = self.get(id, "_test")?;
+        let kind = test.tag_name().name();
+        let object = test.children().find(|n| is(n, "object")).and_then(|n| n.attribute("object_ref"))
+            .ok_or_else(|| format!("{} sem object_ref", id))?;
+        let object = self.get(object, "_object")?;
+        let state = match test.children().find(|n| is(n, "state")).and_then(|n| n.attribute("state_ref")) {
+            Some(state) => Some(self.get(state, "_state")?),
+            None => None,
+        };
+        let without_state = || match state {
+            Some(_) => Err(format!("estado (state) em {} nao suportado", kind)),
+            None => Ok(()),
+        };
+
+        let condition = match kind {
+            "file_test" => {
+                without_state()?;
+                format!("[ -e {} ]", file_path(object)?)
+            }
+            "textfilecontent54_test" => {
+                without_state()?;
+                let pattern = element(object, "pattern")?;
+                if pattern.attribute("operation").is_some_and(|op| op != "pattern match") {
+                    return Err("textfilecontent54 com operacao diferente de 'pattern match'".to_string());
+                }
+                let (pattern, ignore_case) = ere(value(pattern)?)?;
+                format!("grep -Eq{} {} {} 2>/dev/null", if ignore_case { "i" } else { "" }, quote(&pattern), file_path(object)?)
+            }
+            "dpkginfo_test" => {
+                without_state()?;
+                let name = value(element(object, "name")?)?;
+                format!(r#"dpkg-query --admindir="$SCA_ROOT/var/lib/dpkg" -W -f='${{Status}}' {} 2>/dev/null | grep -q 'ok installed'"#, quote(name))
+            }
+            "rpminfo_test" => {
+                without_state()?;
+                let name = value(element(object, "name")?)?;
+                format!(r#"rpm --root "${{SCA_ROOT:-/}}" -q {} >/dev/null 2>&1"#, quote(name))
+            }
+            "sysctl_test" => {
+                let name = value(element(object, "name")?)?;
+                let path = format!("\"$SCA_ROOT\"{}", quote(&format!("/proc/sys/{}", name.replace('.', "/"))));
+                match state {
+                    None => format!("[ -e {} ]", path),
+                    Some(state) => {
+                        let expected = element(state, "value")?;
+                        if expected.attribute("operation").is_some_and(|op| op != "equals") {
+                            return Err("sysctl_test com operacao diferente de 'equals'".to_string());
+                        }
+                        format!("[ \"$(cat {} 2>/dev/null)\" = {} ]", path, quote(value(expected)?))
+                    }
+                }
+            }
+            other => return Err(format!("teste OVAL nao suportado: {}", other)),
+        };
+
+        // check_existence="none_exist": o teste passa quando o objeto NAO existe
+        Ok(match test.attribute("check_existence") {
+            Some("none_exist") => format!("! {{ {}; }}", condition),
+            _ => condition,
+        })
+    }
+
+    fn get(&self, id: &str, kind: &str) -> Result<Node<'a, 'input>, String> {
+        self.nodes.get(id)
+            .filter(|n| n.tag_name().name().ends_with(kind))
+            .copied()
+            .ok_or_else(|| format!("elemento OVAL {} nao encontrado", id))
+    }
+}
+
+fn negate(node: &Node, condition: String) -> String {
+    if node.attribute("negate") == Some("true") {
+        format!("! {{ {}; }}", condition)
+    } else {
+        condition
+    }
+}
+
+fn element<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Result<Node<'a, 'input>, String> {
+    node.children().find(|n| is(n, name)).ok_or_else(|| format!("objeto OVAL sem <{}>", name))
+}
+
+fn value<'a>(node: Node<'a, '_>) -> Result<&'a str, String> {
+    if node.attribute("var_ref").is_some() {
+        return Err("variaveis OVAL (var_ref) nao suportadas".to_string());
+    }
+    node.text().map(str::trim).filter(|t| !t.is_empty())
+        .ok_or_else(|| format!("<{}> vazio", node.tag_name().name()))
+}
+
+/// filepath ou path + filename, sempre relativo a $SCA_ROOT
+fn file_path(object: Node) -> Result<String, String> {
+    let literal = |n: Node| match n.attribute("operation") {
+        Some(op) if op != "equals" => Err(format!("<{}> com operacao '{}' nao suportada", n.tag_name().name(), op)),
+        _ => value(n).map(str::to_string),
+    };
+    let path = match object.children().find(|n| is(n, "filepath")) {
+        Some(filepath) => literal(filepath)?,
+        None => {
+            let dir = literal(element(object, "path")?)?;
+            let file = literal(element(object, "filename")?)?;
+            format!("{}/{}", dir.trim_end_matches('/'), file)
+        }
+    };
+    Ok(format!("\"$SCA_ROOT\"{}", quote(&path)))
+}
+
+/// Regex Perl do OVAL -> ERE do grep. Retorna (padrao, ignore_case).
+pub(super) fn ere(pattern: &str) -> Result<(String, bool), String> {
+    let (pattern, ignore_case) = match pattern.strip_prefix("(?i)") {
+        Some(rest) => (rest, true),
+        None => (pattern, false),
+    };
+    if ["(?", "*?", "+?", "??"].iter().any(|p| pattern.contains(p)) {
+        return Err(format!("regex OVAL sem equivalente em ERE: {}", pattern));
+    }
+
+    let mut out = String::new();
+    let mut in_bracket = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let Some(next) = chars.next() else {
+                    out.push('\\');
+                    break;
+                };
+                match (next, in_bracket) {
+                    ('s', false) => out.push_str("[[:space:]]"),
+                    ('S', false) => out.push_str("[^[:space:]]"),
+                    ('d', false) => out.push_str("[0-9]"),
+                    ('D', false) => out.push_str("[^0-9]"),
+                    ('s', true) => out.push_str("[:space:]"),
+                    ('d', true) => out.push_str("0-9"),
+                    ('S' | 'D', true) => return Err(format!("classe negada dentro de [] nao suportada: {}", pattern)),
+                    _ => {
+                        out.push('\\');
+                        out.push(next);
+                    }
+                }
+            }
+            '[' if !in_bracket => {
+                in_bracket = true;
+                out.push(c);
+                // "]" logo apos "[" ou "[^" eh literal
+                let rest = chars.as_str();
+                let lead = if rest.starts_with("^]") { 2 } else if rest.starts_with('^') || rest.starts_with(']') { 1 } else { 0 };
+                for _ in 0..lead {
+                    out.extend(chars.next());
+                }
+            }
+            ']' if in_bracket => {
+                in_bracket = false;
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    Ok((out, ignore_case))
+}
+
+/// Aspas simples de shell
+pub(super) fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
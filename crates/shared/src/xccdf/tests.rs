@@ -0,0 +1,130 @@
+﻿use crate::models::sca::Severity;
+use super::{import, oval, policy_id, ImportOptions};
+
+/// Benchmark com as definicoes OVAL embutidas (como num data stream)
+fn benchmark(id: &str, rules: &str, definitions: &str) -> String {
+    format!(r#"<?xml version="1.0"?>
+<ds xmlns:x="http://checklists.nist.gov/xccdf/1.2" xmlns:o="http://oval.mitre.org/XMLSchema/oval-definitions-5">
+  <x:Benchmark id="{id}">
+    <x:title>Benchmark de teste</x:title>
+    <x:platform idref="cpe:/o:canonical:ubuntu_linux:22.04"/>
+    {rules}
+  </x:Benchmark>
+  <o:oval_definitions>
+    <o:definitions>{definitions}</o:definitions>
+    <o:tests>
+      <ind:file_test xmlns:ind="x" id="oval:t:tst:1"><ind:object object_ref="oval:t:obj:1"/></ind:file_test>
+      <ind:file_test xmlns:ind="x" id="oval:t:tst:2"><ind:object object_ref="oval:t:obj:2"/></ind:file_test>
+      <ind:textfilecontent54_test xmlns:ind="x" id="oval:t:tst:3"><ind:object object_ref="oval:t:obj:3"/></ind:textfilecontent54_test>
+    </o:tests>
+    <o:objects>
+      <ind:file_object xmlns:ind="x" id="oval:t:obj:1"><ind:filepath>/etc/a</ind:filepath></ind:file_object>
+      <ind:file_object xmlns:ind="x" id="oval:t:obj:2"><ind:path>/etc/</ind:path><ind:filename>it's</ind:filename></ind:file_object>
+      <ind:textfilecontent54_object xmlns:ind="x" id="oval:t:obj:3">
+        <ind:filepath>/etc/ssh/sshd_config</ind:filepath>
+        <ind:pattern operation="pattern match">(?i)^\s*PermitRootLogin\s+no</ind:pattern>
+      </ind:textfilecontent54_object>
+    </o:objects>
+  </o:oval_definitions>
+</ds>"#)
+}
+
+fn rule(id: &str, definition: &str, extra: &str) -> String {
+    format!(r#"<x:Rule id="{id}" severity="high"><x:title>Regra {id}</x:title>{extra}
+      <x:check system="http://oval.mitre.org/XMLSchema/oval-definitions-5"><x:check-content-ref name="{definition}"/></x:check></x:Rule>"#)
+}
+
+fn definition(id: &str, criteria: &str) -> String {
+    format!(r#"<o:definition id="{id}">{criteria}</o:definition>"#)
+}
+
+fn command(criteria: &str) -> String {
+    let xml = benchmark("xccdf_test_benchmark_b", &rule("r1", "oval:t:def:1", ""), &definition("oval:t:def:1", criteria));
+    let report = import(&xml, &[], &ImportOptions::default()).unwrap();
+    assert!(report.skipped.is_empty(), "{:?}", report.skipped);
+    report.policy.rules[0].command.clone()
+}
+
+#[test]
+fn quote_escapes_single_quotes() {
+    assert_eq!(oval::quote("abc"), "'abc'");
+    assert_eq!(oval::quote("a'b"), r"'a'\''b'");
+    assert_eq!(oval::quote("'; rm -rf / #"), r"''\''; rm -rf / #'");
+}
+
+#[test]
+fn criteria_operators_and_negate() {
+    let and = command(r#"<o:criteria><o:criterion test_ref="oval:t:tst:1"/><o:criterion test_ref="oval:t:tst:2"/></o:criteria>"#);
+    assert!(and.contains(r#"if { [ -e "$SCA_ROOT"'/etc/a' ] && [ -e "$SCA_ROOT"'/etc/it'\''s' ]; }; then"#), "{}", and);
+
+    let or = command(r#"<o:criteria operator="OR"><o:criterion test_ref="oval:t:tst:1"/><o:criterion test_ref="oval:t:tst:2" negate="true"/></o:criteria>"#);
+    assert!(or.contains(r#"{ [ -e "$SCA_ROOT"'/etc/a' ] || ! { [ -e "$SCA_ROOT"'/etc/it'\''s' ]; }; }"#), "{}", or);
+
+    let negated = command(r#"<o:criteria negate="true"><o:criterion test_ref="oval:t:tst:1"/></o:criteria>"#);
+    assert!(negated.contains(r#"if ! { { [ -e "$SCA_ROOT"'/etc/a' ]; }; }; then"#), "{}", negated);
+}
+
+#[test]
+fn text_content_pattern_becomes_case_insensitive_ere() {
+    let cmd = command(r#"<o:criteria><o:criterion test_ref="oval:t:tst:3"/></o:criteria>"#);
+    assert!(cmd.contains(r#"grep -Eqi '^[[:space:]]*PermitRootLogin[[:space:]]+no' "$SCA_ROOT"'/etc/ssh/sshd_config'"#), "{}", cmd);
+}
+
+#[test]
+fn unsupported_criteria_is_skipped() {
+    let xml = benchmark("b", &rule("r1", "oval:t:def:1", ""),
+        &definition("oval:t:def:1", r#"<o:criteria operator="XOR"><o:criterion test_ref="oval:t:tst:1"/></o:criteria>"#));
+    let report = import(&xml, &[], &ImportOptions::default()).unwrap();
+    assert!(report.policy.rules.is_empty());
+    assert!(report.skipped[0].reason.contains("XOR"));
+}
+
+#[test]
+fn definition_name_cannot_break_out_of_the_command() {
+    let hostile = "oval:t:def:1'; touch /tmp/pwned; echo '";
+    let xml = benchmark("b", &rule("r1", hostile, ""),
+        &definition(hostile, r#"<o:criteria><o:criterion test_ref="oval:t:tst:1"/></o:criteria>"#));
+    let report = import(&xml, &[], &ImportOptions::default()).unwrap();
+    let cmd = &report.policy.rules[0].command;
+    assert!(cmd.starts_with(r"echo 'oval=oval:t:def:1'\''; touch /tmp/pwned; echo '\'''; if "), "{}", cmd);
+}
+
+#[test]
+fn ere_translation() {
+    assert_eq!(oval::ere(r"^\s*\d+\S").unwrap(), (r"^[[:space:]]*[0-9]+[^[:space:]]".to_string(), false));
+    assert_eq!(oval::ere(r"[\s\d]").unwrap(), ("[[:space:]0-9]".to_string(), false));
+    assert_eq!(oval::ere(r"[]a]\.").unwrap(), (r"[]a]\.".to_string(), false));
+    assert_eq!(oval::ere("(?i)yes").unwrap(), ("yes".to_string(), true));
+    assert!(oval::ere("a+?").is_err());
+    assert!(oval::ere("(?=x)").is_err());
+    assert!(oval::ere(r"[\S]").is_err());
+}
+
+#[test]
+fn policy_id_is_normalized() {
+    assert_eq!(policy_id("xccdf_org.cisecurity.benchmarks_benchmark_2.0.0_CIS_Ubuntu"), "org_cisecurity_benchmarks_benchmark_2_0_0_cis_ubuntu");
+    assert_eq!(policy_id("--Weird  Id--"), "weird_id");
+}
+
+#[test]
+fn references_platform_and_metadata() {
+    let rule_id = "xccdf_org.cisecurity.benchmarks_rule_5.2.10_Ensure_SSH_root_login_is_disabled";
+    let extra = r#"<x:ident system="https://nvd.nist.gov/cce/index.cfm">CCE-1234-5</x:ident>
+      <x:reference href="https://csrc.nist.gov/publications/detail/sp/800-53/rev-5/final">NIST SP 800-53 AC-6</x:reference>
+      <x:fix system="urn:xccdf:fix:script:sh">sed -i 's/yes/no/' /etc/ssh/sshd_config</x:fix>"#;
+    let xml = benchmark("xccdf_org.cisecurity.benchmarks_benchmark_1.0.0_CIS", &rule(rule_id, "oval:t:def:1", extra),
+        &definition("oval:t:def:1", r#"<o:criteria><o:criterion test_ref="oval:t:tst:1"/></o:criteria>"#));
+    let opts = ImportOptions { policy_id: None, first_rule_id: 100 };
+    let report = import(&xml, &[], &opts).unwrap();
+    let policy = &report.policy;
+
+    assert_eq!(policy.id, "org_cisecurity_benchmarks_benchmark_1_0_0_cis");
+    assert_eq!(policy.applies_to.as_ref().unwrap().os_family, vec!["linux".to_string()]);
+    let rule = &policy.rules[0];
+    assert_eq!(rule.id, 100);
+    assert_eq!(rule.severity, Severity::High);
+    assert_eq!(rule.remediation.as_deref(), Some("sed -i 's/yes/no/' /etc/ssh/sshd_config"));
+
+    let refs: Vec<(&str, &str)> = rule.references.iter().map(|r| (r.framework.as_str(), r.id.as_str())).collect();
+    assert_eq!(refs, vec![("XCCDF", rule_id), ("CIS", "5.2.10"), ("CCE", "CCE-1234-5"), ("NIST-800-53", "AC-6")]);
+}